@@ -0,0 +1,121 @@
+use crate::llm::cancel;
+use crate::llm::provider::{ChatProvider, ChatRequest, ChatResponse, CANCELLED};
+use crate::llm::usage::{Metered, UsageContext};
+use crate::llm::{self, openai, AiConfig};
+use crate::commands::book;
+use crate::db;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+
+/// 流式输出事件（事件名 `ai-stream`）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AiStreamEvent {
+    pub request_id: String,
+    /// delta / done / cancelled / error
+    pub kind: String,
+    /// delta：增量文本；done：完整回复；error：错误信息
+    pub text: String,
+}
+
+impl AiStreamEvent {
+    pub fn new(request_id: &str, kind: &str, text: impl Into<String>) -> Self {
+        Self {
+            request_id: request_id.to_string(),
+            kind: kind.to_string(),
+            text: text.into(),
+        }
+    }
+}
+
+/// 一次性对话请求
+#[tauri::command]
+pub async fn ai_chat(app: AppHandle, request_id: String, request: ChatRequest) -> Result<ChatResponse, String> {
+    let provider = metered_provider(&app, None, "chat")?;
+    let registration = cancel::register(&request_id);
+    provider.chat(&request, &registration.token).await
+}
+
+/// 流式对话：增量文本通过 `ai-stream` 事件推送，返回值为完整回复
+///
+/// 可随时用 `cancel_ai_request` 取消，取消后推送 kind = cancelled 的事件。
+#[tauri::command]
+pub async fn ai_chat_stream(app: AppHandle, request_id: String, request: ChatRequest) -> Result<ChatResponse, String> {
+    let provider = metered_provider(&app, None, "chat")?;
+    stream_to_events(&app, &provider, &request_id, &request).await
+}
+
+/// 取消进行中的 AI 请求；请求已结束时返回 false
+#[tauri::command]
+pub async fn cancel_ai_request(request_id: String) -> Result<bool, String> {
+    Ok(cancel::cancel(&request_id))
+}
+
+/// 获取接口提供的模型列表（也用于设置页测试连接）
+#[tauri::command]
+pub async fn list_ai_models() -> Result<Vec<String>, String> {
+    let conn = db::init_global_db()?;
+    let config = AiConfig::load(&conn)?;
+    openai::OpenAiCompatible::new(config)?.list_models().await
+}
+
+/// 按当前设置创建服务实例，调用记入用量（`storage_path` 为 None 表示不属于某本书）
+pub(crate) fn metered_provider(
+    app: &AppHandle,
+    storage_path: Option<&str>,
+    feature: &str,
+) -> Result<Metered<openai::OpenAiCompatible>, String> {
+    Ok(Metered::new(llm::configured_provider()?, usage_context(app, storage_path, feature)?))
+}
+
+/// 调用归属；预算超出（warn）时推送 `ai-budget-warning` 事件
+pub(crate) fn usage_context(app: &AppHandle, storage_path: Option<&str>, feature: &str) -> Result<UsageContext, String> {
+    let book_id = match storage_path {
+        Some(sp) => book::book_id_for(&db::open_global_db()?, sp)?,
+        None => None,
+    };
+    let emitter = app.clone();
+    Ok(UsageContext {
+        on_warning: Some(Arc::new(move |budget| {
+            let _ = emitter.emit("ai-budget-warning", budget.clone());
+        })),
+        ..UsageContext::new(book_id, feature)
+    })
+}
+
+/// 执行流式请求并把过程转成 `ai-stream` 事件
+pub(crate) async fn stream_to_events(
+    app: &AppHandle,
+    provider: &dyn ChatProvider,
+    request_id: &str,
+    request: &ChatRequest,
+) -> Result<ChatResponse, String> {
+    let emitter = app.clone();
+    let mut on_event = move |event: AiStreamEvent| {
+        let _ = emitter.emit("ai-stream", event);
+    };
+    stream_with(provider, request_id, request, &mut on_event).await
+}
+
+/// 执行流式请求：每段增量推送 delta，结束时推送 done / cancelled / error
+pub(crate) async fn stream_with(
+    provider: &dyn ChatProvider,
+    request_id: &str,
+    request: &ChatRequest,
+    on_event: &mut (dyn FnMut(AiStreamEvent) + Send),
+) -> Result<ChatResponse, String> {
+    let registration = cancel::register(request_id);
+
+    let result = {
+        let mut on_delta = |delta: &str| on_event(AiStreamEvent::new(request_id, "delta", delta));
+        provider.chat_stream(request, &registration.token, &mut on_delta).await
+    };
+
+    let event = match &result {
+        Ok(resp) => AiStreamEvent::new(request_id, "done", resp.content.clone()),
+        Err(e) if e == CANCELLED => AiStreamEvent::new(request_id, "cancelled", ""),
+        Err(e) => AiStreamEvent::new(request_id, "error", e.clone()),
+    };
+    on_event(event);
+
+    result
+}
@@ -0,0 +1,241 @@
+use crate::db;
+use chrono::{Duration, NaiveDate};
+use rusqlite::params;
+use std::collections::HashMap;
+
+/// 日期格式：YYYY-MM-DD
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// 热力图分级数（0 为未写作，1-4 按当段最高字数等分）
+const HEATMAP_LEVELS: i64 = 4;
+
+/// 写作分析结果（指定日期范围，连续天数按全部历史计算）
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WritingAnalytics {
+    /// 截至今天连续达成目标的天数（今天尚未达成时从昨天算起）
+    pub current_streak: i64,
+    pub longest_streak: i64,
+    pub total_words: i64,
+    pub total_duration_seconds: i64,
+    /// 每小时写作时长产出的字数，范围内没有时长记录时为 None
+    pub words_per_hour: Option<f64>,
+    /// 截至范围最后一天的 7 日 / 30 日日均字数
+    pub avg_7d: f64,
+    pub avg_30d: f64,
+    /// 范围内每天一项，无记录的日期字数为 0
+    pub heatmap: Vec<HeatmapDay>,
+    pub rolling: Vec<RollingAverage>,
+    /// 0-23 时各一项
+    pub by_hour: Vec<HourBucket>,
+}
+
+/// 热力图中的一天
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HeatmapDay {
+    pub date: String,
+    pub word_count: i64,
+    /// 当天适用的目标字数，0 为未设目标
+    pub goal: i64,
+    pub goal_met: bool,
+    /// 0-4
+    pub level: i64,
+}
+
+/// 某天的滚动平均字数
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RollingAverage {
+    pub date: String,
+    pub avg_7d: f64,
+    pub avg_30d: f64,
+}
+
+/// 一天中某个小时的写作量（本地时间）
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HourBucket {
+    pub hour: i64,
+    pub word_count: i64,
+    pub duration_seconds: i64,
+}
+
+/// 一天的统计：字数、时长、目标
+struct Day {
+    words: i64,
+    seconds: i64,
+    goal: i64,
+}
+
+impl Day {
+    /// 设了目标时需达到目标，未设目标时有写作即算达成
+    fn goal_met(&self) -> bool {
+        if self.goal > 0 {
+            self.words >= self.goal
+        } else {
+            self.words > 0
+        }
+    }
+}
+
+/// 获取写作分析：连续达成天数、热力图、时速、滚动平均、按小时分布
+#[tauri::command]
+pub async fn get_writing_analytics(start_date: String, end_date: String) -> Result<WritingAnalytics, String> {
+    let start = parse_date(&start_date)?;
+    let end = parse_date(&end_date)?;
+    if start > end {
+        return Err("开始日期不能晚于结束日期".into());
+    }
+
+    let conn = db::init_global_db()?;
+    let days = load_days(&conn)?;
+    let today = chrono::Local::now().date_naive();
+    let (current_streak, longest_streak) = streaks(&days, today);
+
+    let words_on = |date: NaiveDate| days.get(&date).map_or(0, |d| d.words);
+    let average = |date: NaiveDate, span: i64| {
+        (0..span).map(|i| words_on(date - Duration::days(i))).sum::<i64>() as f64 / span as f64
+    };
+
+    let range: Vec<NaiveDate> = start.iter_days().take_while(|d| *d <= end).collect();
+    let max_words = range.iter().map(|d| words_on(*d)).max().unwrap_or(0);
+    let mut analytics = WritingAnalytics {
+        current_streak,
+        longest_streak,
+        total_words: 0,
+        total_duration_seconds: 0,
+        words_per_hour: None,
+        avg_7d: average(end, 7),
+        avg_30d: average(end, 30),
+        heatmap: Vec::with_capacity(range.len()),
+        rolling: Vec::with_capacity(range.len()),
+        by_hour: load_hours(&conn, &start_date, &end_date)?,
+    };
+    for date in range {
+        let label = date.format(DATE_FORMAT).to_string();
+        let (words, seconds, goal, goal_met) = match days.get(&date) {
+            Some(d) => (d.words, d.seconds, d.goal, d.goal_met()),
+            None => (0, 0, 0, false),
+        };
+        analytics.total_words += words;
+        analytics.total_duration_seconds += seconds;
+        analytics.heatmap.push(HeatmapDay {
+            date: label.clone(),
+            word_count: words,
+            goal,
+            goal_met,
+            level: heatmap_level(words, max_words),
+        });
+        analytics.rolling.push(RollingAverage {
+            date: label,
+            avg_7d: average(date, 7),
+            avg_30d: average(date, 30),
+        });
+    }
+    if analytics.total_duration_seconds > 0 {
+        analytics.words_per_hour =
+            Some(analytics.total_words as f64 * 3600.0 / analytics.total_duration_seconds as f64);
+    }
+    Ok(analytics)
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, DATE_FORMAT).map_err(|_| format!("日期格式无效: {}", value))
+}
+
+/// 读取全部每日统计（目标为当天记录的目标）
+fn load_days(conn: &rusqlite::Connection) -> Result<HashMap<NaiveDate, Day>, String> {
+    let mut stmt = conn
+        .prepare("SELECT date, word_count, duration_seconds, daily_goal FROM daily_stats")
+        .map_err(|e| format!("查询统计失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })
+        .map_err(|e| format!("读取统计失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析统计失败: {}", e))?;
+
+    let mut days = HashMap::new();
+    for (date, words, seconds, goal) in rows {
+        let Ok(date) = NaiveDate::parse_from_str(&date, DATE_FORMAT) else {
+            continue;
+        };
+        days.insert(date, Day { words, seconds, goal });
+    }
+    Ok(days)
+}
+
+/// 当前与最长连续达成天数
+fn streaks(days: &HashMap<NaiveDate, Day>, today: NaiveDate) -> (i64, i64) {
+    let met = |date: NaiveDate| days.get(&date).is_some_and(Day::goal_met);
+
+    let mut met_dates: Vec<NaiveDate> = days.keys().copied().filter(|d| met(*d)).collect();
+    met_dates.sort();
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for date in met_dates {
+        run = match previous {
+            Some(p) if date - p == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(date);
+    }
+
+    // 今天还没写完不算中断
+    let mut cursor = if met(today) { today } else { today - Duration::days(1) };
+    let mut current = 0;
+    while met(cursor) {
+        current += 1;
+        cursor -= Duration::days(1);
+    }
+    (current, longest)
+}
+
+/// 按当段最高字数等分为 1-4 级
+fn heatmap_level(words: i64, max_words: i64) -> i64 {
+    if words <= 0 || max_words <= 0 {
+        return 0;
+    }
+    ((words * HEATMAP_LEVELS + max_words - 1) / max_words).clamp(1, HEATMAP_LEVELS)
+}
+
+/// 范围内 0-23 时各时段的字数与时长合计
+fn load_hours(conn: &rusqlite::Connection, start_date: &str, end_date: &str) -> Result<Vec<HourBucket>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT hour, SUM(word_count), SUM(duration_seconds) FROM hourly_stats
+             WHERE date >= ?1 AND date <= ?2 GROUP BY hour",
+        )
+        .map_err(|e| format!("查询按小时统计失败: {}", e))?;
+    let rows = stmt
+        .query_map(params![start_date, end_date], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?))
+        })
+        .map_err(|e| format!("读取按小时统计失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析按小时统计失败: {}", e))?;
+
+    let mut buckets: Vec<HourBucket> = (0..24)
+        .map(|hour| HourBucket {
+            hour,
+            word_count: 0,
+            duration_seconds: 0,
+        })
+        .collect();
+    for (hour, words, seconds) in rows {
+        if let Some(bucket) = buckets.get_mut(hour as usize) {
+            bucket.word_count = words;
+            bucket.duration_seconds = seconds;
+        }
+    }
+    Ok(buckets)
+}
@@ -0,0 +1,465 @@
+use crate::commands::{ai, chapter};
+use crate::db;
+use crate::db::config;
+use crate::db::models::RagArc;
+use crate::llm::cancel::{self, CancelToken};
+use crate::llm::provider::{ChatProvider, CANCELLED};
+use crate::llm::summary;
+use rusqlite::params;
+use std::collections::HashMap;
+use tauri::AppHandle;
+
+fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
+    let cfg = config::load_config()?;
+    db::open_book_db(&cfg, storage_path)
+}
+
+/// 默认每段剧情弧的章节数
+const DEFAULT_ARC_SIZE: usize = 10;
+
+/// 批量生成概要的结果
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ArcBatchResult {
+    pub updated: Vec<RagArc>,
+    /// (剧情弧 ID, 错误信息)
+    pub failed: Vec<(String, String)>,
+    pub cancelled: bool,
+}
+
+/// 获取全部剧情弧（按起始章节的阅读顺序）
+#[tauri::command]
+pub async fn list_arcs(storage_path: String) -> Result<Vec<RagArc>, String> {
+    let conn = open_book(&storage_path)?;
+    let order = chapter::reading_order(&conn)?;
+    let position = positions(&order);
+
+    let mut arcs = load_arcs(&conn)?;
+    arcs.sort_by_key(|a| position.get(a.start_chapter_id.as_str()).copied().unwrap_or(usize::MAX));
+    Ok(arcs)
+}
+
+/// 手动创建剧情弧（范围不能与已有剧情弧重叠）
+#[tauri::command]
+pub async fn create_arc(
+    storage_path: String,
+    start_chapter_id: String,
+    end_chapter_id: String,
+    summary: Option<String>,
+) -> Result<RagArc, String> {
+    let conn = open_book(&storage_path)?;
+    let order = chapter::reading_order(&conn)?;
+    check_range(&conn, &order, &start_chapter_id, &end_chapter_id, None)?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+    let stale = summary.is_none();
+    let summary = summary.unwrap_or_default();
+
+    conn.execute(
+        "INSERT INTO rag_arcs (id, start_chapter_id, end_chapter_id, summary, stale, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![id, start_chapter_id, end_chapter_id, summary, stale, now, now],
+    )
+    .map_err(|e| format!("创建剧情弧失败: {}", e))?;
+
+    Ok(RagArc {
+        id,
+        start_chapter_id,
+        end_chapter_id,
+        summary,
+        stale,
+        created_at: now.clone(),
+        updated_at: now,
+    })
+}
+
+/// 修改剧情弧范围或概要
+///
+/// 手动填写概要后视为最新；只改范围不改概要时标记为待重新生成。
+#[tauri::command]
+pub async fn update_arc(
+    storage_path: String,
+    id: String,
+    start_chapter_id: Option<String>,
+    end_chapter_id: Option<String>,
+    summary: Option<String>,
+) -> Result<RagArc, String> {
+    let conn = open_book(&storage_path)?;
+    let mut arc = load_arc(&conn, &id)?;
+
+    let range_changed = start_chapter_id.as_ref().is_some_and(|s| *s != arc.start_chapter_id)
+        || end_chapter_id.as_ref().is_some_and(|e| *e != arc.end_chapter_id);
+    if range_changed {
+        let start = start_chapter_id.unwrap_or(arc.start_chapter_id);
+        let end = end_chapter_id.unwrap_or(arc.end_chapter_id);
+        let order = chapter::reading_order(&conn)?;
+        check_range(&conn, &order, &start, &end, Some(&id))?;
+        arc.start_chapter_id = start;
+        arc.end_chapter_id = end;
+        arc.stale = true;
+    }
+    if let Some(summary) = summary {
+        arc.summary = summary;
+        arc.stale = false;
+    }
+    arc.updated_at = chrono::Utc::now().to_rfc3339();
+
+    conn.execute(
+        "UPDATE rag_arcs SET start_chapter_id = ?1, end_chapter_id = ?2, summary = ?3, stale = ?4, updated_at = ?5
+         WHERE id = ?6",
+        params![arc.start_chapter_id, arc.end_chapter_id, arc.summary, arc.stale, arc.updated_at, id],
+    )
+    .map_err(|e| format!("更新剧情弧失败: {}", e))?;
+
+    Ok(arc)
+}
+
+/// 删除剧情弧（概要可重新生成，不进回收站）
+#[tauri::command]
+pub async fn delete_arc(storage_path: String, id: String) -> Result<(), String> {
+    let conn = open_book(&storage_path)?;
+    conn.execute("DELETE FROM rag_arcs WHERE id = ?1", params![id])
+        .map_err(|e| format!("删除剧情弧失败: {}", e))?;
+    Ok(())
+}
+
+/// 按阅读顺序把全书切分为每 `arc_size`（默认 10）章一段
+///
+/// 与切分结果范围完全一致的已有剧情弧保留（含概要），其余删除后重建，
+/// 新建的剧情弧概要为空、标记为待生成。
+#[tauri::command]
+pub async fn segment_arcs(storage_path: String, arc_size: Option<usize>) -> Result<Vec<RagArc>, String> {
+    let mut conn = open_book(&storage_path)?;
+    let size = arc_size.filter(|&n| n > 0).unwrap_or(DEFAULT_ARC_SIZE);
+    let order = chapter::reading_order(&conn)?;
+    let existing = load_arcs(&conn)?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+    let mut kept = Vec::new();
+    for chunk in order.chunks(size) {
+        let (start, end) = (&chunk[0], &chunk[chunk.len() - 1]);
+        match existing.iter().find(|a| a.start_chapter_id == *start && a.end_chapter_id == *end) {
+            Some(arc) => kept.push(arc.id.clone()),
+            None => {
+                let id = uuid::Uuid::new_v4().to_string();
+                tx.execute(
+                    "INSERT INTO rag_arcs (id, start_chapter_id, end_chapter_id, summary, stale, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, '', 1, ?4, ?5)",
+                    params![id, start, end, now, now],
+                )
+                .map_err(|e| format!("创建剧情弧失败: {}", e))?;
+                kept.push(id);
+            }
+        }
+    }
+    for arc in existing.iter().filter(|a| !kept.contains(&a.id)) {
+        tx.execute("DELETE FROM rag_arcs WHERE id = ?1", params![arc.id])
+            .map_err(|e| format!("删除剧情弧失败: {}", e))?;
+    }
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+
+    list_arcs(storage_path).await
+}
+
+/// 获取覆盖某章节的剧情弧
+#[tauri::command]
+pub async fn get_arc_for_chapter(storage_path: String, chapter_id: String) -> Result<Option<RagArc>, String> {
+    let conn = open_book(&storage_path)?;
+    let order = chapter::reading_order(&conn)?;
+    let position = positions(&order);
+    let Some(&pos) = position.get(chapter_id.as_str()) else {
+        return Err("章节不存在".into());
+    };
+
+    Ok(load_arcs(&conn)?
+        .into_iter()
+        .find(|arc| span(&position, arc).is_some_and(|(s, e)| s <= pos && pos <= e)))
+}
+
+/// 根据弧内各章 L2 摘要生成剧情弧概要
+#[tauri::command]
+pub async fn generate_arc_summary(
+    app: AppHandle,
+    storage_path: String,
+    request_id: String,
+    id: String,
+) -> Result<RagArc, String> {
+    let provider = ai::metered_provider(&app, Some(&storage_path), "arc_summary")?;
+    let registration = cancel::register(&request_id);
+    summarize_arc(&provider, &storage_path, &id, &registration.token).await
+}
+
+/// 为全部待生成（stale）的剧情弧生成概要；单个失败不影响其余
+#[tauri::command]
+pub async fn summarize_stale_arcs(
+    app: AppHandle,
+    storage_path: String,
+    request_id: String,
+) -> Result<ArcBatchResult, String> {
+    let provider = ai::metered_provider(&app, Some(&storage_path), "arc_summary")?;
+    let registration = cancel::register(&request_id);
+    summarize_stale(&provider, &storage_path, &registration.token).await
+}
+
+/// 生成单个剧情弧概要并写回
+///
+/// 弧内任一章节缺少 L2 摘要时报错。生成期间剧情弧被再次置为失效的，
+/// 仍写入概要但保持 stale。
+pub async fn summarize_arc(
+    provider: &dyn ChatProvider,
+    storage_path: &str,
+    id: &str,
+    cancel: &CancelToken,
+) -> Result<RagArc, String> {
+    let (arc, chapters) = {
+        let conn = open_book(storage_path)?;
+        let arc = load_arc(&conn, id)?;
+        let chapters = member_summaries(&conn, &arc)?;
+        (arc, chapters)
+    };
+
+    let text = summary::generate_arc_summary(provider, &chapters, cancel).await?;
+
+    let conn = open_book(storage_path)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE rag_arcs SET summary = ?1, stale = CASE WHEN updated_at = ?2 THEN 0 ELSE stale END, updated_at = ?3
+         WHERE id = ?4",
+        params![text, arc.updated_at, now, id],
+    )
+    .map_err(|e| format!("保存剧情弧概要失败: {}", e))?;
+
+    load_arc(&conn, id)
+}
+
+/// 依次生成全部 stale 剧情弧的概要
+pub async fn summarize_stale(
+    provider: &dyn ChatProvider,
+    storage_path: &str,
+    cancel: &CancelToken,
+) -> Result<ArcBatchResult, String> {
+    let stale: Vec<String> = list_arcs(storage_path.to_string())
+        .await?
+        .into_iter()
+        .filter(|a| a.stale)
+        .map(|a| a.id)
+        .collect();
+
+    let mut result = ArcBatchResult::default();
+    for id in stale {
+        if cancel.is_cancelled() {
+            result.cancelled = true;
+            break;
+        }
+        match summarize_arc(provider, storage_path, &id, cancel).await {
+            Ok(arc) => result.updated.push(arc),
+            Err(e) if e == CANCELLED => {
+                result.cancelled = true;
+                break;
+            }
+            Err(e) => result.failed.push((id, e)),
+        }
+    }
+    Ok(result)
+}
+
+// ============================================================================
+// 失效处理（由章节编辑、排序、删除等命令调用）
+// ============================================================================
+
+/// 将覆盖该章节的剧情弧标记为失效（章节内容被编辑或新增到范围内时）
+pub(crate) fn invalidate_chapter(conn: &rusqlite::Connection, chapter_id: &str) -> Result<(), String> {
+    let order = chapter::reading_order(conn)?;
+    let position = positions(&order);
+    let Some(&pos) = position.get(chapter_id) else {
+        return Ok(());
+    };
+    let ids: Vec<String> = load_arcs(conn)?
+        .into_iter()
+        .filter(|arc| span(&position, arc).is_none_or(|(s, e)| s <= pos && pos <= e))
+        .map(|arc| arc.id)
+        .collect();
+    mark_stale(conn, &ids)
+}
+
+/// 章节顺序变化后，将成员发生变化的剧情弧标记为失效
+///
+/// `before` 为变化前的阅读顺序（`chapter::reading_order`）。
+pub(crate) fn invalidate_reordered(conn: &rusqlite::Connection, before: &[String]) -> Result<(), String> {
+    let after = chapter::reading_order(conn)?;
+    if before == after.as_slice() {
+        return Ok(());
+    }
+    let pos_before = positions(before);
+    let pos_after = positions(&after);
+
+    let ids: Vec<String> = load_arcs(conn)?
+        .into_iter()
+        .filter(|arc| {
+            let members_before = span(&pos_before, arc).map(|(s, e)| &before[s..=e]);
+            let members_after = span(&pos_after, arc).map(|(s, e)| &after[s..=e]);
+            members_before != members_after
+        })
+        .map(|arc| arc.id)
+        .collect();
+    mark_stale(conn, &ids)
+}
+
+/// 章节删除前调用：覆盖它的剧情弧标记为失效，以它为边界的剧情弧边界内缩，
+/// 只剩它一章的剧情弧直接删除
+pub(crate) fn detach_chapter(conn: &rusqlite::Connection, chapter_id: &str) -> Result<(), String> {
+    invalidate_chapter(conn, chapter_id)?;
+
+    let order = chapter::reading_order(conn)?;
+    let position = positions(&order);
+    let now = chrono::Utc::now().to_rfc3339();
+
+    for arc in load_arcs(conn)? {
+        if arc.start_chapter_id != chapter_id && arc.end_chapter_id != chapter_id {
+            continue;
+        }
+        let Some((s, e)) = span(&position, &arc) else {
+            conn.execute("DELETE FROM rag_arcs WHERE id = ?1", params![arc.id])
+                .map_err(|e| format!("删除剧情弧失败: {}", e))?;
+            continue;
+        };
+        if s == e {
+            conn.execute("DELETE FROM rag_arcs WHERE id = ?1", params![arc.id])
+                .map_err(|e| format!("删除剧情弧失败: {}", e))?;
+            continue;
+        }
+        let start = if arc.start_chapter_id == chapter_id { &order[s + 1] } else { &order[s] };
+        let end = if arc.end_chapter_id == chapter_id { &order[e - 1] } else { &order[e] };
+        conn.execute(
+            "UPDATE rag_arcs SET start_chapter_id = ?1, end_chapter_id = ?2, stale = 1, updated_at = ?3 WHERE id = ?4",
+            params![start, end, now, arc.id],
+        )
+        .map_err(|e| format!("调整剧情弧范围失败: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 有效剧情弧及其在阅读顺序中的 [起, 止] 位置，按起始位置排序
+pub(crate) fn arc_spans(conn: &rusqlite::Connection, order: &[String]) -> Result<Vec<(RagArc, usize, usize)>, String> {
+    let position = positions(order);
+    let mut spans: Vec<_> = load_arcs(conn)?
+        .into_iter()
+        .filter_map(|arc| span(&position, &arc).map(|(s, e)| (arc, s, e)))
+        .collect();
+    spans.sort_by_key(|(_, s, _)| *s);
+    Ok(spans)
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+fn map_arc(row: &rusqlite::Row) -> rusqlite::Result<RagArc> {
+    Ok(RagArc {
+        id: row.get(0)?,
+        start_chapter_id: row.get(1)?,
+        end_chapter_id: row.get(2)?,
+        summary: row.get(3)?,
+        stale: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+fn load_arc(conn: &rusqlite::Connection, id: &str) -> Result<RagArc, String> {
+    conn.query_row(
+        "SELECT id, start_chapter_id, end_chapter_id, summary, stale, created_at, updated_at
+         FROM rag_arcs WHERE id = ?1",
+        params![id],
+        map_arc,
+    )
+    .map_err(|e| format!("获取剧情弧失败: {}", e))
+}
+
+fn load_arcs(conn: &rusqlite::Connection) -> Result<Vec<RagArc>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, start_chapter_id, end_chapter_id, summary, stale, created_at, updated_at FROM rag_arcs")
+        .map_err(|e| format!("查询剧情弧失败: {}", e))?;
+    let arcs = stmt
+        .query_map([], map_arc)
+        .map_err(|e| format!("读取剧情弧失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析剧情弧失败: {}", e))?;
+    Ok(arcs)
+}
+
+fn positions(order: &[String]) -> HashMap<&str, usize> {
+    order.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect()
+}
+
+/// 剧情弧在阅读顺序中的 [起, 止] 位置；边界章节缺失或起止颠倒时为 None
+fn span(position: &HashMap<&str, usize>, arc: &RagArc) -> Option<(usize, usize)> {
+    let s = *position.get(arc.start_chapter_id.as_str())?;
+    let e = *position.get(arc.end_chapter_id.as_str())?;
+    (s <= e).then_some((s, e))
+}
+
+/// 校验范围合法且不与其他剧情弧重叠，返回 [起, 止] 位置
+fn check_range(
+    conn: &rusqlite::Connection,
+    order: &[String],
+    start: &str,
+    end: &str,
+    exclude_id: Option<&str>,
+) -> Result<(usize, usize), String> {
+    let position = positions(order);
+    let (Some(&s), Some(&e)) = (position.get(start), position.get(end)) else {
+        return Err("起止章节不存在".into());
+    };
+    if s > e {
+        return Err("起始章节必须在结束章节之前".into());
+    }
+
+    let overlapped = load_arcs(conn)?
+        .iter()
+        .filter(|arc| Some(arc.id.as_str()) != exclude_id)
+        .filter_map(|arc| span(&position, arc))
+        .any(|(as_, ae)| as_ <= e && s <= ae);
+    if overlapped {
+        return Err("与已有剧情弧范围重叠".into());
+    }
+    Ok((s, e))
+}
+
+fn mark_stale(conn: &rusqlite::Connection, ids: &[String]) -> Result<(), String> {
+    let now = chrono::Utc::now().to_rfc3339();
+    for id in ids {
+        conn.execute(
+            "UPDATE rag_arcs SET stale = 1, updated_at = ?1 WHERE id = ?2",
+            params![now, id],
+        )
+        .map_err(|e| format!("标记剧情弧失效失败: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 弧内各章 (章节名, L2 摘要)，按阅读顺序
+fn member_summaries(conn: &rusqlite::Connection, arc: &RagArc) -> Result<Vec<(String, String)>, String> {
+    let order = chapter::reading_order(conn)?;
+    let position = positions(&order);
+    let Some((s, e)) = span(&position, arc) else {
+        return Err("剧情弧范围无效，请重新切分".into());
+    };
+
+    let mut chapters = Vec::new();
+    for id in &order[s..=e] {
+        let (name, l2): (String, Option<String>) = conn
+            .query_row(
+                "SELECT name, l2_summary FROM chapters WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| format!("获取章节失败: {}", e))?;
+        match l2.filter(|s| !s.trim().is_empty()) {
+            Some(l2) => chapters.push((name, l2)),
+            None => return Err(format!("章节「{}」缺少 L2 摘要，请先生成", name)),
+        }
+    }
+    Ok(chapters)
+}
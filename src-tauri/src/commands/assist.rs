@@ -0,0 +1,371 @@
+use crate::commands::{ai, chapter, context as book_context};
+use crate::db;
+use crate::db::config;
+use crate::db::models::{Chapter, PromptTemplate};
+use crate::llm::context::ContextConfig;
+use crate::llm::provider::{ChatMessage, ChatRequest, ChatResponse};
+use crate::llm::tokens::{CjkEstimator, TokenEstimator};
+use crate::llm::prompt;
+use rusqlite::params;
+use tauri::AppHandle;
+
+fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
+    let cfg = config::load_config()?;
+    db::open_book_db(&cfg, storage_path)
+}
+
+/// 默认续写字数
+const DEFAULT_CONTINUE_LENGTH: usize = 800;
+/// 改写/润色时选区前后各取的字数
+const SELECTION_WINDOW: usize = 800;
+
+// ============================================================================
+// 提示词模板
+// ============================================================================
+
+/// 获取本书的全部提示词模板（未修改的返回内置默认）
+#[tauri::command]
+pub async fn list_prompt_templates(storage_path: String) -> Result<Vec<PromptTemplate>, String> {
+    let conn = open_book(&storage_path)?;
+    prompt::KINDS.iter().map(|kind| load_template(&conn, kind)).collect()
+}
+
+/// 修改本书的提示词模板
+#[tauri::command]
+pub async fn update_prompt_template(
+    storage_path: String,
+    kind: String,
+    template: String,
+) -> Result<PromptTemplate, String> {
+    if prompt::default_template(&kind).is_none() {
+        return Err(format!("未知的模板类型: {}", kind));
+    }
+    let conn = open_book(&storage_path)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO prompt_templates (kind, template, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(kind) DO UPDATE SET template = ?2, updated_at = ?3",
+        params![kind, template, now],
+    )
+    .map_err(|e| format!("保存模板失败: {}", e))?;
+    load_template(&conn, &kind)
+}
+
+/// 恢复内置默认模板
+#[tauri::command]
+pub async fn reset_prompt_template(storage_path: String, kind: String) -> Result<PromptTemplate, String> {
+    let conn = open_book(&storage_path)?;
+    conn.execute("DELETE FROM prompt_templates WHERE kind = ?1", params![kind])
+        .map_err(|e| format!("重置模板失败: {}", e))?;
+    load_template(&conn, &kind)
+}
+
+// ============================================================================
+// 续写 / 改写 / 润色
+// ============================================================================
+
+/// 流式续写当前章节（增量通过 `ai-stream` 事件推送，可用 `cancel_ai_request` 取消）
+///
+/// 结果不会自动写入章节，采纳时调用 `apply_ai_output`。
+#[tauri::command]
+pub async fn ai_continue_chapter(
+    app: AppHandle,
+    storage_path: String,
+    request_id: String,
+    chapter_id: String,
+    instruction: Option<String>,
+    length: Option<usize>,
+) -> Result<ChatResponse, String> {
+    let provider = ai::metered_provider(&app, Some(&storage_path), "continuation")?;
+    let config = ContextConfig::load(&db::open_global_db()?)?;
+    let estimator = CjkEstimator {
+        tokens_per_cjk: config.tokens_per_cjk,
+        ..Default::default()
+    };
+    let request = {
+        let conn = open_book(&storage_path)?;
+        continuation_request(&conn, &chapter_id, instruction.as_deref(), length, &config, &estimator)?
+    };
+    ai::stream_to_events(&app, &provider, &request_id, &request).await
+}
+
+/// 流式改写或润色选区（`kind` 为 rewrite / polish，`start`、`end` 为字符偏移）
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn ai_rewrite_selection(
+    app: AppHandle,
+    storage_path: String,
+    request_id: String,
+    chapter_id: String,
+    kind: String,
+    start: usize,
+    end: usize,
+    instruction: Option<String>,
+) -> Result<ChatResponse, String> {
+    let provider = ai::metered_provider(&app, Some(&storage_path), "rewrite")?;
+    let request = {
+        let conn = open_book(&storage_path)?;
+        rewrite_request(&conn, &chapter_id, &kind, start, end, instruction.as_deref())?
+    };
+    ai::stream_to_events(&app, &provider, &request_id, &request).await
+}
+
+/// 采纳 AI 输出：经 `update_chapter` 写入，快照来源标记为 ai
+///
+/// 未给出选区时追加到正文末尾（续写）；给出选区时替换该区间（改写/润色）。
+/// `expected` 为生成时的选区原文，正文已被修改导致不一致时拒绝写入。
+#[tauri::command]
+pub async fn apply_ai_output(
+    app: AppHandle,
+    storage_path: String,
+    chapter_id: String,
+    text: String,
+    start: Option<usize>,
+    end: Option<usize>,
+    expected: Option<String>,
+) -> Result<Chapter, String> {
+    let current = chapter::get_chapter(storage_path.clone(), chapter_id.clone()).await?;
+    let content = splice_output(&current.content, &text, start, end, expected.as_deref())?;
+    chapter::update_chapter(app, storage_path.clone(), chapter_id.clone(), content, Some("ai".into())).await?;
+    chapter::get_chapter(storage_path, chapter_id).await
+}
+
+/// 把 AI 输出拼入正文（规则同 `apply_ai_output`），返回新正文
+fn splice_output(
+    content: &str,
+    text: &str,
+    start: Option<usize>,
+    end: Option<usize>,
+    expected: Option<&str>,
+) -> Result<String, String> {
+    let chars: Vec<char> = content.chars().collect();
+
+    let (start, end) = match (start, end) {
+        (Some(s), Some(e)) => (s, e),
+        (Some(s), None) => (s, s),
+        _ => (chars.len(), chars.len()),
+    };
+    if start > end || end > chars.len() {
+        return Err("选区超出正文范围".into());
+    }
+    if let Some(expected) = expected {
+        if chars[start..end].iter().collect::<String>() != expected {
+            return Err("选区内容已变化，请重新生成".into());
+        }
+    }
+
+    Ok(chars[..start]
+        .iter()
+        .copied()
+        .chain(text.chars())
+        .chain(chars[end..].iter().copied())
+        .collect())
+}
+
+/// 构建续写请求
+pub fn continuation_request(
+    conn: &rusqlite::Connection,
+    chapter_id: &str,
+    instruction: Option<&str>,
+    length: Option<usize>,
+    config: &ContextConfig,
+    estimator: &dyn TokenEstimator,
+) -> Result<ChatRequest, String> {
+    let context = book_context::build_context(conn, chapter_id, config, estimator)?;
+    let template = load_template(conn, "continue")?;
+    let name = chapter_name(conn, chapter_id)?;
+    let length = length.unwrap_or(DEFAULT_CONTINUE_LENGTH);
+
+    let user = prompt::render(
+        &template.template,
+        &[
+            ("chapter_name", &name),
+            ("context", &context.text),
+            ("length", &length.to_string()),
+            ("instruction", &instruction_line(instruction)),
+        ],
+    );
+    Ok(ChatRequest {
+        messages: vec![ChatMessage::system(prompt::SYSTEM_PROMPT), ChatMessage::user(user)],
+        temperature: Some(0.8),
+        max_tokens: Some((length * 2) as u32),
+    })
+}
+
+/// 构建改写/润色请求
+pub fn rewrite_request(
+    conn: &rusqlite::Connection,
+    chapter_id: &str,
+    kind: &str,
+    start: usize,
+    end: usize,
+    instruction: Option<&str>,
+) -> Result<ChatRequest, String> {
+    if !matches!(kind, "rewrite" | "polish") {
+        return Err(format!("未知的改写类型: {}", kind));
+    }
+    let (name, content): (String, String) = conn
+        .query_row(
+            "SELECT name, content FROM chapters WHERE id = ?1",
+            params![chapter_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("获取章节失败: {}", e))?;
+    let chars: Vec<char> = content.chars().collect();
+    if start >= end || end > chars.len() {
+        return Err("选区超出正文范围".into());
+    }
+
+    let before: String = chars[start.saturating_sub(SELECTION_WINDOW)..start].iter().collect();
+    let selection: String = chars[start..end].iter().collect();
+    let after: String = chars[end..(end + SELECTION_WINDOW).min(chars.len())].iter().collect();
+    let template = load_template(conn, kind)?;
+
+    let user = prompt::render(
+        &template.template,
+        &[
+            ("chapter_name", &name),
+            ("before", &before),
+            ("selection", &selection),
+            ("after", &after),
+            ("instruction", &instruction_line(instruction)),
+        ],
+    );
+    Ok(ChatRequest {
+        messages: vec![ChatMessage::system(prompt::SYSTEM_PROMPT), ChatMessage::user(user)],
+        temperature: Some(if kind == "polish" { 0.3 } else { 0.7 }),
+        max_tokens: Some((selection.chars().count() * 3).max(200) as u32),
+    })
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+fn load_template(conn: &rusqlite::Connection, kind: &str) -> Result<PromptTemplate, String> {
+    let Some(default) = prompt::default_template(kind) else {
+        return Err(format!("未知的模板类型: {}", kind));
+    };
+    let stored = conn.query_row(
+        "SELECT template, updated_at FROM prompt_templates WHERE kind = ?1",
+        params![kind],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+    );
+    match stored {
+        Ok((template, updated_at)) => Ok(PromptTemplate {
+            kind: kind.to_string(),
+            template,
+            is_default: false,
+            updated_at: Some(updated_at),
+        }),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(PromptTemplate {
+            kind: kind.to_string(),
+            template: default.to_string(),
+            is_default: true,
+            updated_at: None,
+        }),
+        Err(e) => Err(format!("读取模板失败: {}", e)),
+    }
+}
+
+fn chapter_name(conn: &rusqlite::Connection, chapter_id: &str) -> Result<String, String> {
+    conn.query_row("SELECT name FROM chapters WHERE id = ?1", params![chapter_id], |row| row.get(0))
+        .map_err(|e| format!("获取章节失败: {}", e))
+}
+
+fn instruction_line(instruction: Option<&str>) -> String {
+    match instruction.map(str::trim) {
+        Some(text) if !text.is_empty() => format!("额外要求：{}", text),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ai::{self, AiStreamEvent};
+    use crate::commands::volume;
+    use crate::db::TestBook;
+    use crate::llm::cancel;
+    use crate::llm::context::CompressionMode;
+    use crate::llm::provider::fake::FakeProvider;
+    use crate::llm::provider::CANCELLED;
+    use crate::text::count::CountMode;
+
+    /// 临时测试书籍，含一个已有正文的章节；返回 (书籍, 章节 ID)
+    async fn test_chapter(content: &str) -> (TestBook, String) {
+        let book = TestBook::new();
+        let volume = volume::create_volume(book.to_string(), "第一卷".into()).await.unwrap();
+        let chapter = chapter::create_chapter(book.to_string(), volume.id, "第一章".into()).await.unwrap();
+        let conn = open_book(&book).unwrap();
+        chapter::save_content(&conn, &chapter.id, content, "user", CountMode::default()).unwrap();
+        (book, chapter.id)
+    }
+
+    fn kinds(events: &[AiStreamEvent]) -> Vec<(&str, &str)> {
+        events.iter().map(|e| (e.kind.as_str(), e.text.as_str())).collect()
+    }
+
+    #[tokio::test]
+    async fn continuation_streams_and_accepts_as_ai() {
+        let (book, id) = test_chapter("夜色渐深。").await;
+        let config = ContextConfig {
+            budget: 8000,
+            recent_chapters: 3,
+            mode: CompressionMode::Auto,
+            tokens_per_cjk: 1.0,
+        };
+        let request = {
+            let conn = open_book(&book).unwrap();
+            continuation_request(&conn, &id, Some("写雨"), Some(100), &config, &CjkEstimator::default()).unwrap()
+        };
+        assert!(request.messages[1].content.contains("夜色渐深"));
+
+        let provider = FakeProvider::new(|_| Ok("雨落".into()));
+        let mut events = Vec::new();
+        let mut on_event = |e: AiStreamEvent| events.push(e);
+        let response = ai::stream_with(&provider, "assist-test-continue", &request, &mut on_event).await.unwrap();
+        assert_eq!(kinds(&events), vec![("delta", "雨"), ("delta", "落"), ("done", "雨落")]);
+        assert!(events.iter().all(|e| e.request_id == "assist-test-continue"));
+
+        let conn = open_book(&book).unwrap();
+        let content = splice_output("夜色渐深。", &response.content, None, None, None).unwrap();
+        chapter::save_content(&conn, &id, &content, "ai", CountMode::default()).unwrap();
+        let (snapshot, source): (String, String) = conn
+            .query_row(
+                "SELECT snapshot_content, source FROM snapshots WHERE chapter_id = ?1 ORDER BY created_at DESC, rowid DESC LIMIT 1",
+                [&id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((snapshot.as_str(), source.as_str()), ("夜色渐深。雨落", "ai"));
+    }
+
+    #[tokio::test]
+    async fn rewrite_stream_can_be_cancelled() {
+        let (book, id) = test_chapter("他走进了房间。").await;
+        let request = {
+            let conn = open_book(&book).unwrap();
+            rewrite_request(&conn, &id, "rewrite", 1, 6, None).unwrap()
+        };
+        assert!(request.messages[1].content.contains("走进了房间"));
+
+        let provider = FakeProvider::new(|_| Ok("推门而入".into()));
+        let mut events = Vec::new();
+        let mut on_event = |e: AiStreamEvent| {
+            if e.kind == "delta" {
+                cancel::cancel("assist-test-rewrite");
+            }
+            events.push(e);
+        };
+        let err = ai::stream_with(&provider, "assist-test-rewrite", &request, &mut on_event).await.unwrap_err();
+        assert_eq!(err, CANCELLED);
+        assert_eq!(kinds(&events), vec![("delta", "推"), ("cancelled", "")]);
+    }
+
+    #[test]
+    fn splice_rejects_changed_selection() {
+        assert_eq!(splice_output("他走进了房间。", "推门而入", Some(1), Some(6), Some("走进了房间")).unwrap(), "他推门而入。");
+        assert!(splice_output("他跑进了房间。", "推门而入", Some(1), Some(6), Some("走进了房间")).is_err());
+    }
+}
@@ -70,6 +70,45 @@ pub async fn list_books() -> Result<Vec<Book>, String> {
     Ok(books)
 }
 
+/// 获取书籍列表及每本书的概况（总字数、章节数、分卷数、最近编辑的章节、今日字数）
+///
+/// 书籍目录缺失或 book.db 无法读取时概况字段为 0 / None（后者记录日志），不影响其它书籍。
+#[tauri::command]
+pub async fn list_books_with_stats() -> Result<Vec<BookOverview>, String> {
+    let cfg = config::load_config()?;
+    let global = db::init_global_db()?;
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+
+    let mut overviews = Vec::new();
+    for book in list_books().await? {
+        let today_words: i64 = global
+            .query_row(
+                "SELECT COALESCE(SUM(word_count), 0) FROM book_daily_stats WHERE book_id = ?1 AND date = ?2",
+                params![book.id, today],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("查询书籍统计失败: {}", e))?;
+
+        let mut overview = BookOverview {
+            book,
+            total_words: 0,
+            chapter_count: 0,
+            volume_count: 0,
+            last_edited_chapter: None,
+            today_words,
+        };
+        if config::book_db_path(&cfg, &overview.book.storage_path).exists() {
+            let filled = db::open_book_db(&cfg, &overview.book.storage_path)
+                .and_then(|conn| fill_overview(&conn, &mut overview));
+            if let Err(e) = filled {
+                log::warn!("读取书籍概况失败 ({}): {}", overview.book.storage_path, e);
+            }
+        }
+        overviews.push(overview);
+    }
+    Ok(overviews)
+}
+
 /// 更新书籍信息（书名、作者笔名、封面）
 #[tauri::command]
 pub async fn update_book(
@@ -180,6 +219,8 @@ pub async fn permanently_delete_book(id: String) -> Result<(), String> {
     // 从数据库删除记录
     conn.execute("DELETE FROM books WHERE id = ?1", params![id])
         .map_err(|e| format!("永久删除书籍记录失败: {}", e))?;
+    conn.execute("DELETE FROM book_daily_stats WHERE book_id = ?1", params![id])
+        .map_err(|e| format!("删除书籍统计失败: {}", e))?;
 
     // 删除磁盘上的书籍目录
     if let Some(sp) = storage_path {
@@ -210,6 +251,75 @@ pub struct DeletedBook {
     pub deleted_at: String,
 }
 
+/// 书架上的书籍概况
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BookOverview {
+    #[serde(flatten)]
+    pub book: Book,
+    pub total_words: i64,
+    pub chapter_count: i64,
+    pub volume_count: i64,
+    pub last_edited_chapter: Option<RecentChapter>,
+    /// 今日在这本书上计入写作的字数
+    pub today_words: i64,
+}
+
+/// 最近编辑的章节
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecentChapter {
+    pub id: String,
+    pub name: String,
+    pub updated_at: String,
+}
+
+/// 按 storage_path 查找书籍 ID（书籍记录不存在时为 None）
+pub(crate) fn book_id_for(conn: &rusqlite::Connection, storage_path: &str) -> Result<Option<String>, String> {
+    match conn.query_row(
+        "SELECT id FROM books WHERE storage_path = ?1",
+        params![storage_path],
+        |row| row.get(0),
+    ) {
+        Ok(id) => Ok(Some(id)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(format!("查询书籍失败: {}", e)),
+    }
+}
+
+/// 从 book.db 读取字数、章节数、分卷数与最近编辑的章节
+/// 读取失败时不修改 `overview`
+fn fill_overview(conn: &rusqlite::Connection, overview: &mut BookOverview) -> Result<(), String> {
+    let (total_words, chapter_count) = conn
+        .query_row(
+            "SELECT COALESCE(SUM(word_count), 0), COUNT(*) FROM chapters",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("统计章节失败: {}", e))?;
+    let volume_count = conn
+        .query_row("SELECT COUNT(*) FROM volumes", [], |row| row.get(0))
+        .map_err(|e| format!("统计分卷失败: {}", e))?;
+    let last_edited_chapter = match conn.query_row(
+        "SELECT id, name, updated_at FROM chapters ORDER BY updated_at DESC LIMIT 1",
+        [],
+        |row| {
+            Ok(RecentChapter {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                updated_at: row.get(2)?,
+            })
+        },
+    ) {
+        Ok(chapter) => Some(chapter),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(format!("查询最近编辑的章节失败: {}", e)),
+    };
+    overview.total_words = total_words;
+    overview.chapter_count = chapter_count;
+    overview.volume_count = volume_count;
+    overview.last_edited_chapter = last_edited_chapter;
+    Ok(())
+}
+
 /// 去除文件名中不安全的字符
 fn sanitize_dir_name(name: &str) -> String {
     let re = regex::Regex::new(r#"[<>:"/\\|?*\x00-\x1f]"#).unwrap();
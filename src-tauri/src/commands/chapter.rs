@@ -1,7 +1,13 @@
+use crate::commands::{arc, stats, status, vector};
 use crate::db;
 use crate::db::config;
 use crate::db::models::Chapter;
+use crate::text::count::{self, CountMode};
 use rusqlite::params;
+use tauri::{AppHandle, Emitter};
+
+/// `update_chapter` 接受的来源（import / restore 等由后端内部写入）
+const UPDATE_SOURCES: [&str; 3] = ["user", "ai", "replace"];
 
 fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
     let cfg = config::load_config()?;
@@ -34,6 +40,9 @@ pub async fn create_chapter(
     )
     .map_err(|e| format!("创建章节失败: {}", e))?;
 
+    // 新章节可能落在某个剧情弧范围内（非末卷追加时）
+    arc::invalidate_chapter(&conn, &id)?;
+
     Ok(Chapter {
         id,
         volume_id,
@@ -44,6 +53,9 @@ pub async fn create_chapter(
         status: "draft".into(),
         word_count: 0,
         sort_order: max_order + 1,
+        scheduled_at: None,
+        published_at: None,
+        copied_from: None,
         created_at: now.clone(),
         updated_at: now,
     })
@@ -55,7 +67,8 @@ pub async fn list_chapters(storage_path: String, volume_id: String) -> Result<Ve
     let conn = open_book(&storage_path)?;
     let mut stmt = conn
         .prepare(
-            "SELECT id, volume_id, name, '', l2_summary, l3_title, status, word_count, sort_order, created_at, updated_at
+            "SELECT id, volume_id, name, '', l2_summary, l3_title, status, word_count, sort_order,
+                    scheduled_at, published_at, copied_from, created_at, updated_at
              FROM chapters WHERE volume_id = ?1 ORDER BY sort_order ASC",
         )
         .map_err(|e| format!("查询章节失败: {}", e))?;
@@ -72,8 +85,11 @@ pub async fn list_chapters(storage_path: String, volume_id: String) -> Result<Ve
                 status: row.get(6)?,
                 word_count: row.get(7)?,
                 sort_order: row.get(8)?,
-                created_at: row.get(9)?,
-                updated_at: row.get(10)?,
+                scheduled_at: row.get(9)?,
+                published_at: row.get(10)?,
+                copied_from: row.get(11)?,
+                created_at: row.get(12)?,
+                updated_at: row.get(13)?,
             })
         })
         .map_err(|e| format!("读取章节失败: {}", e))?
@@ -88,7 +104,8 @@ pub async fn list_chapters(storage_path: String, volume_id: String) -> Result<Ve
 pub async fn get_chapter(storage_path: String, id: String) -> Result<Chapter, String> {
     let conn = open_book(&storage_path)?;
     conn.query_row(
-        "SELECT id, volume_id, name, content, l2_summary, l3_title, status, word_count, sort_order, created_at, updated_at
+        "SELECT id, volume_id, name, content, l2_summary, l3_title, status, word_count, sort_order,
+                scheduled_at, published_at, copied_from, created_at, updated_at
          FROM chapters WHERE id = ?1",
         params![id],
         |row| {
@@ -102,39 +119,76 @@ pub async fn get_chapter(storage_path: String, id: String) -> Result<Chapter, St
                 status: row.get(6)?,
                 word_count: row.get(7)?,
                 sort_order: row.get(8)?,
-                created_at: row.get(9)?,
-                updated_at: row.get(10)?,
+                scheduled_at: row.get(9)?,
+                published_at: row.get(10)?,
+                copied_from: row.get(11)?,
+                created_at: row.get(12)?,
+                updated_at: row.get(13)?,
             })
         },
     )
     .map_err(|e| format!("获取章节失败: {}", e))
 }
 
-/// 更新章节内容（自动计算字数，自动创建快照）
+/// 更新章节内容（自动计算字数，自动创建快照，记录写作日志）
+///
+/// `source` 为快照与写作日志的来源：默认 user，采纳 AI 输出时传 ai，批量替换时传 replace。
+/// 保存后当天字数首次达到目标时推送 `daily-goal-met` 事件。
 #[tauri::command]
 pub async fn update_chapter(
+    app: AppHandle,
     storage_path: String,
     id: String,
     content: String,
+    source: Option<String>,
 ) -> Result<(), String> {
+    let source = source.unwrap_or_else(|| "user".into());
+    if !UPDATE_SOURCES.contains(&source.as_str()) {
+        return Err(format!("无效的来源: {}", source));
+    }
+    let mode = stats::count_mode()?;
     let conn = open_book(&storage_path)?;
+    let delta = save_content(&conn, &id, &content, &source, mode)?;
+    for reached in stats::log_writing(&conn, &storage_path, &id, delta, &source)? {
+        let _ = app.emit("daily-goal-met", reached);
+    }
+    Ok(())
+}
+
+/// 写入章节正文：按 `mode` 更新字数与状态、刷新索引、创建快照；返回字数变化
+pub(crate) fn save_content(
+    conn: &rusqlite::Connection,
+    id: &str,
+    content: &str,
+    source: &str,
+    mode: CountMode,
+) -> Result<i64, String> {
     let now = chrono::Utc::now().to_rfc3339();
-    let word_count = content.chars().count() as i64;
+    let word_count = count::count(content, mode);
+    let (previous, previous_status): (i64, String) = conn
+        .query_row(
+            "SELECT word_count, status FROM chapters WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("获取章节失败: {}", e))?;
 
-    // 如果当前状态是 complete，编辑后变为 dirty
     conn.execute(
-        "UPDATE chapters SET content = ?1, word_count = ?2, updated_at = ?3,
-         status = CASE WHEN status = 'complete' THEN 'dirty' ELSE status END
-         WHERE id = ?4",
+        "UPDATE chapters SET content = ?1, word_count = ?2, updated_at = ?3 WHERE id = ?4",
         params![content, word_count, now, id],
     )
     .map_err(|e| format!("更新章节失败: {}", e))?;
+    // 如果当前状态是 complete，编辑后变为 dirty
+    status::after_edit(conn, id, &previous_status)?;
+
+    arc::invalidate_chapter(conn, id)?;
+    vector::refresh_chapter(conn, id)?;
 
     // 自动创建快照
     let snap_id = uuid::Uuid::new_v4().to_string();
     conn.execute(
-        "INSERT INTO snapshots (id, chapter_id, snapshot_content, created_at) VALUES (?1, ?2, ?3, ?4)",
-        params![snap_id, id, content, now],
+        "INSERT INTO snapshots (id, chapter_id, snapshot_content, source, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![snap_id, id, content, source, now],
     )
     .map_err(|e| format!("创建快照失败: {}", e))?;
 
@@ -147,7 +201,7 @@ pub async fn update_chapter(
     )
     .map_err(|e| format!("清理旧快照失败: {}", e))?;
 
-    Ok(())
+    Ok(word_count - previous)
 }
 
 /// 重命名章节
@@ -167,6 +221,7 @@ pub async fn rename_chapter(storage_path: String, id: String, name: String) -> R
 #[tauri::command]
 pub async fn reorder_chapters(storage_path: String, ids: Vec<String>) -> Result<(), String> {
     let conn = open_book(&storage_path)?;
+    let before = reading_order(&conn)?;
     for (i, id) in ids.iter().enumerate() {
         conn.execute(
             "UPDATE chapters SET sort_order = ?1 WHERE id = ?2",
@@ -174,6 +229,7 @@ pub async fn reorder_chapters(storage_path: String, ids: Vec<String>) -> Result<
         )
         .map_err(|e| format!("排序章节失败: {}", e))?;
     }
+    arc::invalidate_reordered(&conn, &before)?;
     Ok(())
 }
 
@@ -186,6 +242,7 @@ pub async fn move_chapter(
 ) -> Result<(), String> {
     let conn = open_book(&storage_path)?;
     let now = chrono::Utc::now().to_rfc3339();
+    let before = reading_order(&conn)?;
 
     let max_order: i64 = conn
         .query_row(
@@ -201,37 +258,85 @@ pub async fn move_chapter(
     )
     .map_err(|e| format!("移动章节失败: {}", e))?;
 
+    arc::invalidate_reordered(&conn, &before)?;
+
     Ok(())
 }
 
-/// 删除章节（移入回收站）
+/// 复制章节（正文与摘要，不含快照），新章节记录复制来源
+///
+/// 未指定 `target_volume_id` 或与原章节同卷时紧随原章节，否则排在目标分卷末尾。
+/// 新章节名默认为「原章节名（副本）」，状态为草稿，不带发布计划。
 #[tauri::command]
-pub async fn delete_chapter(storage_path: String, id: String) -> Result<(), String> {
-    let conn = open_book(&storage_path)?;
-    let now = chrono::Utc::now().to_rfc3339();
+pub async fn duplicate_chapter(
+    storage_path: String,
+    id: String,
+    target_volume_id: Option<String>,
+    name: Option<String>,
+) -> Result<Chapter, String> {
+    let mut conn = open_book(&storage_path)?;
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+    let before = reading_order(&tx)?;
 
-    let data_json: String = conn
+    let (volume_id, source_name, sort_order): (String, String, i64) = tx
         .query_row(
-            "SELECT json_object('id', id, 'volume_id', volume_id, 'name', name, 'content', content,
-             'l2_summary', l2_summary, 'l3_title', l3_title, 'status', status,
-             'word_count', word_count, 'sort_order', sort_order,
-             'created_at', created_at, 'updated_at', updated_at) FROM chapters WHERE id = ?1",
+            "SELECT volume_id, name, sort_order FROM chapters WHERE id = ?1",
             params![id],
-            |row| row.get(0),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
         )
-        .map_err(|e| format!("序列化章节失败: {}", e))?;
+        .map_err(|e| format!("获取章节失败: {}", e))?;
+    let target_volume_id = target_volume_id.unwrap_or_else(|| volume_id.clone());
+    let target_order = if target_volume_id == volume_id {
+        tx.execute(
+            "UPDATE chapters SET sort_order = sort_order + 1 WHERE volume_id = ?1 AND sort_order > ?2",
+            params![volume_id, sort_order],
+        )
+        .map_err(|e| format!("排序章节失败: {}", e))?;
+        sort_order + 1
+    } else {
+        tx.query_row(
+            "SELECT COALESCE(MAX(sort_order), -1) + 1 FROM chapters WHERE volume_id = ?1",
+            params![target_volume_id],
+            |r| r.get(0),
+        )
+        .map_err(|e| format!("查询排序失败: {}", e))?
+    };
+
+    let name = name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| format!("{}（副本）", source_name));
+    let new_id = copy_chapter(&tx, &id, &target_volume_id, &name, target_order)?;
+    arc::invalidate_reordered(&tx, &before)?;
+    tx.commit().map_err(|e| format!("提交复制失败: {}", e))?;
+
+    get_chapter(storage_path, new_id).await
+}
+
+/// 删除章节（移入回收站；在一个事务内完成，删除失败时不留下已修改的剧情弧与索引）
+#[tauri::command]
+pub async fn delete_chapter(storage_path: String, id: String) -> Result<(), String> {
+    let mut conn = open_book(&storage_path)?;
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    let data = chapter_json(&tx, &id)?;
 
     let trash_id = uuid::Uuid::new_v4().to_string();
-    conn.execute(
+    tx.execute(
         "INSERT INTO trash (id, original_table, original_id, data_json, deleted_at, deleted_by) VALUES (?1, 'chapters', ?2, ?3, ?4, 'user')",
-        params![trash_id, id, data_json, now],
+        params![trash_id, id, data.to_string(), now],
     )
     .map_err(|e| format!("移入回收站失败: {}", e))?;
 
-    conn.execute("DELETE FROM chapters WHERE id = ?1", params![id])
+    arc::detach_chapter(&tx, &id)?;
+    vector::remove_source(&tx, "chapter", &id)?;
+    vector::remove_source(&tx, "summary", &id)?;
+
+    tx.execute("DELETE FROM chapters WHERE id = ?1", params![id])
         .map_err(|e| format!("删除章节失败: {}", e))?;
 
-    Ok(())
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))
 }
 
 /// 搜索章节内容
@@ -284,7 +389,27 @@ pub async fn search_chapters(
     Ok(results)
 }
 
-/// 标记章节完成状态
+/// 全书阅读顺序的章节 ID 列表（先按分卷排序，再按卷内章节排序）
+pub(crate) fn reading_order(conn: &rusqlite::Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT c.id FROM chapters c JOIN volumes v ON v.id = c.volume_id
+             ORDER BY v.sort_order ASC, c.sort_order ASC",
+        )
+        .map_err(|e| format!("查询章节顺序失败: {}", e))?;
+
+    let ids = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("读取章节顺序失败: {}", e))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| format!("解析章节顺序失败: {}", e))?;
+
+    Ok(ids)
+}
+
+/// 标记章节状态（内置或自定义状态，须符合流转规则，见 `status` 模块）
+///
+/// 标记为 published 时记录发布时间，改回其它状态时清除。
 #[tauri::command]
 pub async fn set_chapter_status(
     storage_path: String,
@@ -292,11 +417,81 @@ pub async fn set_chapter_status(
     status: String,
 ) -> Result<(), String> {
     let conn = open_book(&storage_path)?;
-    let now = chrono::Utc::now().to_rfc3339();
+    status::transition(&conn, &id, &status, "user")?;
+    Ok(())
+}
+
+/// 章节完整记录（回收站、拆分合并的还原数据使用此格式）
+pub(crate) fn chapter_json(conn: &rusqlite::Connection, id: &str) -> Result<serde_json::Value, String> {
+    let data_json: String = conn
+        .query_row(
+            "SELECT json_object('id', id, 'volume_id', volume_id, 'name', name, 'content', content,
+             'l2_summary', l2_summary, 'l3_title', l3_title, 'status', status,
+             'word_count', word_count, 'sort_order', sort_order,
+             'scheduled_at', scheduled_at, 'published_at', published_at, 'copied_from', copied_from,
+             'created_at', created_at, 'updated_at', updated_at) FROM chapters WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("序列化章节失败: {}", e))?;
+    serde_json::from_str(&data_json).map_err(|e| format!("解析章节数据失败: {}", e))
+}
+
+/// 按 `chapter_json` 的格式写回章节（已存在则覆盖），返回按 `mode` 重新统计的字数
+pub(crate) fn insert_chapter_json(
+    conn: &rusqlite::Connection,
+    data: &serde_json::Value,
+    mode: CountMode,
+) -> Result<i64, String> {
+    // 按当前模式重新统计，删除后可能切换过字数口径
+    let word_count = count::count(data["content"].as_str().unwrap_or_default(), mode);
     conn.execute(
-        "UPDATE chapters SET status = ?1, updated_at = ?2 WHERE id = ?3",
-        params![status, now, id],
+        "INSERT OR REPLACE INTO chapters (id, volume_id, name, content, l2_summary, l3_title, status, word_count, sort_order,
+         scheduled_at, published_at, copied_from, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        params![
+            data["id"].as_str().unwrap_or_default(),
+            data["volume_id"].as_str().unwrap_or_default(),
+            data["name"].as_str().unwrap_or_default(),
+            data["content"].as_str().unwrap_or_default(),
+            data["l2_summary"].as_str(),
+            data["l3_title"].as_str(),
+            data["status"].as_str().unwrap_or("draft"),
+            word_count,
+            data["sort_order"].as_i64().unwrap_or(0),
+            data["scheduled_at"].as_str(),
+            data["published_at"].as_str(),
+            data["copied_from"].as_str(),
+            data["created_at"].as_str().unwrap_or_default(),
+            data["updated_at"].as_str().unwrap_or_default(),
+        ],
     )
-    .map_err(|e| format!("更新章节状态失败: {}", e))?;
-    Ok(())
+    .map_err(|e| format!("恢复章节失败: {}", e))?;
+    Ok(word_count)
+}
+
+/// 复制章节行（正文与摘要，不含快照）到指定分卷与位置，新章节为草稿并记录复制来源，返回新章节 ID
+pub(crate) fn copy_chapter(
+    conn: &rusqlite::Connection,
+    source_id: &str,
+    volume_id: &str,
+    name: &str,
+    sort_order: i64,
+) -> Result<String, String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+    let copied = conn
+        .execute(
+            "INSERT INTO chapters (id, volume_id, name, content, l2_summary, l3_title, status, word_count, sort_order,
+             copied_from, created_at, updated_at)
+             SELECT ?1, ?2, ?3, content, l2_summary, l3_title, 'draft', word_count, ?4, id, ?5, ?5
+             FROM chapters WHERE id = ?6",
+            params![id, volume_id, name, sort_order, now, source_id],
+        )
+        .map_err(|e| format!("复制章节失败: {}", e))?;
+    if copied == 0 {
+        return Err("章节不存在".into());
+    }
+    vector::refresh_chapter(conn, &id)?;
+    Ok(id)
 }
@@ -0,0 +1,245 @@
+use crate::commands::{arc, chapter, io};
+use crate::db;
+use crate::db::config;
+use crate::llm::context::{self, ArcText, AssembledContext, ChapterText, ContextConfig, ContextSources, EntityCard, ForeshadowItem};
+use crate::llm::tokens::{CjkEstimator, TokenEstimator};
+use std::collections::HashMap;
+
+fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
+    let cfg = config::load_config()?;
+    db::open_book_db(&cfg, storage_path)
+}
+
+/// 为目标章节组装续写上下文
+///
+/// 预算、近章数、压缩策略默认取 global.db 设置，`budget` / `recent_chapters` 可临时覆盖。
+#[tauri::command]
+pub async fn build_ai_context(
+    storage_path: String,
+    chapter_id: String,
+    budget: Option<usize>,
+    recent_chapters: Option<usize>,
+) -> Result<AssembledContext, String> {
+    let mut config = ContextConfig::load(&db::open_global_db()?)?;
+    if let Some(budget) = budget {
+        config.budget = budget;
+    }
+    if let Some(k) = recent_chapters {
+        config.recent_chapters = k;
+    }
+
+    let conn = open_book(&storage_path)?;
+    let estimator = CjkEstimator {
+        tokens_per_cjk: config.tokens_per_cjk,
+        ..Default::default()
+    };
+    build_context(&conn, &chapter_id, &config, &estimator)
+}
+
+/// 读取素材并组装（估算器可替换）
+pub fn build_context(
+    conn: &rusqlite::Connection,
+    chapter_id: &str,
+    config: &ContextConfig,
+    estimator: &dyn TokenEstimator,
+) -> Result<AssembledContext, String> {
+    let sources = load_sources(conn, chapter_id, config.recent_chapters.max(1))?;
+    Ok(context::assemble(&sources, config.budget, config.mode, estimator))
+}
+
+/// 按阅读顺序切出各层素材
+///
+/// 当前剧情弧为覆盖目标章节的剧情弧；目标章节不在任何剧情弧内时，
+/// 视为从上一个剧情弧结束后开始。更早的剧情弧只取已有概要的。
+fn load_sources(conn: &rusqlite::Connection, chapter_id: &str, k: usize) -> Result<ContextSources, String> {
+    let order = chapter::reading_order(conn)?;
+    let Some(pos) = order.iter().position(|id| id == chapter_id) else {
+        return Err("章节不存在".into());
+    };
+    let recent_start = (pos + 1).saturating_sub(k);
+
+    let spans = arc::arc_spans(conn, &order)?;
+    let arc_start = spans
+        .iter()
+        .find(|(_, s, e)| *s <= pos && pos <= *e)
+        .map(|(_, s, _)| *s)
+        .or_else(|| spans.iter().filter(|(_, _, e)| *e < pos).map(|(_, _, e)| e + 1).max())
+        .unwrap_or(0);
+
+    let chapters = load_chapters(conn)?;
+    let text_of = |id: &String| chapters.get(id).cloned();
+    let name_of = |id: &String| chapters.get(id).map(|c| c.name.clone()).unwrap_or_default();
+
+    let history = spans
+        .iter()
+        .filter(|(a, _, e)| *e < arc_start && !a.summary.trim().is_empty())
+        .map(|(a, s, e)| ArcText {
+            label: format!("{} ~ {}", name_of(&order[*s]), name_of(&order[*e])),
+            summary: a.summary.clone(),
+        })
+        .collect();
+    let arc_chapters = order[arc_start.min(recent_start)..recent_start].iter().filter_map(text_of).collect();
+    let recent: Vec<ChapterText> = order[recent_start..=pos].iter().filter_map(text_of).collect();
+
+    let recent_text: String = recent.iter().map(|c| c.content.as_str()).collect::<Vec<_>>().join("\n");
+    let entities = mentioned_characters(conn, &recent_text)?;
+
+    let position: HashMap<&str, usize> = order.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+    let foreshadows = open_foreshadows(conn, &position, pos, &name_of)?;
+
+    Ok(ContextSources {
+        history,
+        arc_chapters,
+        entities,
+        foreshadows,
+        recent,
+    })
+}
+
+fn load_chapters(conn: &rusqlite::Connection) -> Result<HashMap<String, ChapterText>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name, content, l2_summary, l3_title FROM chapters")
+        .map_err(|e| format!("查询章节失败: {}", e))?;
+    let chapters = stmt
+        .query_map([], |row| {
+            let chapter = ChapterText {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                content: row.get(2)?,
+                l2_summary: row.get(3)?,
+                l3_title: row.get(4)?,
+            };
+            Ok((chapter.id.clone(), chapter))
+        })
+        .map_err(|e| format!("读取章节失败: {}", e))?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| format!("解析章节失败: {}", e))?;
+    Ok(chapters)
+}
+
+/// 近章正文中出现的人物（不含 Inbox），按出现次数降序
+fn mentioned_characters(conn: &rusqlite::Connection, text: &str) -> Result<Vec<EntityCard>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name, status, attributes_json FROM entities WHERE entity_type = 'character' AND inbox = 0")
+        .map_err(|e| format!("查询实体失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })
+        .map_err(|e| format!("读取实体失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析实体失败: {}", e))?;
+
+    let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+    let mut stmt = conn
+        .prepare("SELECT entity_id, alias FROM entity_aliases ORDER BY created_at ASC")
+        .map_err(|e| format!("查询别名失败: {}", e))?;
+    let alias_rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("读取别名失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析别名失败: {}", e))?;
+    for (entity_id, alias) in alias_rows {
+        aliases.entry(entity_id).or_default().push(alias);
+    }
+
+    let mut cards: Vec<(usize, EntityCard)> = rows
+        .into_iter()
+        .filter_map(|(id, name, status, attributes_json)| {
+            let aliases = aliases.remove(&id).unwrap_or_default();
+            let mentions: usize = std::iter::once(&name)
+                .chain(aliases.iter())
+                .filter(|n| !n.is_empty())
+                .map(|n| text.matches(n.as_str()).count())
+                .sum();
+            if mentions == 0 {
+                return None;
+            }
+            let attributes = serde_json::from_str(&attributes_json).unwrap_or(serde_json::Value::Null);
+            let mut flat = Vec::new();
+            flatten_attributes("", &attributes, &mut flat);
+            Some((
+                mentions,
+                EntityCard {
+                    name,
+                    aliases,
+                    status: io::entity_status_label(&status).to_string(),
+                    attributes: flat,
+                },
+            ))
+        })
+        .collect();
+    cards.sort_by_key(|(mentions, _)| std::cmp::Reverse(*mentions));
+    Ok(cards.into_iter().map(|(_, card)| card).collect())
+}
+
+/// 属性 JSON 展开为 (点分路径, 文本值)
+pub(crate) fn flatten_attributes(prefix: &str, value: &serde_json::Value, out: &mut Vec<(String, String)>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (k, v) in map {
+                let path = if prefix.is_empty() { k.clone() } else { format!("{}.{}", prefix, k) };
+                flatten_attributes(&path, v, out);
+            }
+        }
+        serde_json::Value::Null => {}
+        other if !prefix.is_empty() => {
+            let text = io::attribute_text(other);
+            if !text.is_empty() {
+                out.push((prefix.to_string(), text));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 目标章节及之前埋设的未回收伏笔，按优先级降序、埋设先后排列
+fn open_foreshadows(
+    conn: &rusqlite::Connection,
+    position: &HashMap<&str, usize>,
+    pos: usize,
+    name_of: &dyn Fn(&String) -> String,
+) -> Result<Vec<ForeshadowItem>, String> {
+    let mut stmt = conn
+        .prepare("SELECT description, priority, plant_chapter_id FROM foreshadows WHERE status = 'open'")
+        .map_err(|e| format!("查询伏笔失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })
+        .map_err(|e| format!("读取伏笔失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析伏笔失败: {}", e))?;
+
+    let mut items: Vec<(i64, usize, ForeshadowItem)> = rows
+        .into_iter()
+        .filter_map(|(description, priority, plant)| {
+            let plant_pos = match &plant {
+                Some(id) => *position.get(id.as_str())?,
+                None => 0,
+            };
+            (plant_pos <= pos).then(|| {
+                (
+                    priority,
+                    plant_pos,
+                    ForeshadowItem {
+                        description,
+                        priority,
+                        planted_in: plant.as_ref().map(name_of),
+                    },
+                )
+            })
+        })
+        .collect();
+    items.sort_by_key(|(priority, plant_pos, _)| (std::cmp::Reverse(*priority), *plant_pos));
+    Ok(items.into_iter().map(|(_, _, item)| item).collect())
+}
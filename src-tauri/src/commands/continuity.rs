@@ -0,0 +1,277 @@
+use crate::commands::chapter;
+use crate::db;
+use crate::db::config;
+use rusqlite::params;
+use std::collections::{HashMap, HashSet};
+
+fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
+    let cfg = config::load_config()?;
+    db::open_book_db(&cfg, storage_path)
+}
+
+/// 连续性问题
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ContinuityFinding {
+    /// 稳定标识（kind:entity_id:chapter_id），用于忽略
+    pub finding_key: String,
+    /// dead_character_mentioned / destroyed_item_mentioned
+    pub kind: String,
+    pub entity_id: String,
+    pub entity_name: String,
+    /// 状态变更发生的章节
+    pub status_chapter_id: String,
+    /// 出现问题的章节
+    pub chapter_id: String,
+    pub chapter_name: String,
+    /// 命中的名称（本名或别名）
+    pub matched_name: String,
+    /// 首次命中位置（字符偏移）
+    pub offset: i64,
+    pub length: i64,
+    pub snippet: String,
+    /// 本章命中次数
+    pub occurrences: i64,
+    pub dismissed: bool,
+}
+
+/// 全书连续性检查
+///
+/// 按阅读顺序回放每个实体的时间线 `status_change`：角色变为 dead、道具变为
+/// destroyed 之后（直到再次变更状态为止），后续章节正文中出现其名称或别名即报告。
+/// 同一章内无法区分先后，状态变更所在章节本身不检查。没有状态变更节点的实体无从
+/// 确定变更位置，不检查。
+/// 书中暂无地点追踪数据，因此不检查"同章出现在两地"。
+#[tauri::command]
+pub async fn check_continuity(
+    storage_path: String,
+    include_dismissed: Option<bool>,
+) -> Result<Vec<ContinuityFinding>, String> {
+    let conn = open_book(&storage_path)?;
+
+    let order = chapter::reading_order(&conn)?;
+    let position: HashMap<&str, usize> = order.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+
+    let dismissed = load_dismissed(&conn)?;
+    let chapters = load_chapters(&conn)?;
+    let names = load_names(&conn)?;
+
+    // 实体的状态变更（按阅读顺序回放）
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.entity_id, e.name, t.chapter_id, t.status_change
+             FROM timeline t JOIN entities e ON e.id = t.entity_id
+             WHERE t.status_change IS NOT NULL",
+        )
+        .map_err(|e| format!("查询时间线失败: {}", e))?;
+
+    // 实体 ID → (实体名, [(阅读位置, 章节 ID, 变更后状态)])
+    let mut changes: HashMap<String, (String, Vec<StatusChange>)> = HashMap::new();
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })
+        .map_err(|e| format!("读取时间线失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析时间线失败: {}", e))?;
+
+    for (entity_id, name, chapter_id, status_change) in rows {
+        let Some(&pos) = position.get(chapter_id.as_str()) else {
+            continue;
+        };
+        changes
+            .entry(entity_id)
+            .or_insert_with(|| (name, Vec::new()))
+            .1
+            .push((pos, chapter_id, status_change));
+    }
+
+    let mut findings = Vec::new();
+
+    for (entity_id, (entity_name, mut entity_changes)) in changes {
+        entity_changes.sort_by_key(|c| c.0);
+        let entity_names = names.get(&entity_id).cloned().unwrap_or_else(|| vec![entity_name.clone()]);
+        let Some(matcher) = name_matcher(&entity_names)? else {
+            continue;
+        };
+
+        for (i, (pos, status_chapter_id, status)) in entity_changes.iter().enumerate() {
+            let kind = match status.as_str() {
+                "dead" => "dead_character_mentioned",
+                "destroyed" => "destroyed_item_mentioned",
+                _ => continue,
+            };
+            // 有效区间：状态变更之后，到下一次状态变更（含复活/修复）所在章节之前
+            let end = entity_changes.get(i + 1).map(|c| c.0).unwrap_or(order.len());
+
+            for chapter_id in &order[pos + 1..end] {
+                let Some((chapter_name, content)) = chapters.get(chapter_id) else {
+                    continue;
+                };
+                let Some(hit) = find_first(content, &matcher) else {
+                    continue;
+                };
+
+                let finding_key = format!("{}:{}:{}", kind, entity_id, chapter_id);
+                let is_dismissed = dismissed.contains(&finding_key);
+                if is_dismissed && !include_dismissed.unwrap_or(false) {
+                    continue;
+                }
+
+                findings.push(ContinuityFinding {
+                    finding_key,
+                    kind: kind.to_string(),
+                    entity_id: entity_id.clone(),
+                    entity_name: entity_name.clone(),
+                    status_chapter_id: status_chapter_id.clone(),
+                    chapter_id: chapter_id.clone(),
+                    chapter_name: chapter_name.clone(),
+                    matched_name: hit.name,
+                    offset: hit.offset,
+                    length: hit.length,
+                    snippet: hit.snippet,
+                    occurrences: hit.occurrences,
+                    dismissed: is_dismissed,
+                });
+            }
+        }
+    }
+
+    findings.sort_by(|a, b| {
+        position[a.chapter_id.as_str()]
+            .cmp(&position[b.chapter_id.as_str()])
+            .then(a.offset.cmp(&b.offset))
+    });
+
+    Ok(findings)
+}
+
+/// 忽略某个连续性问题（之后的检查中不再出现）
+#[tauri::command]
+pub async fn dismiss_continuity_finding(
+    storage_path: String,
+    finding_key: String,
+    note: Option<String>,
+) -> Result<(), String> {
+    let conn = open_book(&storage_path)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO continuity_dismissals (finding_key, note, dismissed_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(finding_key) DO UPDATE SET note = ?2, dismissed_at = ?3",
+        params![finding_key, note, now],
+    )
+    .map_err(|e| format!("忽略问题失败: {}", e))?;
+    Ok(())
+}
+
+/// 取消忽略
+#[tauri::command]
+pub async fn undismiss_continuity_finding(storage_path: String, finding_key: String) -> Result<(), String> {
+    let conn = open_book(&storage_path)?;
+    conn.execute(
+        "DELETE FROM continuity_dismissals WHERE finding_key = ?1",
+        params![finding_key],
+    )
+    .map_err(|e| format!("取消忽略失败: {}", e))?;
+    Ok(())
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+/// (阅读位置, 章节 ID, 变更后状态)
+type StatusChange = (usize, String, String);
+
+struct NameHit {
+    name: String,
+    offset: i64,
+    length: i64,
+    snippet: String,
+    occurrences: i64,
+}
+
+fn load_dismissed(conn: &rusqlite::Connection) -> Result<HashSet<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT finding_key FROM continuity_dismissals")
+        .map_err(|e| format!("查询忽略记录失败: {}", e))?;
+    let keys = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("读取忽略记录失败: {}", e))?
+        .collect::<Result<HashSet<String>, _>>()
+        .map_err(|e| format!("解析忽略记录失败: {}", e))?;
+    Ok(keys)
+}
+
+/// 章节 ID → (章节名, 正文)
+fn load_chapters(conn: &rusqlite::Connection) -> Result<HashMap<String, (String, String)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name, content FROM chapters")
+        .map_err(|e| format!("查询章节失败: {}", e))?;
+    let chapters = stmt
+        .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+        .map_err(|e| format!("读取章节失败: {}", e))?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| format!("解析章节失败: {}", e))?;
+    Ok(chapters)
+}
+
+/// 实体 ID → 本名 + 全部别名
+fn load_names(conn: &rusqlite::Connection) -> Result<HashMap<String, Vec<String>>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, name FROM entities
+             UNION ALL
+             SELECT entity_id, alias FROM entity_aliases",
+        )
+        .map_err(|e| format!("查询实体名称失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("读取实体名称失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析实体名称失败: {}", e))?;
+
+    let mut names: HashMap<String, Vec<String>> = HashMap::new();
+    for (id, name) in rows {
+        if !name.is_empty() {
+            names.entry(id).or_default().push(name);
+        }
+    }
+    Ok(names)
+}
+
+/// 实体全部名称编译为一个按长度降序的正则（"林动(少年)"优先于"林动"）；没有名称时为 None
+fn name_matcher(names: &[String]) -> Result<Option<regex::Regex>, String> {
+    let mut names: Vec<&str> = names.iter().map(String::as_str).filter(|n| !n.is_empty()).collect();
+    if names.is_empty() {
+        return Ok(None);
+    }
+    names.sort_by_key(|n| std::cmp::Reverse(n.len()));
+    let pattern = names.iter().map(|n| regex::escape(n)).collect::<Vec<_>>().join("|");
+    regex::Regex::new(&pattern)
+        .map(Some)
+        .map_err(|e| format!("构建名称匹配失败: {}", e))
+}
+
+/// 在正文中查找任一名称的最早出现位置；命中次数不重复计入被长名称包含的短名称
+fn find_first(content: &str, matcher: &regex::Regex) -> Option<NameHit> {
+    let first = matcher.find(content)?;
+    let occurrences = matcher.find_iter(content).count() as i64;
+    let (byte_pos, name) = (first.start(), first.as_str());
+    let offset = content[..byte_pos].chars().count();
+    let length = name.chars().count();
+    let start = offset.saturating_sub(20);
+    let snippet: String = content.chars().skip(start).take(length + 60).collect();
+
+    Some(NameHit {
+        name: name.to_string(),
+        offset: offset as i64,
+        length: length as i64,
+        snippet,
+        occurrences,
+    })
+}
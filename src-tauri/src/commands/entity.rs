@@ -1,7 +1,10 @@
 use crate::db;
 use crate::db::config;
-use crate::db::models::Entity;
+use crate::commands::chapter;
+use crate::db::models::{Entity, EntityAlias};
+use crate::text::pinyin;
 use rusqlite::params;
+use std::collections::{HashMap, HashSet};
 
 fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
     let cfg = config::load_config()?;
@@ -157,7 +160,10 @@ pub async fn delete_entity(storage_path: String, id: String) -> Result<(), Strin
             "SELECT json_object('id', id, 'name', name, 'entity_type', entity_type,
              'attributes_json', attributes_json, 'status', status, 'inbox', inbox,
              'first_chapter_id', first_chapter_id, 'last_chapter_id', last_chapter_id,
-             'created_at', created_at, 'updated_at', updated_at) FROM entities WHERE id = ?1",
+             'created_by', created_by, 'created_at', created_at, 'updated_at', updated_at,
+             'aliases', json((SELECT json_group_array(alias) FROM entity_aliases WHERE entity_id = entities.id)),
+             'foreshadow_ids', json((SELECT json_group_array(foreshadow_id) FROM foreshadow_entities WHERE entity_id = entities.id)))
+             FROM entities WHERE id = ?1",
             params![id],
             |row| row.get(0),
         )
@@ -174,8 +180,744 @@ pub async fn delete_entity(storage_path: String, id: String) -> Result<(), Strin
     conn.execute("DELETE FROM timeline WHERE entity_id = ?1", params![id])
         .map_err(|e| format!("删除时间线失败: {}", e))?;
 
+    conn.execute("DELETE FROM entity_aliases WHERE entity_id = ?1", params![id])
+        .map_err(|e| format!("删除别名失败: {}", e))?;
+
+    conn.execute("DELETE FROM foreshadow_entities WHERE entity_id = ?1", params![id])
+        .map_err(|e| format!("删除伏笔关联失败: {}", e))?;
+
     conn.execute("DELETE FROM entities WHERE id = ?1", params![id])
         .map_err(|e| format!("删除实体失败: {}", e))?;
 
     Ok(())
 }
+
+// ============================================================================
+// 别名
+// ============================================================================
+
+/// 获取实体的别名列表
+#[tauri::command]
+pub async fn list_entity_aliases(storage_path: String, entity_id: String) -> Result<Vec<EntityAlias>, String> {
+    let conn = open_book(&storage_path)?;
+    load_aliases(&conn, &entity_id)
+}
+
+/// 为实体添加别名
+#[tauri::command]
+pub async fn add_entity_alias(
+    storage_path: String,
+    entity_id: String,
+    alias: String,
+) -> Result<EntityAlias, String> {
+    let conn = open_book(&storage_path)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO entity_aliases (id, entity_id, alias, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![id, entity_id, alias, now],
+    )
+    .map_err(|e| format!("添加别名失败: {}", e))?;
+
+    Ok(EntityAlias {
+        id,
+        entity_id,
+        alias,
+        created_at: now,
+    })
+}
+
+/// 删除别名
+#[tauri::command]
+pub async fn remove_entity_alias(storage_path: String, id: String) -> Result<(), String> {
+    let conn = open_book(&storage_path)?;
+    conn.execute("DELETE FROM entity_aliases WHERE id = ?1", params![id])
+        .map_err(|e| format!("删除别名失败: {}", e))?;
+    Ok(())
+}
+
+// ============================================================================
+// 合并重复实体
+// ============================================================================
+
+/// 属性冲突（两个实体在同一属性路径上取值不同）
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AttributeConflict {
+    /// 属性路径，嵌套对象用 "." 连接（如 "境界" 或 "外貌.发色"）
+    pub path: String,
+    pub survivor_value: serde_json::Value,
+    pub loser_value: serde_json::Value,
+    /// 最终写入保留方的值
+    pub resolved_value: serde_json::Value,
+}
+
+/// 合并结果
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EntityMergeResult {
+    pub entity: Entity,
+    pub conflicts: Vec<AttributeConflict>,
+    /// 被合并方移入回收站的记录 ID（从回收站恢复即可撤销合并）
+    pub trash_id: String,
+}
+
+/// 合并两个实体：loser 并入 survivor
+///
+/// - 属性 JSON 取并集：对象递归合并，数组去重追加，冲突项默认保留 survivor 的值，
+///   可通过 `resolutions_json`（`{"属性路径": 值}`）逐项指定
+/// - loser 的时间线节点、别名、伏笔关联移到 survivor，loser 的名称成为 survivor 的别名
+/// - 重新计算首次/最后出场章节
+/// - loser 移入回收站，记录合并前的全部状态，从回收站恢复即撤销合并
+#[tauri::command]
+pub async fn merge_entities(
+    storage_path: String,
+    survivor_id: String,
+    loser_id: String,
+    resolutions_json: Option<String>,
+) -> Result<EntityMergeResult, String> {
+    if survivor_id == loser_id {
+        return Err("不能将实体与自身合并".into());
+    }
+
+    let mut conn = open_book(&storage_path)?;
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    let survivor = load_entity(&tx, &survivor_id)?;
+    let loser = load_entity(&tx, &loser_id)?;
+    let loser_created_by: String = tx
+        .query_row("SELECT created_by FROM entities WHERE id = ?1", params![loser_id], |row| row.get(0))
+        .map_err(|e| format!("获取实体失败: {}", e))?;
+
+    let resolutions: serde_json::Map<String, serde_json::Value> = match resolutions_json {
+        Some(r) => serde_json::from_str(&r).map_err(|e| format!("解析冲突处理方案失败: {}", e))?,
+        None => serde_json::Map::new(),
+    };
+
+    // 1. 合并属性
+    let survivor_attrs = parse_attributes(&survivor)?;
+    let loser_attrs = parse_attributes(&loser)?;
+    let mut conflicts = Vec::new();
+    let merged_attrs = merge_json("", &survivor_attrs, &loser_attrs, &resolutions, &mut conflicts);
+
+    // 2. 迁移时间线节点
+    let moved_timeline_ids = query_ids(&tx, "SELECT id FROM timeline WHERE entity_id = ?1", &loser_id)?;
+    tx.execute(
+        "UPDATE timeline SET entity_id = ?1 WHERE entity_id = ?2",
+        params![survivor_id, loser_id],
+    )
+    .map_err(|e| format!("迁移时间线失败: {}", e))?;
+
+    // 3. 迁移别名，并把 loser 的名称登记为别名
+    let moved_alias_ids = query_ids(&tx, "SELECT id FROM entity_aliases WHERE entity_id = ?1", &loser_id)?;
+    tx.execute(
+        "UPDATE entity_aliases SET entity_id = ?1 WHERE entity_id = ?2",
+        params![survivor_id, loser_id],
+    )
+    .map_err(|e| format!("迁移别名失败: {}", e))?;
+
+    // survivor 已有同名别名（含刚迁移过来的）时不重复登记
+    let alias_exists: bool = tx
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM entity_aliases WHERE entity_id = ?1 AND alias = ?2)",
+            params![survivor_id, loser.name],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("查询别名失败: {}", e))?;
+    let mut added_alias_id = None;
+    if loser.name != survivor.name && !alias_exists {
+        let alias_id = uuid::Uuid::new_v4().to_string();
+        tx.execute(
+            "INSERT INTO entity_aliases (id, entity_id, alias, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![alias_id, survivor_id, loser.name, now],
+        )
+        .map_err(|e| format!("添加别名失败: {}", e))?;
+        added_alias_id = Some(alias_id);
+    }
+
+    // 4. 迁移伏笔关联（survivor 已关联的伏笔不重复添加）
+    let loser_foreshadow_ids = query_ids(
+        &tx,
+        "SELECT foreshadow_id FROM foreshadow_entities WHERE entity_id = ?1",
+        &loser_id,
+    )?;
+    let survivor_foreshadow_ids: HashSet<String> = query_ids(
+        &tx,
+        "SELECT foreshadow_id FROM foreshadow_entities WHERE entity_id = ?1",
+        &survivor_id,
+    )?
+    .into_iter()
+    .collect();
+    let added_foreshadow_ids: Vec<&String> = loser_foreshadow_ids
+        .iter()
+        .filter(|id| !survivor_foreshadow_ids.contains(*id))
+        .collect();
+    for foreshadow_id in &added_foreshadow_ids {
+        tx.execute(
+            "INSERT INTO foreshadow_entities (foreshadow_id, entity_id, created_at) VALUES (?1, ?2, ?3)",
+            params![foreshadow_id, survivor_id, now],
+        )
+        .map_err(|e| format!("迁移伏笔关联失败: {}", e))?;
+    }
+    tx.execute("DELETE FROM foreshadow_entities WHERE entity_id = ?1", params![loser_id])
+        .map_err(|e| format!("迁移伏笔关联失败: {}", e))?;
+
+    // 5. 重新计算首次/最后出场
+    let (first_chapter_id, last_chapter_id) = recompute_appearance(&tx, &survivor, &loser)?;
+
+    let merged_attrs_str = merged_attrs.to_string();
+    tx.execute(
+        "UPDATE entities SET attributes_json = ?1, first_chapter_id = ?2, last_chapter_id = ?3, updated_at = ?4 WHERE id = ?5",
+        params![merged_attrs_str, first_chapter_id, last_chapter_id, now, survivor_id],
+    )
+    .map_err(|e| format!("更新合并后实体失败: {}", e))?;
+
+    // 6. loser 移入回收站（附带撤销合并所需的数据）
+    let data = serde_json::json!({
+        "id": loser.id,
+        "name": loser.name,
+        "entity_type": loser.entity_type,
+        "attributes_json": loser.attributes_json,
+        "status": loser.status,
+        "inbox": loser.inbox as i64,
+        "first_chapter_id": loser.first_chapter_id,
+        "last_chapter_id": loser.last_chapter_id,
+        "created_by": loser_created_by,
+        "created_at": loser.created_at,
+        "updated_at": loser.updated_at,
+        "merge": {
+            "survivor_id": survivor.id,
+            "survivor_attributes_json": survivor.attributes_json,
+            "survivor_first_chapter_id": survivor.first_chapter_id,
+            "survivor_last_chapter_id": survivor.last_chapter_id,
+            "merged_attributes_json": merged_attrs_str,
+            "merged_first_chapter_id": first_chapter_id,
+            "merged_last_chapter_id": last_chapter_id,
+            "moved_timeline_ids": moved_timeline_ids,
+            "moved_alias_ids": moved_alias_ids,
+            "added_alias_id": added_alias_id,
+            "loser_foreshadow_ids": loser_foreshadow_ids,
+            "added_foreshadow_ids": added_foreshadow_ids,
+        },
+    });
+
+    let trash_id = uuid::Uuid::new_v4().to_string();
+    tx.execute(
+        "INSERT INTO trash (id, original_table, original_id, data_json, deleted_at, deleted_by) VALUES (?1, 'entities', ?2, ?3, ?4, 'user')",
+        params![trash_id, loser_id, data.to_string(), now],
+    )
+    .map_err(|e| format!("移入回收站失败: {}", e))?;
+
+    tx.execute("DELETE FROM entities WHERE id = ?1", params![loser_id])
+        .map_err(|e| format!("删除被合并实体失败: {}", e))?;
+
+    let entity = load_entity(&tx, &survivor_id)?;
+    tx.commit().map_err(|e| format!("提交合并失败: {}", e))?;
+
+    Ok(EntityMergeResult {
+        entity,
+        conflicts,
+        trash_id,
+    })
+}
+
+/// 撤销合并：恢复被合并方，并把 survivor 还原到合并前的属性与出场章节
+///
+/// 由 `restore_from_trash` 在回收站数据带有 `merge` 字段时调用。合并后 survivor 的属性或出场章节被修改时拒绝撤销。
+pub(crate) fn undo_merge(conn: &rusqlite::Connection, data: &serde_json::Value) -> Result<(), String> {
+    let merge = &data["merge"];
+    let loser_id = data["id"].as_str().unwrap_or_default();
+    let survivor_id = merge["survivor_id"].as_str().unwrap_or_default();
+    let now = chrono::Utc::now().to_rfc3339();
+
+    let current: (String, Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT attributes_json, first_chapter_id, last_chapter_id FROM entities WHERE id = ?1",
+            params![survivor_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| format!("获取保留实体失败: {}", e))?;
+    let merged = (
+        merge["merged_attributes_json"].as_str().unwrap_or_default(),
+        merge["merged_first_chapter_id"].as_str(),
+        merge["merged_last_chapter_id"].as_str(),
+    );
+    if (current.0.as_str(), current.1.as_deref(), current.2.as_deref()) != merged {
+        return Err("合并后的实体已修改，无法撤销".into());
+    }
+
+    conn.execute(
+        "INSERT OR REPLACE INTO entities (id, name, entity_type, attributes_json, status, inbox, first_chapter_id, last_chapter_id, created_by, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            loser_id,
+            data["name"].as_str().unwrap_or_default(),
+            data["entity_type"].as_str().unwrap_or_default(),
+            data["attributes_json"].as_str().unwrap_or("{}"),
+            data["status"].as_str().unwrap_or("alive"),
+            data["inbox"].as_i64().unwrap_or(0),
+            data["first_chapter_id"].as_str(),
+            data["last_chapter_id"].as_str(),
+            data["created_by"].as_str().unwrap_or("user"),
+            data["created_at"].as_str().unwrap_or_default(),
+            data["updated_at"].as_str().unwrap_or_default(),
+        ],
+    )
+    .map_err(|e| format!("恢复被合并实体失败: {}", e))?;
+
+    for id in merge["moved_timeline_ids"].as_array().into_iter().flatten() {
+        conn.execute(
+            "UPDATE timeline SET entity_id = ?1 WHERE id = ?2",
+            params![loser_id, id.as_str().unwrap_or_default()],
+        )
+        .map_err(|e| format!("还原时间线失败: {}", e))?;
+    }
+
+    for id in merge["moved_alias_ids"].as_array().into_iter().flatten() {
+        conn.execute(
+            "UPDATE entity_aliases SET entity_id = ?1 WHERE id = ?2",
+            params![loser_id, id.as_str().unwrap_or_default()],
+        )
+        .map_err(|e| format!("还原别名失败: {}", e))?;
+    }
+
+    for id in merge["added_foreshadow_ids"].as_array().into_iter().flatten() {
+        conn.execute(
+            "DELETE FROM foreshadow_entities WHERE foreshadow_id = ?1 AND entity_id = ?2",
+            params![id.as_str().unwrap_or_default(), survivor_id],
+        )
+        .map_err(|e| format!("还原伏笔关联失败: {}", e))?;
+    }
+
+    for id in merge["loser_foreshadow_ids"].as_array().into_iter().flatten() {
+        conn.execute(
+            "INSERT OR IGNORE INTO foreshadow_entities (foreshadow_id, entity_id, created_at) VALUES (?1, ?2, ?3)",
+            params![id.as_str().unwrap_or_default(), loser_id, now],
+        )
+        .map_err(|e| format!("还原伏笔关联失败: {}", e))?;
+    }
+
+    if let Some(alias_id) = merge["added_alias_id"].as_str() {
+        conn.execute("DELETE FROM entity_aliases WHERE id = ?1", params![alias_id])
+            .map_err(|e| format!("删除合并别名失败: {}", e))?;
+    }
+
+    conn.execute(
+        "UPDATE entities SET attributes_json = ?1, first_chapter_id = ?2, last_chapter_id = ?3, updated_at = ?4 WHERE id = ?5",
+        params![
+            merge["survivor_attributes_json"].as_str().unwrap_or("{}"),
+            merge["survivor_first_chapter_id"].as_str(),
+            merge["survivor_last_chapter_id"].as_str(),
+            now,
+            survivor_id,
+        ],
+    )
+    .map_err(|e| format!("还原保留实体失败: {}", e))?;
+
+    Ok(())
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+fn load_entity(conn: &rusqlite::Connection, id: &str) -> Result<Entity, String> {
+    conn.query_row(
+        "SELECT id, name, entity_type, attributes_json, status, inbox, first_chapter_id, last_chapter_id, created_at, updated_at
+         FROM entities WHERE id = ?1",
+        params![id],
+        |row| {
+            Ok(Entity {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                entity_type: row.get(2)?,
+                attributes_json: row.get(3)?,
+                status: row.get(4)?,
+                inbox: row.get::<_, i32>(5)? != 0,
+                first_chapter_id: row.get(6)?,
+                last_chapter_id: row.get(7)?,
+                created_at: row.get(8)?,
+                updated_at: row.get(9)?,
+            })
+        },
+    )
+    .map_err(|e| format!("获取实体失败: {}", e))
+}
+
+fn load_aliases(conn: &rusqlite::Connection, entity_id: &str) -> Result<Vec<EntityAlias>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, entity_id, alias, created_at FROM entity_aliases
+             WHERE entity_id = ?1 ORDER BY created_at ASC",
+        )
+        .map_err(|e| format!("查询别名失败: {}", e))?;
+
+    let aliases = stmt
+        .query_map(params![entity_id], |row| {
+            Ok(EntityAlias {
+                id: row.get(0)?,
+                entity_id: row.get(1)?,
+                alias: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })
+        .map_err(|e| format!("读取别名失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析别名失败: {}", e))?;
+
+    Ok(aliases)
+}
+
+fn query_ids(conn: &rusqlite::Connection, sql: &str, key: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| format!("查询失败: {}", e))?;
+    let ids = stmt
+        .query_map(params![key], |row| row.get(0))
+        .map_err(|e| format!("读取失败: {}", e))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| format!("解析失败: {}", e))?;
+    Ok(ids)
+}
+
+fn parse_attributes(entity: &Entity) -> Result<serde_json::Value, String> {
+    if entity.attributes_json.trim().is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+    serde_json::from_str(&entity.attributes_json)
+        .map_err(|e| format!("解析实体「{}」属性失败: {}", entity.name, e))
+}
+
+/// 递归合并属性 JSON，记录冲突
+fn merge_json(
+    path: &str,
+    survivor: &serde_json::Value,
+    loser: &serde_json::Value,
+    resolutions: &serde_json::Map<String, serde_json::Value>,
+    conflicts: &mut Vec<AttributeConflict>,
+) -> serde_json::Value {
+    use serde_json::Value;
+
+    match (survivor, loser) {
+        (Value::Object(s), Value::Object(l)) => {
+            let mut merged = s.clone();
+            for (key, l_val) in l {
+                let child_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                let value = match s.get(key) {
+                    Some(s_val) => merge_json(&child_path, s_val, l_val, resolutions, conflicts),
+                    None => l_val.clone(),
+                };
+                merged.insert(key.clone(), value);
+            }
+            Value::Object(merged)
+        }
+        (Value::Array(s), Value::Array(l)) => {
+            let mut merged = s.clone();
+            for item in l {
+                if !merged.contains(item) {
+                    merged.push(item.clone());
+                }
+            }
+            Value::Array(merged)
+        }
+        (s, l) if s == l => s.clone(),
+        (Value::Null, l) => l.clone(),
+        (s, Value::Null) => s.clone(),
+        (s, l) => {
+            let resolved = resolutions.get(path).cloned().unwrap_or_else(|| s.clone());
+            conflicts.push(AttributeConflict {
+                path: path.to_string(),
+                survivor_value: s.clone(),
+                loser_value: l.clone(),
+                resolved_value: resolved.clone(),
+            });
+            resolved
+        }
+    }
+}
+
+/// 重新计算合并后实体的首次/最后出场章节
+///
+/// 候选章节：双方原有的首末章节、合并后的时间线节点所在章节，
+/// 以及正文中出现 survivor 名称或任一别名的章节；按全书阅读顺序取首尾。
+fn recompute_appearance(
+    conn: &rusqlite::Connection,
+    survivor: &Entity,
+    loser: &Entity,
+) -> Result<(Option<String>, Option<String>), String> {
+    let order = chapter::reading_order(conn)?;
+
+    let mut candidates: HashSet<String> = [
+        &survivor.first_chapter_id,
+        &survivor.last_chapter_id,
+        &loser.first_chapter_id,
+        &loser.last_chapter_id,
+    ]
+    .into_iter()
+    .flatten()
+    .cloned()
+    .collect();
+
+    candidates.extend(query_ids(
+        conn,
+        "SELECT DISTINCT chapter_id FROM timeline WHERE entity_id = ?1",
+        &survivor.id,
+    )?);
+
+    let mut names = vec![survivor.name.clone()];
+    names.extend(load_aliases(conn, &survivor.id)?.into_iter().map(|a| a.alias));
+    for name in names.iter().filter(|n| !n.is_empty()) {
+        candidates.extend(query_ids(
+            conn,
+            "SELECT id FROM chapters WHERE instr(content, ?1) > 0",
+            name,
+        )?);
+    }
+
+    let first = order.iter().find(|id| candidates.contains(*id)).cloned();
+    let last = order.iter().rev().find(|id| candidates.contains(*id)).cloned();
+    Ok((first, last))
+}
+
+// ============================================================================
+// 搜索与筛选
+// ============================================================================
+
+/// 实体查询条件（所有字段可选，组合使用）
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct EntityQuery {
+    /// 关键字：匹配本名/别名子串，或拼音首字母（如 "ld" 匹配"林动"）
+    pub keyword: Option<String>,
+    pub entity_type: Option<String>,
+    /// alive / dead 等
+    pub status: Option<String>,
+    pub inbox: Option<bool>,
+    /// 属性值筛选：`{"境界": "金丹", "外貌.发色": "黑"}`，嵌套属性用 "." 连接
+    pub attributes: HashMap<String, String>,
+    /// 出场范围：与 [from, to] 章节区间（阅读顺序）有交集的实体
+    pub appears_from_chapter_id: Option<String>,
+    pub appears_to_chapter_id: Option<String>,
+    /// created_at（默认）/ updated_at / name / first_appearance / last_appearance / mention_count
+    pub sort_by: Option<String>,
+    pub sort_desc: Option<bool>,
+    pub offset: Option<i64>,
+    /// 默认 50
+    pub limit: Option<i64>,
+}
+
+/// 搜索结果中的单个实体
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EntityListItem {
+    #[serde(flatten)]
+    pub entity: Entity,
+    pub aliases: Vec<String>,
+    /// 全书正文中本名与别名的出现次数（仅按 mention_count 排序时计算）
+    pub mention_count: Option<i64>,
+}
+
+/// 分页结果
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EntityPage {
+    pub items: Vec<EntityListItem>,
+    /// 筛选后的总数（分页前）
+    pub total: i64,
+}
+
+/// 搜索/筛选实体（支持拼音首字母、属性值、出场范围、排序与分页）
+#[tauri::command]
+pub async fn search_entities(storage_path: String, query: EntityQuery) -> Result<EntityPage, String> {
+    let conn = open_book(&storage_path)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, name, entity_type, attributes_json, status, inbox, first_chapter_id, last_chapter_id, created_at, updated_at
+             FROM entities
+             WHERE (?1 IS NULL OR entity_type = ?1)
+               AND (?2 IS NULL OR status = ?2)
+               AND (?3 IS NULL OR inbox = ?3)",
+        )
+        .map_err(|e| format!("查询实体失败: {}", e))?;
+
+    let entities = stmt
+        .query_map(
+            params![query.entity_type, query.status, query.inbox.map(|i| i as i32)],
+            |row| {
+                Ok(Entity {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    entity_type: row.get(2)?,
+                    attributes_json: row.get(3)?,
+                    status: row.get(4)?,
+                    inbox: row.get::<_, i32>(5)? != 0,
+                    first_chapter_id: row.get(6)?,
+                    last_chapter_id: row.get(7)?,
+                    created_at: row.get(8)?,
+                    updated_at: row.get(9)?,
+                })
+            },
+        )
+        .map_err(|e| format!("读取实体失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析实体失败: {}", e))?;
+
+    let mut aliases = load_all_aliases(&conn)?;
+    let order = chapter::reading_order(&conn)?;
+    let position: HashMap<&str, usize> = order.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+    let pos_of = |id: &Option<String>| id.as_deref().and_then(|id| position.get(id).copied());
+
+    let keyword = query.keyword.as_deref().map(str::trim).filter(|k| !k.is_empty()).map(str::to_lowercase);
+    let range = match (&query.appears_from_chapter_id, &query.appears_to_chapter_id) {
+        (None, None) => None,
+        (from, to) => Some((pos_of(from).unwrap_or(0), pos_of(to).unwrap_or(usize::MAX))),
+    };
+
+    let mut items: Vec<EntityListItem> = entities
+        .into_iter()
+        .map(|entity| EntityListItem {
+            aliases: aliases.remove(&entity.id).unwrap_or_default(),
+            entity,
+            mention_count: None,
+        })
+        .filter(|item| {
+            keyword
+                .as_deref()
+                .is_none_or(|k| matches_keyword(&item.entity.name, &item.aliases, k))
+        })
+        .filter(|item| matches_attributes(&item.entity.attributes_json, &query.attributes))
+        .filter(|item| {
+            range.is_none_or(|(from, to)| {
+                match (pos_of(&item.entity.first_chapter_id), pos_of(&item.entity.last_chapter_id)) {
+                    (Some(first), Some(last)) => first <= to && last >= from,
+                    (Some(p), None) | (None, Some(p)) => p >= from && p <= to,
+                    (None, None) => false,
+                }
+            })
+        })
+        .collect();
+
+    let sort_by = query.sort_by.as_deref().unwrap_or("created_at");
+    if sort_by == "mention_count" {
+        let counts = count_mentions(&conn, &items)?;
+        for item in &mut items {
+            item.mention_count = Some(counts.get(&item.entity.id).copied().unwrap_or(0));
+        }
+    }
+
+    // 未出场的实体在升序与降序中都排在最后
+    let appearance_key = |pos: Option<usize>, desc: bool| match (pos, desc) {
+        (Some(p), false) => p as i64,
+        (Some(p), true) => -(p as i64),
+        (None, _) => i64::MAX,
+    };
+    let desc = query.sort_desc.unwrap_or(sort_by == "created_at" || sort_by == "mention_count");
+    match sort_by {
+        "first_appearance" => items.sort_by_key(|i| appearance_key(pos_of(&i.entity.first_chapter_id), desc)),
+        "last_appearance" => items.sort_by_key(|i| appearance_key(pos_of(&i.entity.last_chapter_id), desc)),
+        _ => {
+            items.sort_by(|a, b| match sort_by {
+                "name" => a.entity.name.cmp(&b.entity.name),
+                "updated_at" => a.entity.updated_at.cmp(&b.entity.updated_at),
+                "mention_count" => a.mention_count.cmp(&b.mention_count),
+                _ => a.entity.created_at.cmp(&b.entity.created_at),
+            });
+            if desc {
+                items.reverse();
+            }
+        }
+    }
+
+    let total = items.len() as i64;
+    let offset = query.offset.unwrap_or(0).max(0) as usize;
+    let limit = query.limit.unwrap_or(50).max(0) as usize;
+    let items = items.into_iter().skip(offset).take(limit).collect();
+
+    Ok(EntityPage { items, total })
+}
+
+fn load_all_aliases(conn: &rusqlite::Connection) -> Result<HashMap<String, Vec<String>>, String> {
+    let mut stmt = conn
+        .prepare("SELECT entity_id, alias FROM entity_aliases ORDER BY created_at ASC")
+        .map_err(|e| format!("查询别名失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("读取别名失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析别名失败: {}", e))?;
+
+    let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+    for (entity_id, alias) in rows {
+        aliases.entry(entity_id).or_default().push(alias);
+    }
+    Ok(aliases)
+}
+
+/// 关键字匹配：名称子串（不区分大小写），或拼音首字母子串
+fn matches_keyword(name: &str, aliases: &[String], keyword: &str) -> bool {
+    let is_initials = keyword.chars().all(|c| c.is_ascii_alphanumeric());
+    std::iter::once(name).chain(aliases.iter().map(String::as_str)).any(|n| {
+        n.to_lowercase().contains(keyword) || (is_initials && pinyin::initials(n).contains(keyword))
+    })
+}
+
+/// 属性值筛选：每个条件的路径都存在且值相等（非字符串值按 JSON 文本比较）
+fn matches_attributes(attributes_json: &str, filters: &HashMap<String, String>) -> bool {
+    if filters.is_empty() {
+        return true;
+    }
+    let Ok(attrs) = serde_json::from_str::<serde_json::Value>(attributes_json) else {
+        return false;
+    };
+    filters.iter().all(|(path, expected)| {
+        let value = path.split('.').try_fold(&attrs, |v, key| v.get(key));
+        match value {
+            Some(serde_json::Value::String(s)) => s == expected,
+            Some(serde_json::Value::Array(arr)) => arr.iter().any(|v| v.as_str() == Some(expected.as_str())),
+            Some(v) => v.to_string().as_str() == expected,
+            None => false,
+        }
+    })
+}
+
+/// 统计每个实体的本名与别名在全书正文中的出现次数
+///
+/// 所有名称编译为一个按长度降序的正则，逐章扫描一次；"林动(少年)"命中时不再重复计入"林动"。
+fn count_mentions(
+    conn: &rusqlite::Connection,
+    items: &[EntityListItem],
+) -> Result<HashMap<String, i64>, String> {
+    let mut owners: HashMap<&str, Vec<&str>> = HashMap::new();
+    for item in items {
+        for name in std::iter::once(&item.entity.name).chain(item.aliases.iter()) {
+            if !name.is_empty() {
+                owners.entry(name.as_str()).or_default().push(item.entity.id.as_str());
+            }
+        }
+    }
+    if owners.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut names: Vec<&str> = owners.keys().copied().collect();
+    names.sort_by_key(|n| std::cmp::Reverse(n.len()));
+    let pattern = names.iter().map(|n| regex::escape(n)).collect::<Vec<_>>().join("|");
+    let re = regex::Regex::new(&pattern).map_err(|e| format!("构建名称匹配失败: {}", e))?;
+
+    let mut stmt = conn
+        .prepare("SELECT content FROM chapters")
+        .map_err(|e| format!("查询章节失败: {}", e))?;
+    let mut rows = stmt.query([]).map_err(|e| format!("读取章节失败: {}", e))?;
+
+    let mut counts: HashMap<String, i64> = HashMap::new();
+    while let Some(row) = rows.next().map_err(|e| format!("读取章节失败: {}", e))? {
+        let content: String = row.get(0).map_err(|e| format!("解析章节失败: {}", e))?;
+        for m in re.find_iter(&content) {
+            for id in owners.get(m.as_str()).into_iter().flatten() {
+                *counts.entry(id.to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+    Ok(counts)
+}
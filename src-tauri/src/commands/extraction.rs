@@ -0,0 +1,392 @@
+use crate::commands::ai;
+use crate::db;
+use crate::db::config;
+use crate::db::models::{AiProposal, Entity};
+use crate::llm::cancel::{self, CancelToken};
+use crate::llm::extract::{self, KnownEntity};
+use crate::llm::provider::ChatProvider;
+use rusqlite::params;
+use std::collections::HashMap;
+use tauri::AppHandle;
+
+fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
+    let cfg = config::load_config()?;
+    db::open_book_db(&cfg, storage_path)
+}
+
+/// 一次抽取的结果
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExtractionResult {
+    /// 新实体，已放入 Inbox 待确认
+    pub inbox: Vec<Entity>,
+    /// 已有实体的属性更新与时间线事件，进入待确认队列
+    pub proposals: Vec<AiProposal>,
+}
+
+/// 用 AI 从章节正文中抽取实体信息
+///
+/// 不直接修改已有数据：新实体进入 Inbox，属性更新与时间线事件进入待确认队列。
+#[tauri::command]
+pub async fn extract_entities_ai(
+    app: AppHandle,
+    storage_path: String,
+    request_id: String,
+    chapter_id: String,
+) -> Result<ExtractionResult, String> {
+    let provider = ai::metered_provider(&app, Some(&storage_path), "extraction")?;
+    let registration = cancel::register(&request_id);
+    run_extraction(&provider, &storage_path, &chapter_id, &registration.token).await
+}
+
+/// 获取待确认变更（可按状态、章节过滤，按创建时间排序）
+#[tauri::command]
+pub async fn list_ai_proposals(
+    storage_path: String,
+    status: Option<String>,
+    chapter_id: Option<String>,
+) -> Result<Vec<AiProposal>, String> {
+    let conn = open_book(&storage_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, chapter_id, kind, entity_id, payload_json, status, created_at, resolved_at
+             FROM ai_proposals
+             WHERE (?1 IS NULL OR status = ?1) AND (?2 IS NULL OR chapter_id = ?2)
+             ORDER BY created_at ASC",
+        )
+        .map_err(|e| format!("查询待确认变更失败: {}", e))?;
+    let proposals = stmt
+        .query_map(params![status, chapter_id], map_proposal)
+        .map_err(|e| format!("读取待确认变更失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析待确认变更失败: {}", e))?;
+    Ok(proposals)
+}
+
+/// 采纳一条待确认变更
+///
+/// 属性更新写入实体（原值记入变更的 payload 以便追溯）；时间线事件以 ai 为创建者写入，
+/// 带状态变更的同时更新实体状态。
+#[tauri::command]
+pub async fn accept_ai_proposal(storage_path: String, id: String) -> Result<AiProposal, String> {
+    let mut conn = open_book(&storage_path)?;
+    let proposal = load_proposal(&conn, &id)?;
+    if proposal.status != "pending" {
+        return Err("该变更已处理".into());
+    }
+    let mut payload: serde_json::Value =
+        serde_json::from_str(&proposal.payload_json).map_err(|e| format!("解析变更内容失败: {}", e))?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+    match proposal.kind.as_str() {
+        "attribute_update" => {
+            let attributes_json: String = tx
+                .query_row(
+                    "SELECT attributes_json FROM entities WHERE id = ?1",
+                    params![proposal.entity_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| format!("获取实体失败: {}", e))?;
+            let mut attributes: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(&attributes_json).unwrap_or_default();
+
+            let mut previous = serde_json::Map::new();
+            for (key, value) in payload["attributes"].as_object().cloned().unwrap_or_default() {
+                previous.insert(key.clone(), attributes.get(&key).cloned().unwrap_or(serde_json::Value::Null));
+                attributes.insert(key, value);
+            }
+            payload["previous"] = serde_json::Value::Object(previous);
+
+            tx.execute(
+                "UPDATE entities SET attributes_json = ?1, updated_at = ?2 WHERE id = ?3",
+                params![serde_json::Value::Object(attributes).to_string(), now, proposal.entity_id],
+            )
+            .map_err(|e| format!("更新实体属性失败: {}", e))?;
+        }
+        "timeline_event" => {
+            let status_change = payload["status_change"].as_str();
+            tx.execute(
+                "INSERT INTO timeline (id, entity_id, chapter_id, event, status_change, created_at, created_by)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'ai')",
+                params![
+                    uuid::Uuid::new_v4().to_string(),
+                    proposal.entity_id,
+                    proposal.chapter_id,
+                    payload["event"].as_str().unwrap_or_default(),
+                    status_change,
+                    now,
+                ],
+            )
+            .map_err(|e| format!("写入时间线失败: {}", e))?;
+            if let Some(status) = status_change {
+                tx.execute(
+                    "UPDATE entities SET status = ?1, updated_at = ?2 WHERE id = ?3",
+                    params![status, now, proposal.entity_id],
+                )
+                .map_err(|e| format!("更新实体状态失败: {}", e))?;
+            }
+        }
+        other => return Err(format!("未知的变更类型: {}", other)),
+    }
+
+    tx.execute(
+        "UPDATE ai_proposals SET status = 'accepted', payload_json = ?1, resolved_at = ?2 WHERE id = ?3",
+        params![payload.to_string(), now, id],
+    )
+    .map_err(|e| format!("更新变更状态失败: {}", e))?;
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+
+    load_proposal(&conn, &id)
+}
+
+/// 拒绝一条待确认变更
+#[tauri::command]
+pub async fn reject_ai_proposal(storage_path: String, id: String) -> Result<(), String> {
+    let conn = open_book(&storage_path)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let updated = conn
+        .execute(
+            "UPDATE ai_proposals SET status = 'rejected', resolved_at = ?1 WHERE id = ?2 AND status = 'pending'",
+            params![now, id],
+        )
+        .map_err(|e| format!("拒绝变更失败: {}", e))?;
+    if updated == 0 {
+        return Err("变更不存在或已处理".into());
+    }
+    Ok(())
+}
+
+/// 执行一次抽取并落库（provider 可替换为测试用实现）
+///
+/// 与已有实体（本名或别名）同名的"新实体"按属性更新处理；未变化的属性、
+/// 与已有待确认变更完全相同的提议不重复记录。
+pub async fn run_extraction(
+    provider: &dyn ChatProvider,
+    storage_path: &str,
+    chapter_id: &str,
+    cancel: &CancelToken,
+) -> Result<ExtractionResult, String> {
+    let (name, content, known, mut by_name, mut current) = {
+        let conn = open_book(storage_path)?;
+        let (name, content): (String, String) = conn
+            .query_row(
+                "SELECT name, content FROM chapters WHERE id = ?1",
+                params![chapter_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| format!("获取章节失败: {}", e))?;
+        let (known, by_name, current) = load_known(&conn, &content)?;
+        (name, content, known, by_name, current)
+    };
+    if content.trim().is_empty() {
+        return Err("章节正文为空".into());
+    }
+
+    let extraction = extract::extract(provider, &name, &content, &known, cancel).await?;
+
+    let mut conn = open_book(storage_path)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+    let mut result = ExtractionResult {
+        inbox: Vec::new(),
+        proposals: Vec::new(),
+    };
+
+    let mut updates = extraction.attribute_updates;
+    for entity in extraction.new_entities {
+        if by_name.contains_key(&entity.name) {
+            updates.push(extract::AttributeUpdate {
+                name: entity.name,
+                attributes: entity.attributes,
+            });
+            continue;
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        let attributes_json = serde_json::Value::Object(entity.attributes.clone()).to_string();
+        tx.execute(
+            "INSERT INTO entities (id, name, entity_type, attributes_json, status, inbox, first_chapter_id, last_chapter_id, created_by, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, 'alive', 1, ?5, ?5, 'ai', ?6, ?6)",
+            params![id, entity.name, entity.entity_type, attributes_json, chapter_id, now],
+        )
+        .map_err(|e| format!("创建实体失败: {}", e))?;
+        by_name.insert(entity.name.clone(), id.clone());
+        current.insert(id.clone(), entity.attributes);
+        result.inbox.push(Entity {
+            id,
+            name: entity.name,
+            entity_type: entity.entity_type,
+            attributes_json,
+            status: "alive".into(),
+            inbox: true,
+            first_chapter_id: Some(chapter_id.to_string()),
+            last_chapter_id: Some(chapter_id.to_string()),
+            created_at: now.clone(),
+            updated_at: now.clone(),
+        });
+    }
+
+    for update in updates {
+        let Some(entity_id) = by_name.get(&update.name) else {
+            continue;
+        };
+        let existing = current.get(entity_id);
+        let changed: serde_json::Map<_, _> = update
+            .attributes
+            .into_iter()
+            .filter(|(k, v)| existing.and_then(|attrs| attrs.get(k)) != Some(v))
+            .collect();
+        if changed.is_empty() {
+            continue;
+        }
+        let payload = serde_json::json!({ "attributes": changed });
+        if let Some(p) = insert_proposal(&tx, chapter_id, "attribute_update", entity_id, &payload, &now)? {
+            result.proposals.push(p);
+        }
+    }
+
+    for event in extraction.timeline_events {
+        let Some(entity_id) = by_name.get(&event.name) else {
+            continue;
+        };
+        let payload = serde_json::json!({ "event": event.event, "status_change": event.status_change });
+        if let Some(p) = insert_proposal(&tx, chapter_id, "timeline_event", entity_id, &payload, &now)? {
+            result.proposals.push(p);
+        }
+    }
+
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+    Ok(result)
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+fn map_proposal(row: &rusqlite::Row) -> rusqlite::Result<AiProposal> {
+    Ok(AiProposal {
+        id: row.get(0)?,
+        chapter_id: row.get(1)?,
+        kind: row.get(2)?,
+        entity_id: row.get(3)?,
+        payload_json: row.get(4)?,
+        status: row.get(5)?,
+        created_at: row.get(6)?,
+        resolved_at: row.get(7)?,
+    })
+}
+
+fn load_proposal(conn: &rusqlite::Connection, id: &str) -> Result<AiProposal, String> {
+    conn.query_row(
+        "SELECT id, chapter_id, kind, entity_id, payload_json, status, created_at, resolved_at
+         FROM ai_proposals WHERE id = ?1",
+        params![id],
+        map_proposal,
+    )
+    .map_err(|e| format!("获取待确认变更失败: {}", e))
+}
+
+/// 已有实体对应的三份数据：正文中出现过的实体（提供给模型）、名称/别名 → 实体 ID、实体 ID → 属性
+type KnownEntities = (
+    Vec<KnownEntity>,
+    HashMap<String, String>,
+    HashMap<String, serde_json::Map<String, serde_json::Value>>,
+);
+
+fn load_known(conn: &rusqlite::Connection, content: &str) -> Result<KnownEntities, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name, entity_type, attributes_json FROM entities")
+        .map_err(|e| format!("查询实体失败: {}", e))?;
+    let entities = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })
+        .map_err(|e| format!("读取实体失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析实体失败: {}", e))?;
+
+    let mut stmt = conn
+        .prepare("SELECT entity_id, alias FROM entity_aliases")
+        .map_err(|e| format!("查询别名失败: {}", e))?;
+    let aliases = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("读取别名失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析别名失败: {}", e))?;
+
+    let mut by_name: HashMap<String, String> = HashMap::new();
+    let mut names: HashMap<String, Vec<String>> = HashMap::new();
+    for (id, name, _, _) in &entities {
+        by_name.insert(name.clone(), id.clone());
+        names.entry(id.clone()).or_default().push(name.clone());
+    }
+    for (entity_id, alias) in aliases {
+        by_name.entry(alias.clone()).or_insert_with(|| entity_id.clone());
+        names.entry(entity_id).or_default().push(alias);
+    }
+
+    let mut known = Vec::new();
+    let mut current = HashMap::new();
+    for (id, name, entity_type, attributes_json) in entities {
+        let attributes: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&attributes_json).unwrap_or_default();
+        let mentioned = names
+            .get(&id)
+            .is_some_and(|ns| ns.iter().any(|n| !n.is_empty() && content.contains(n.as_str())));
+        if mentioned {
+            known.push(KnownEntity {
+                name,
+                entity_type,
+                attributes: serde_json::Value::Object(attributes.clone()),
+            });
+        }
+        current.insert(id, attributes);
+    }
+    Ok((known, by_name, current))
+}
+
+/// 写入一条待确认变更；已有完全相同的待确认变更时跳过
+fn insert_proposal(
+    conn: &rusqlite::Connection,
+    chapter_id: &str,
+    kind: &str,
+    entity_id: &str,
+    payload: &serde_json::Value,
+    now: &str,
+) -> Result<Option<AiProposal>, String> {
+    let payload_json = payload.to_string();
+    let duplicate: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM ai_proposals
+             WHERE status = 'pending' AND kind = ?1 AND entity_id = ?2 AND chapter_id = ?3 AND payload_json = ?4",
+            params![kind, entity_id, chapter_id, payload_json],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("查询待确认变更失败: {}", e))?;
+    if duplicate > 0 {
+        return Ok(None);
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO ai_proposals (id, chapter_id, kind, entity_id, payload_json, status, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, 'pending', ?6)",
+        params![id, chapter_id, kind, entity_id, payload_json, now],
+    )
+    .map_err(|e| format!("写入待确认变更失败: {}", e))?;
+
+    Ok(Some(AiProposal {
+        id,
+        chapter_id: chapter_id.to_string(),
+        kind: kind.to_string(),
+        entity_id: entity_id.to_string(),
+        payload_json,
+        status: "pending".into(),
+        created_at: now.to_string(),
+        resolved_at: None,
+    }))
+}
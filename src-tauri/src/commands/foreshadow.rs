@@ -1,7 +1,11 @@
+use crate::commands::chapter;
 use crate::db;
 use crate::db::config;
-use crate::db::models::Foreshadow;
+use crate::db::models::{Entity, Foreshadow, ForeshadowAnchor, ForeshadowHistory};
+use crate::text::anchor;
 use rusqlite::params;
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, Emitter};
 
 fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
     let cfg = config::load_config()?;
@@ -14,24 +18,37 @@ pub async fn create_foreshadow(
     storage_path: String,
     description: String,
     plant_chapter_id: Option<String>,
+    planned_reap_chapter_id: Option<String>,
+    deadline_chapters: Option<i64>,
+    priority: Option<i64>,
+    tags_json: Option<String>,
 ) -> Result<Foreshadow, String> {
     let conn = open_book(&storage_path)?;
     let id = uuid::Uuid::new_v4().to_string();
     let now = chrono::Utc::now().to_rfc3339();
+    let priority = priority.unwrap_or(1);
+    let tags_json = tags_json.unwrap_or_else(|| "[]".into());
+    validate_tags(&tags_json)?;
 
     conn.execute(
-        "INSERT INTO foreshadows (id, description, plant_chapter_id, status, created_at, updated_at)
-         VALUES (?1, ?2, ?3, 'open', ?4, ?5)",
-        params![id, description, plant_chapter_id, now, now],
+        "INSERT INTO foreshadows (id, description, plant_chapter_id, status, planned_reap_chapter_id, deadline_chapters, priority, tags_json, created_at, updated_at)
+         VALUES (?1, ?2, ?3, 'open', ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![id, description, plant_chapter_id, planned_reap_chapter_id, deadline_chapters, priority, tags_json, now, now],
     )
     .map_err(|e| format!("创建伏笔失败: {}", e))?;
 
+    record_history(&conn, &id, None, "open", plant_chapter_id.as_deref(), None)?;
+
     Ok(Foreshadow {
         id,
         description,
         plant_chapter_id,
         reap_chapter_id: None,
         status: "open".into(),
+        planned_reap_chapter_id,
+        deadline_chapters,
+        priority,
+        tags_json,
         created_at: now.clone(),
         updated_at: now,
     })
@@ -43,7 +60,8 @@ pub async fn list_foreshadows(storage_path: String) -> Result<Vec<Foreshadow>, S
     let conn = open_book(&storage_path)?;
     let mut stmt = conn
         .prepare(
-            "SELECT id, description, plant_chapter_id, reap_chapter_id, status, created_at, updated_at
+            "SELECT id, description, plant_chapter_id, reap_chapter_id, status,
+             planned_reap_chapter_id, deadline_chapters, priority, tags_json, created_at, updated_at
              FROM foreshadows ORDER BY created_at DESC",
         )
         .map_err(|e| format!("查询伏笔失败: {}", e))?;
@@ -56,8 +74,12 @@ pub async fn list_foreshadows(storage_path: String) -> Result<Vec<Foreshadow>, S
                 plant_chapter_id: row.get(2)?,
                 reap_chapter_id: row.get(3)?,
                 status: row.get(4)?,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
+                planned_reap_chapter_id: row.get(5)?,
+                deadline_chapters: row.get(6)?,
+                priority: row.get(7)?,
+                tags_json: row.get(8)?,
+                created_at: row.get(9)?,
+                updated_at: row.get(10)?,
             })
         })
         .map_err(|e| format!("读取伏笔失败: {}", e))?
@@ -76,19 +98,569 @@ pub async fn resolve_foreshadow(
 ) -> Result<(), String> {
     let conn = open_book(&storage_path)?;
     let now = chrono::Utc::now().to_rfc3339();
+    let from_status = current_status(&conn, &id)?;
+    if from_status == "resolved" {
+        return Err("伏笔已回收".into());
+    }
     conn.execute(
         "UPDATE foreshadows SET status = 'resolved', reap_chapter_id = ?1, updated_at = ?2 WHERE id = ?3",
         params![reap_chapter_id, now, id],
     )
     .map_err(|e| format!("回收伏笔失败: {}", e))?;
+    record_history(&conn, &id, Some(&from_status), "resolved", Some(&reap_chapter_id), None)?;
     Ok(())
 }
 
-/// 删除伏笔
+/// 编辑伏笔；`plant_chapter_id` / `planned_reap_chapter_id` 传空字符串表示清除，
+/// `deadline_chapters` 传 0 表示取消"N 章内回收"期限
 #[tauri::command]
-pub async fn delete_foreshadow(storage_path: String, id: String) -> Result<(), String> {
+#[allow(clippy::too_many_arguments)]
+pub async fn update_foreshadow(
+    storage_path: String,
+    id: String,
+    description: Option<String>,
+    plant_chapter_id: Option<String>,
+    planned_reap_chapter_id: Option<String>,
+    deadline_chapters: Option<i64>,
+    priority: Option<i64>,
+    tags_json: Option<String>,
+) -> Result<(), String> {
+    if let Some(t) = &tags_json {
+        validate_tags(t)?;
+    }
+    let conn = open_book(&storage_path)?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    if let Some(d) = description {
+        conn.execute(
+            "UPDATE foreshadows SET description = ?1, updated_at = ?2 WHERE id = ?3",
+            params![d, now, id],
+        )
+        .map_err(|e| format!("更新伏笔描述失败: {}", e))?;
+    }
+    if let Some(p) = plant_chapter_id {
+        let p = if p.is_empty() { None } else { Some(p) };
+        conn.execute(
+            "UPDATE foreshadows SET plant_chapter_id = ?1, updated_at = ?2 WHERE id = ?3",
+            params![p, now, id],
+        )
+        .map_err(|e| format!("更新埋设章节失败: {}", e))?;
+    }
+    if let Some(p) = planned_reap_chapter_id {
+        let p = if p.is_empty() { None } else { Some(p) };
+        conn.execute(
+            "UPDATE foreshadows SET planned_reap_chapter_id = ?1, updated_at = ?2 WHERE id = ?3",
+            params![p, now, id],
+        )
+        .map_err(|e| format!("更新计划回收章节失败: {}", e))?;
+    }
+    if let Some(n) = deadline_chapters {
+        let n = if n > 0 { Some(n) } else { None };
+        conn.execute(
+            "UPDATE foreshadows SET deadline_chapters = ?1, updated_at = ?2 WHERE id = ?3",
+            params![n, now, id],
+        )
+        .map_err(|e| format!("更新回收期限失败: {}", e))?;
+    }
+    if let Some(p) = priority {
+        conn.execute(
+            "UPDATE foreshadows SET priority = ?1, updated_at = ?2 WHERE id = ?3",
+            params![p, now, id],
+        )
+        .map_err(|e| format!("更新伏笔优先级失败: {}", e))?;
+    }
+    if let Some(t) = tags_json {
+        conn.execute(
+            "UPDATE foreshadows SET tags_json = ?1, updated_at = ?2 WHERE id = ?3",
+            params![t, now, id],
+        )
+        .map_err(|e| format!("更新伏笔标签失败: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// 重新打开已回收的伏笔（清除回收章节，状态回到 open）
+#[tauri::command]
+pub async fn reopen_foreshadow(storage_path: String, id: String, note: Option<String>) -> Result<(), String> {
     let conn = open_book(&storage_path)?;
-    conn.execute("DELETE FROM foreshadows WHERE id = ?1", params![id])
+    let now = chrono::Utc::now().to_rfc3339();
+    let from_status = current_status(&conn, &id)?;
+    if from_status == "open" {
+        return Ok(());
+    }
+    conn.execute(
+        "UPDATE foreshadows SET status = 'open', reap_chapter_id = NULL, updated_at = ?1 WHERE id = ?2",
+        params![now, id],
+    )
+    .map_err(|e| format!("重新打开伏笔失败: {}", e))?;
+    record_history(&conn, &id, Some(&from_status), "open", None, note.as_deref())?;
+    Ok(())
+}
+
+/// 获取伏笔的状态变更历史（按时间正序）
+#[tauri::command]
+pub async fn list_foreshadow_history(
+    storage_path: String,
+    foreshadow_id: String,
+) -> Result<Vec<ForeshadowHistory>, String> {
+    let conn = open_book(&storage_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, foreshadow_id, from_status, to_status, chapter_id, note, created_at
+             FROM foreshadow_history WHERE foreshadow_id = ?1 ORDER BY created_at ASC",
+        )
+        .map_err(|e| format!("查询伏笔历史失败: {}", e))?;
+
+    let items = stmt
+        .query_map(params![foreshadow_id], |row| {
+            Ok(ForeshadowHistory {
+                id: row.get(0)?,
+                foreshadow_id: row.get(1)?,
+                from_status: row.get(2)?,
+                to_status: row.get(3)?,
+                chapter_id: row.get(4)?,
+                note: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })
+        .map_err(|e| format!("读取伏笔历史失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析伏笔历史失败: {}", e))?;
+
+    Ok(items)
+}
+
+/// 删除伏笔（连同锚点与实体关联移入回收站）
+#[tauri::command]
+pub async fn delete_foreshadow(storage_path: String, id: String) -> Result<(), String> {
+    let mut conn = open_book(&storage_path)?;
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    let (data_json, from_status): (String, String) = tx
+        .query_row(
+            "SELECT json_object('id', id, 'description', description,
+             'plant_chapter_id', plant_chapter_id, 'reap_chapter_id', reap_chapter_id,
+             'status', status, 'planned_reap_chapter_id', planned_reap_chapter_id,
+             'deadline_chapters', deadline_chapters, 'priority', priority, 'tags_json', tags_json,
+             'created_at', created_at, 'updated_at', updated_at,
+             'anchors', json((SELECT json_group_array(json_object('id', a.id, 'role', a.role,
+                 'chapter_id', a.chapter_id, 'quote', a.quote, 'char_offset', a.char_offset,
+                 'context_before', a.context_before, 'context_after', a.context_after,
+                 'created_at', a.created_at, 'updated_at', a.updated_at))
+                 FROM foreshadow_anchors a WHERE a.foreshadow_id = foreshadows.id)),
+             'entities', json((SELECT json_group_array(json_object('entity_id', e.entity_id, 'created_at', e.created_at))
+                 FROM foreshadow_entities e WHERE e.foreshadow_id = foreshadows.id))), status
+             FROM foreshadows WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("序列化伏笔失败: {}", e))?;
+
+    let trash_id = uuid::Uuid::new_v4().to_string();
+    tx.execute(
+        "INSERT INTO trash (id, original_table, original_id, data_json, deleted_at, deleted_by) VALUES (?1, 'foreshadows', ?2, ?3, ?4, 'user')",
+        params![trash_id, id, data_json, now],
+    )
+    .map_err(|e| format!("移入回收站失败: {}", e))?;
+
+    tx.execute("DELETE FROM foreshadow_anchors WHERE foreshadow_id = ?1", params![id])
+        .map_err(|e| format!("删除伏笔锚点失败: {}", e))?;
+    tx.execute("DELETE FROM foreshadow_entities WHERE foreshadow_id = ?1", params![id])
+        .map_err(|e| format!("删除伏笔关联失败: {}", e))?;
+    tx.execute("DELETE FROM foreshadows WHERE id = ?1", params![id])
         .map_err(|e| format!("删除伏笔失败: {}", e))?;
+
+    record_history(&tx, &id, Some(&from_status), "deleted", None, None)?;
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))
+}
+
+// ============================================================================
+// 未回收伏笔报告
+// ============================================================================
+
+/// 未回收伏笔的进度
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OpenForeshadowReport {
+    #[serde(flatten)]
+    pub foreshadow: Foreshadow,
+    /// 埋设后已写章节数（全书最新章节与埋设章节的阅读顺序差），未关联埋设章节时为 None
+    pub chapters_since_plant: Option<i64>,
+    /// 距期限剩余章节数（负数表示已超期），无期限时为 None
+    pub chapters_until_deadline: Option<i64>,
+    pub overdue: bool,
+}
+
+/// 未回收伏笔报告：按章节阅读顺序（分卷 + 章节 sort_order）计算埋设至今的章节数，
+/// 并对照计划回收章节或"N 章内回收"期限标记超期。两者都设置时取较早者。
+///
+/// 排序：超期在前，其次按优先级降序、埋设至今章节数降序。
+#[tauri::command]
+pub async fn foreshadow_report(storage_path: String) -> Result<Vec<OpenForeshadowReport>, String> {
+    let foreshadows = list_foreshadows(storage_path.clone()).await?;
+    let conn = open_book(&storage_path)?;
+
+    let order = chapter::reading_order(&conn)?;
+    let position: HashMap<&str, i64> = order.iter().enumerate().map(|(i, id)| (id.as_str(), i as i64)).collect();
+    let pos_of = |id: &Option<String>| id.as_deref().and_then(|id| position.get(id).copied());
+    let latest = order.len() as i64 - 1;
+
+    let mut report: Vec<OpenForeshadowReport> = foreshadows
+        .into_iter()
+        .filter(|f| f.status == "open")
+        .map(|f| {
+            let plant = pos_of(&f.plant_chapter_id);
+            let by_count = plant.zip(f.deadline_chapters).map(|(p, n)| p + n);
+            let by_chapter = pos_of(&f.planned_reap_chapter_id);
+            let deadline = match (by_count, by_chapter) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            let chapters_until_deadline = deadline.map(|d| d - latest);
+
+            OpenForeshadowReport {
+                chapters_since_plant: plant.map(|p| latest - p),
+                overdue: chapters_until_deadline.is_some_and(|d| d < 0),
+                chapters_until_deadline,
+                foreshadow: f,
+            }
+        })
+        .collect();
+
+    report.sort_by(|a, b| {
+        b.overdue
+            .cmp(&a.overdue)
+            .then(b.foreshadow.priority.cmp(&a.foreshadow.priority))
+            .then(b.chapters_since_plant.cmp(&a.chapters_since_plant))
+    });
+
+    Ok(report)
+}
+
+// ============================================================================
+// 文本锚点
+// ============================================================================
+
+/// 锚点定位结果
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AnchorLocation {
+    pub anchor: ForeshadowAnchor,
+    pub found: bool,
+    /// 当前正文中的字符偏移与长度（未找到时沿用原记录）
+    pub offset: i64,
+    pub length: i64,
+    /// 原文已被修改、通过近似匹配找到
+    pub fuzzy: bool,
+}
+
+/// 编辑器高亮事件的负载
+#[derive(Debug, Clone, serde::Serialize)]
+struct HighlightPayload {
+    chapter_id: String,
+    offset: i64,
+    length: i64,
+}
+
+/// 设置伏笔的埋设/回收锚点（每个伏笔每种角色一个，重复设置则覆盖）
+///
+/// `offset` 为编辑器中选区的字符偏移，仅作定位提示：会在章节正文中重新定位 `quote`
+/// 并记录实际偏移及前后上下文。
+#[tauri::command]
+pub async fn set_foreshadow_anchor(
+    storage_path: String,
+    foreshadow_id: String,
+    role: String,
+    chapter_id: String,
+    quote: String,
+    offset: i64,
+) -> Result<ForeshadowAnchor, String> {
+    if role != "plant" && role != "reap" {
+        return Err(format!("无效的锚点类型: {}", role));
+    }
+    if quote.is_empty() {
+        return Err("锚点原文不能为空".into());
+    }
+
+    let conn = open_book(&storage_path)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let content = chapter_content(&conn, &chapter_id)?;
+
+    let hint = offset.max(0) as usize;
+    let found = anchor::locate(&content, &quote, hint, "", "")
+        .filter(|m| m.distance == 0)
+        .ok_or("章节正文中找不到锚点原文")?;
+    let (context_before, context_after) = anchor::context(&content, found.offset, found.length, ANCHOR_CONTEXT_CHARS);
+
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO foreshadow_anchors (id, foreshadow_id, role, chapter_id, quote, char_offset, context_before, context_after, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+         ON CONFLICT(foreshadow_id, role) DO UPDATE SET
+            chapter_id = ?4, quote = ?5, char_offset = ?6, context_before = ?7, context_after = ?8, updated_at = ?10",
+        params![id, foreshadow_id, role, chapter_id, quote, found.offset as i64, context_before, context_after, now, now],
+    )
+    .map_err(|e| format!("设置伏笔锚点失败: {}", e))?;
+
+    load_anchor(&conn, &foreshadow_id, &role)
+}
+
+/// 删除伏笔锚点
+#[tauri::command]
+pub async fn remove_foreshadow_anchor(storage_path: String, foreshadow_id: String, role: String) -> Result<(), String> {
+    let conn = open_book(&storage_path)?;
+    conn.execute(
+        "DELETE FROM foreshadow_anchors WHERE foreshadow_id = ?1 AND role = ?2",
+        params![foreshadow_id, role],
+    )
+    .map_err(|e| format!("删除伏笔锚点失败: {}", e))?;
+    Ok(())
+}
+
+/// 在当前正文中重新定位伏笔的全部锚点
+///
+/// 定位成功后回写最新的偏移、原文与上下文，使锚点跟随后续编辑。
+#[tauri::command]
+pub async fn locate_foreshadow_anchors(
+    storage_path: String,
+    foreshadow_id: String,
+) -> Result<Vec<AnchorLocation>, String> {
+    let conn = open_book(&storage_path)?;
+    let mut stmt = conn
+        .prepare("SELECT role FROM foreshadow_anchors WHERE foreshadow_id = ?1 ORDER BY role DESC")
+        .map_err(|e| format!("查询伏笔锚点失败: {}", e))?;
+    let roles = stmt
+        .query_map(params![foreshadow_id], |row| row.get(0))
+        .map_err(|e| format!("读取伏笔锚点失败: {}", e))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| format!("解析伏笔锚点失败: {}", e))?;
+
+    roles
+        .iter()
+        .map(|role| relocate_anchor(&conn, load_anchor(&conn, &foreshadow_id, role)?))
+        .collect()
+}
+
+/// 跳转到伏笔锚点：重新定位后向前端发送 `editor-highlight` 事件（章节 ID + 偏移 + 长度）
+#[tauri::command]
+pub async fn jump_to_foreshadow_anchor(
+    app: AppHandle,
+    storage_path: String,
+    foreshadow_id: String,
+    role: String,
+) -> Result<AnchorLocation, String> {
+    let conn = open_book(&storage_path)?;
+    let location = relocate_anchor(&conn, load_anchor(&conn, &foreshadow_id, &role)?)?;
+    if !location.found {
+        return Err("锚点原文已被删除，无法定位".into());
+    }
+
+    app.emit(
+        "editor-highlight",
+        HighlightPayload {
+            chapter_id: location.anchor.chapter_id.clone(),
+            offset: location.offset,
+            length: location.length,
+        },
+    )
+    .map_err(|e| format!("发送高亮事件失败: {}", e))?;
+
+    Ok(location)
+}
+
+// ============================================================================
+// 关联实体
+// ============================================================================
+
+/// 关联伏笔与实体
+#[tauri::command]
+pub async fn link_foreshadow_entity(storage_path: String, foreshadow_id: String, entity_id: String) -> Result<(), String> {
+    let conn = open_book(&storage_path)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR IGNORE INTO foreshadow_entities (foreshadow_id, entity_id, created_at) VALUES (?1, ?2, ?3)",
+        params![foreshadow_id, entity_id, now],
+    )
+    .map_err(|e| format!("关联实体失败: {}", e))?;
+    Ok(())
+}
+
+/// 取消伏笔与实体的关联
+#[tauri::command]
+pub async fn unlink_foreshadow_entity(storage_path: String, foreshadow_id: String, entity_id: String) -> Result<(), String> {
+    let conn = open_book(&storage_path)?;
+    conn.execute(
+        "DELETE FROM foreshadow_entities WHERE foreshadow_id = ?1 AND entity_id = ?2",
+        params![foreshadow_id, entity_id],
+    )
+    .map_err(|e| format!("取消关联失败: {}", e))?;
+    Ok(())
+}
+
+/// 获取伏笔关联的实体
+#[tauri::command]
+pub async fn list_foreshadow_entities(storage_path: String, foreshadow_id: String) -> Result<Vec<Entity>, String> {
+    let conn = open_book(&storage_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT e.id, e.name, e.entity_type, e.attributes_json, e.status, e.inbox, e.first_chapter_id, e.last_chapter_id, e.created_at, e.updated_at
+             FROM foreshadow_entities fe JOIN entities e ON e.id = fe.entity_id
+             WHERE fe.foreshadow_id = ?1 ORDER BY fe.created_at ASC",
+        )
+        .map_err(|e| format!("查询关联实体失败: {}", e))?;
+
+    let items = stmt
+        .query_map(params![foreshadow_id], |row| {
+            Ok(Entity {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                entity_type: row.get(2)?,
+                attributes_json: row.get(3)?,
+                status: row.get(4)?,
+                inbox: row.get::<_, i32>(5)? != 0,
+                first_chapter_id: row.get(6)?,
+                last_chapter_id: row.get(7)?,
+                created_at: row.get(8)?,
+                updated_at: row.get(9)?,
+            })
+        })
+        .map_err(|e| format!("读取关联实体失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析关联实体失败: {}", e))?;
+
+    Ok(items)
+}
+
+/// 获取与某实体相关的伏笔（实体页"涉及此角色的未回收伏笔"）
+#[tauri::command]
+pub async fn list_entity_foreshadows(
+    storage_path: String,
+    entity_id: String,
+    open_only: Option<bool>,
+) -> Result<Vec<Foreshadow>, String> {
+    let conn = open_book(&storage_path)?;
+    let mut stmt = conn
+        .prepare("SELECT foreshadow_id FROM foreshadow_entities WHERE entity_id = ?1")
+        .map_err(|e| format!("查询关联伏笔失败: {}", e))?;
+    let linked = stmt
+        .query_map(params![entity_id], |row| row.get(0))
+        .map_err(|e| format!("读取关联伏笔失败: {}", e))?
+        .collect::<Result<HashSet<String>, _>>()
+        .map_err(|e| format!("解析关联伏笔失败: {}", e))?;
+
+    let items = list_foreshadows(storage_path)
+        .await?
+        .into_iter()
+        .filter(|f| linked.contains(&f.id))
+        .filter(|f| !open_only.unwrap_or(false) || f.status == "open")
+        .collect();
+
+    Ok(items)
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+/// 标签须为字符串数组的 JSON
+fn validate_tags(tags_json: &str) -> Result<(), String> {
+    serde_json::from_str::<Vec<String>>(tags_json)
+        .map(|_| ())
+        .map_err(|e| format!("标签格式错误: {}", e))
+}
+
+fn current_status(conn: &rusqlite::Connection, id: &str) -> Result<String, String> {
+    conn.query_row("SELECT status FROM foreshadows WHERE id = ?1", params![id], |row| row.get(0))
+        .map_err(|e| format!("读取伏笔状态失败: {}", e))
+}
+
+/// 记录一次伏笔状态变更
+pub(crate) fn record_history(
+    conn: &rusqlite::Connection,
+    foreshadow_id: &str,
+    from_status: Option<&str>,
+    to_status: &str,
+    chapter_id: Option<&str>,
+    note: Option<&str>,
+) -> Result<(), String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO foreshadow_history (id, foreshadow_id, from_status, to_status, chapter_id, note, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![id, foreshadow_id, from_status, to_status, chapter_id, note, now],
+    )
+    .map_err(|e| format!("记录伏笔历史失败: {}", e))?;
     Ok(())
 }
+
+/// 锚点记录的上下文长度（字符）
+const ANCHOR_CONTEXT_CHARS: usize = 20;
+
+fn chapter_content(conn: &rusqlite::Connection, chapter_id: &str) -> Result<String, String> {
+    conn.query_row("SELECT content FROM chapters WHERE id = ?1", params![chapter_id], |row| row.get(0))
+        .map_err(|e| format!("读取章节失败: {}", e))
+}
+
+fn load_anchor(conn: &rusqlite::Connection, foreshadow_id: &str, role: &str) -> Result<ForeshadowAnchor, String> {
+    conn.query_row(
+        "SELECT id, foreshadow_id, role, chapter_id, quote, char_offset, context_before, context_after, created_at, updated_at
+         FROM foreshadow_anchors WHERE foreshadow_id = ?1 AND role = ?2",
+        params![foreshadow_id, role],
+        |row| {
+            Ok(ForeshadowAnchor {
+                id: row.get(0)?,
+                foreshadow_id: row.get(1)?,
+                role: row.get(2)?,
+                chapter_id: row.get(3)?,
+                quote: row.get(4)?,
+                char_offset: row.get(5)?,
+                context_before: row.get(6)?,
+                context_after: row.get(7)?,
+                created_at: row.get(8)?,
+                updated_at: row.get(9)?,
+            })
+        },
+    )
+    .map_err(|e| format!("获取伏笔锚点失败: {}", e))
+}
+
+/// 在章节当前正文中重新定位锚点，找到则回写
+fn relocate_anchor(conn: &rusqlite::Connection, mut a: ForeshadowAnchor) -> Result<AnchorLocation, String> {
+    let content = chapter_content(conn, &a.chapter_id).unwrap_or_default();
+    let hint = a.char_offset.max(0) as usize;
+
+    let Some(m) = anchor::locate(&content, &a.quote, hint, &a.context_before, &a.context_after) else {
+        return Ok(AnchorLocation {
+            offset: a.char_offset,
+            length: a.quote.chars().count() as i64,
+            found: false,
+            fuzzy: false,
+            anchor: a,
+        });
+    };
+
+    let quote: String = content.chars().skip(m.offset).take(m.length).collect();
+    let (before, after) = anchor::context(&content, m.offset, m.length, ANCHOR_CONTEXT_CHARS);
+    if m.offset as i64 != a.char_offset || quote != a.quote || before != a.context_before || after != a.context_after {
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE foreshadow_anchors SET quote = ?1, char_offset = ?2, context_before = ?3, context_after = ?4, updated_at = ?5 WHERE id = ?6",
+            params![quote, m.offset as i64, before, after, now, a.id],
+        )
+        .map_err(|e| format!("更新伏笔锚点失败: {}", e))?;
+        a.quote = quote;
+        a.char_offset = m.offset as i64;
+        a.context_before = before;
+        a.context_after = after;
+        a.updated_at = now;
+    }
+
+    Ok(AnchorLocation {
+        offset: m.offset as i64,
+        length: m.length as i64,
+        found: true,
+        fuzzy: m.distance > 0,
+        anchor: a,
+    })
+}
@@ -1,6 +1,9 @@
+use crate::commands::{chapter, entity, stats};
 use crate::db;
 use crate::db::config;
+use crate::text::count;
 use rusqlite::params;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 
 fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
@@ -85,6 +88,7 @@ pub async fn import_txt(
     let paragraphs: Vec<&str> = content.split("\n\n").collect();
 
     // 每段作为一个章节（如果内容很短则合并）
+    let mode = stats::count_mode()?;
     let mut chapter_idx = 0;
     for para in &paragraphs {
         let trimmed = para.trim();
@@ -95,7 +99,7 @@ pub async fn import_txt(
         chapter_idx += 1;
         let ch_id = uuid::Uuid::new_v4().to_string();
         let ch_name = format!("第{}章", chapter_idx);
-        let word_count = trimmed.chars().count() as i64;
+        let word_count = count::count(trimmed, mode);
 
         conn.execute(
             "INSERT INTO chapters (id, volume_id, name, content, status, word_count, sort_order, created_at, updated_at)
@@ -103,7 +107,430 @@ pub async fn import_txt(
             params![ch_id, vol_id, ch_name, trimmed, word_count, chapter_idx - 1, now, now],
         )
         .map_err(|e| format!("创建章节失败: {}", e))?;
+        stats::log_writing(&conn, &storage_path, &ch_id, word_count, "import")?;
     }
 
     Ok(vol_id)
 }
+
+// ============================================================================
+// 设定集导出
+// ============================================================================
+
+/// 设定集文档（JSON 导出的结构，Markdown/HTML 也由它渲染）
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorldBible {
+    pub generated_at: String,
+    pub groups: Vec<WorldBibleGroup>,
+    pub foreshadows: Vec<WorldBibleForeshadow>,
+}
+
+/// 同类型实体分组
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorldBibleGroup {
+    pub entity_type: String,
+    /// 中文类型名（人物/道具/地点/势力）
+    pub label: String,
+    pub entities: Vec<WorldBibleEntity>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorldBibleEntity {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+    pub aliases: Vec<String>,
+    /// 属性 JSON（人物关系等自由字段也在其中）
+    pub attributes: serde_json::Value,
+    /// 首次出场章节名
+    pub first_appearance: Option<String>,
+    /// 最后出场章节名
+    pub last_appearance: Option<String>,
+    pub timeline: Vec<WorldBibleEvent>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorldBibleEvent {
+    pub chapter: String,
+    pub event: String,
+    pub status_change: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorldBibleForeshadow {
+    pub description: String,
+    pub status: String,
+    pub plant_chapter: Option<String>,
+    pub reap_chapter: Option<String>,
+}
+
+/// 导出设定集为 Markdown / HTML / JSON
+///
+/// 实体按类型分组，章节引用解析为「卷名 · 章名」，时间线按阅读顺序排列。
+/// `entity_types`、`statuses` 为空时不过滤，指定时伏笔只保留关联了导出实体的；
+/// Inbox 中待确认的实体默认不导出。
+#[tauri::command]
+pub async fn export_world_bible(
+    storage_path: String,
+    output_path: String,
+    format: String,
+    entity_types: Option<Vec<String>>,
+    statuses: Option<Vec<String>>,
+    include_inbox: Option<bool>,
+) -> Result<(), String> {
+    let entities = entity::list_entities(storage_path.clone(), None, None).await?;
+    let conn = open_book(&storage_path)?;
+
+    let chapter_names = load_chapter_labels(&conn)?;
+    let order = chapter::reading_order(&conn)?;
+    let position: HashMap<&str, usize> = order.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+    let label_of = |id: &Option<String>| id.as_ref().and_then(|id| chapter_names.get(id).cloned());
+
+    let mut alias_stmt = conn
+        .prepare("SELECT alias FROM entity_aliases WHERE entity_id = ?1 ORDER BY created_at ASC")
+        .map_err(|e| format!("查询别名失败: {}", e))?;
+    let mut tl_stmt = conn
+        .prepare("SELECT chapter_id, event, status_change FROM timeline WHERE entity_id = ?1")
+        .map_err(|e| format!("查询时间线失败: {}", e))?;
+
+    let mut groups: Vec<WorldBibleGroup> = Vec::new();
+    let mut exported: HashSet<String> = HashSet::new();
+    for e in entities {
+        if e.inbox && !include_inbox.unwrap_or(false) {
+            continue;
+        }
+        if entity_types.as_ref().is_some_and(|t| !t.is_empty() && !t.contains(&e.entity_type)) {
+            continue;
+        }
+        if statuses.as_ref().is_some_and(|s| !s.is_empty() && !s.contains(&e.status)) {
+            continue;
+        }
+
+        let aliases = alias_stmt
+            .query_map(params![e.id], |row| row.get(0))
+            .map_err(|e| format!("读取别名失败: {}", e))?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|e| format!("解析别名失败: {}", e))?;
+
+        let mut timeline = tl_stmt
+            .query_map(params![e.id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })
+            .map_err(|e| format!("读取时间线失败: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("解析时间线失败: {}", e))?;
+        timeline.sort_by_key(|(ch, _, _)| position.get(ch.as_str()).copied().unwrap_or(usize::MAX));
+
+        let attributes = serde_json::from_str(&e.attributes_json).unwrap_or_else(|_| serde_json::json!({}));
+
+        exported.insert(e.id.clone());
+        let item = WorldBibleEntity {
+            first_appearance: label_of(&e.first_chapter_id),
+            last_appearance: label_of(&e.last_chapter_id),
+            timeline: timeline
+                .into_iter()
+                .map(|(ch, event, status_change)| WorldBibleEvent {
+                    chapter: chapter_names.get(&ch).cloned().unwrap_or_default(),
+                    event,
+                    status_change,
+                })
+                .collect(),
+            id: e.id,
+            name: e.name,
+            status: e.status,
+            aliases,
+            attributes,
+        };
+
+        match groups.iter_mut().find(|g| g.entity_type == e.entity_type) {
+            Some(g) => g.entities.push(item),
+            None => groups.push(WorldBibleGroup {
+                label: entity_type_label(&e.entity_type),
+                entity_type: e.entity_type,
+                entities: vec![item],
+            }),
+        }
+    }
+
+    // 固定类型在前（人物/道具/地点/势力），自定义类型按名称排在后面；组内按名称排序
+    let type_rank = |t: &str| ["character", "item", "location", "faction"].iter().position(|x| *x == t);
+    groups.sort_by(|a, b| {
+        (type_rank(&a.entity_type).unwrap_or(usize::MAX), &a.entity_type)
+            .cmp(&(type_rank(&b.entity_type).unwrap_or(usize::MAX), &b.entity_type))
+    });
+    for g in &mut groups {
+        g.entities.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    // 指定了类型或状态筛选时，只导出关联了已导出实体的伏笔
+    let filtered = entity_types.as_ref().is_some_and(|t| !t.is_empty()) || statuses.as_ref().is_some_and(|s| !s.is_empty());
+    let mut links: HashMap<String, Vec<String>> = HashMap::new();
+    if filtered {
+        let mut link_stmt = conn
+            .prepare("SELECT foreshadow_id, entity_id FROM foreshadow_entities")
+            .map_err(|e| format!("查询伏笔关联失败: {}", e))?;
+        let rows = link_stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| format!("读取伏笔关联失败: {}", e))?;
+        for row in rows {
+            let (foreshadow_id, entity_id) = row.map_err(|e| format!("解析伏笔关联失败: {}", e))?;
+            links.entry(foreshadow_id).or_default().push(entity_id);
+        }
+    }
+
+    let mut fs_stmt = conn
+        .prepare("SELECT id, description, status, plant_chapter_id, reap_chapter_id FROM foreshadows ORDER BY created_at ASC")
+        .map_err(|e| format!("查询伏笔失败: {}", e))?;
+    let foreshadows = fs_stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                WorldBibleForeshadow {
+                    description: row.get(1)?,
+                    status: row.get(2)?,
+                    plant_chapter: label_of(&row.get(3)?),
+                    reap_chapter: label_of(&row.get(4)?),
+                },
+            ))
+        })
+        .map_err(|e| format!("读取伏笔失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析伏笔失败: {}", e))?
+        .into_iter()
+        .filter(|(id, _)| !filtered || links.get(id).is_some_and(|ids| ids.iter().any(|e| exported.contains(e))))
+        .map(|(_, f)| f)
+        .collect();
+
+    let bible = WorldBible {
+        generated_at: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        groups,
+        foreshadows,
+    };
+
+    let output = match format.as_str() {
+        "json" => serde_json::to_string_pretty(&bible).map_err(|e| format!("序列化设定集失败: {}", e))?,
+        "markdown" | "md" => render_bible_markdown(&bible),
+        "html" => render_bible_html(&bible),
+        _ => return Err(format!("不支持的导出格式: {}", format)),
+    };
+
+    fs::write(&output_path, output).map_err(|e| format!("写入文件失败: {}", e))?;
+    Ok(())
+}
+
+/// 章节 ID → 「卷名 · 章名」
+fn load_chapter_labels(conn: &rusqlite::Connection) -> Result<HashMap<String, String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT c.id, v.name, c.name FROM chapters c JOIN volumes v ON v.id = c.volume_id")
+        .map_err(|e| format!("查询章节失败: {}", e))?;
+    let labels = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, format!("{} · {}", row.get::<_, String>(1)?, row.get::<_, String>(2)?)))
+        })
+        .map_err(|e| format!("读取章节失败: {}", e))?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| format!("解析章节失败: {}", e))?;
+    Ok(labels)
+}
+
+fn entity_type_label(entity_type: &str) -> String {
+    match entity_type {
+        "character" => "人物",
+        "item" => "道具",
+        "location" => "地点",
+        "faction" => "势力",
+        other => other,
+    }
+    .to_string()
+}
+
+pub(crate) fn entity_status_label(status: &str) -> &str {
+    match status {
+        "alive" => "存活",
+        "dead" => "死亡",
+        "destroyed" => "已损毁",
+        other => other,
+    }
+}
+
+fn foreshadow_status_label(status: &str) -> &str {
+    match status {
+        "open" => "未回收",
+        "resolved" => "已回收",
+        other => other,
+    }
+}
+
+/// 属性值的纯文本形式（数组用"、"连接）
+pub(crate) fn attribute_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(items) => items.iter().map(attribute_text).collect::<Vec<_>>().join("、"),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Markdown 表格单元格转义：`|` 会截断单元格，换行会截断整行
+fn escape_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace("\r\n", "<br>").replace('\n', "<br>")
+}
+
+fn render_bible_markdown(bible: &WorldBible) -> String {
+    fn attributes_md(out: &mut String, value: &serde_json::Value, depth: usize) {
+        let Some(map) = value.as_object() else {
+            return;
+        };
+        for (key, v) in map {
+            let indent = "  ".repeat(depth);
+            if v.is_object() {
+                out.push_str(&format!("{}- **{}**\n", indent, key));
+                attributes_md(out, v, depth + 1);
+            } else {
+                out.push_str(&format!("{}- **{}**：{}\n", indent, key, attribute_text(v)));
+            }
+        }
+    }
+
+    let mut out = format!("# 设定集\n\n> 导出时间：{}\n\n", bible.generated_at);
+    for group in &bible.groups {
+        out.push_str(&format!("## {}\n\n", group.label));
+        for e in &group.entities {
+            out.push_str(&format!("### {}\n\n", e.name));
+            if !e.aliases.is_empty() {
+                out.push_str(&format!("- 别名：{}\n", e.aliases.join("、")));
+            }
+            out.push_str(&format!("- 状态：{}\n", entity_status_label(&e.status)));
+            if let Some(first) = &e.first_appearance {
+                out.push_str(&format!("- 首次出场：{}\n", first));
+            }
+            if let Some(last) = &e.last_appearance {
+                out.push_str(&format!("- 最后出场：{}\n", last));
+            }
+            out.push('\n');
+
+            if e.attributes.as_object().is_some_and(|m| !m.is_empty()) {
+                out.push_str("#### 属性\n\n");
+                attributes_md(&mut out, &e.attributes, 0);
+                out.push('\n');
+            }
+
+            if !e.timeline.is_empty() {
+                out.push_str("#### 时间线\n\n| 章节 | 事件 | 状态变更 |\n| --- | --- | --- |\n");
+                for t in &e.timeline {
+                    out.push_str(&format!(
+                        "| {} | {} | {} |\n",
+                        escape_cell(&t.chapter),
+                        escape_cell(&t.event),
+                        escape_cell(t.status_change.as_deref().unwrap_or(""))
+                    ));
+                }
+                out.push('\n');
+            }
+        }
+    }
+
+    if !bible.foreshadows.is_empty() {
+        out.push_str("## 伏笔\n\n| 伏笔 | 状态 | 埋设 | 回收 |\n| --- | --- | --- | --- |\n");
+        for f in &bible.foreshadows {
+            out.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                escape_cell(&f.description),
+                escape_cell(foreshadow_status_label(&f.status)),
+                escape_cell(f.plant_chapter.as_deref().unwrap_or("")),
+                escape_cell(f.reap_chapter.as_deref().unwrap_or(""))
+            ));
+        }
+    }
+
+    out
+}
+
+fn render_bible_html(bible: &WorldBible) -> String {
+    fn esc(s: &str) -> String {
+        s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+    }
+
+    fn attributes_html(out: &mut String, value: &serde_json::Value) {
+        let Some(map) = value.as_object() else {
+            return;
+        };
+        out.push_str("<dl>");
+        for (key, v) in map {
+            out.push_str(&format!("<dt>{}</dt><dd>", esc(key)));
+            if v.is_object() {
+                attributes_html(out, v);
+            } else {
+                out.push_str(&esc(&attribute_text(v)));
+            }
+            out.push_str("</dd>");
+        }
+        out.push_str("</dl>\n");
+    }
+
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n<title>设定集</title>\n<style>\n\
+         body{font-family:sans-serif;max-width:960px;margin:2em auto;line-height:1.6}\n\
+         table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:4px 8px}\n\
+         dt{font-weight:bold}dd{margin:0 0 .5em 1.5em}\n</style>\n</head>\n<body>\n",
+    );
+    out.push_str(&format!("<h1>设定集</h1>\n<p>导出时间：{}</p>\n", esc(&bible.generated_at)));
+
+    for group in &bible.groups {
+        out.push_str(&format!("<h2>{}</h2>\n", esc(&group.label)));
+        for e in &group.entities {
+            out.push_str(&format!("<section>\n<h3>{}</h3>\n<ul>\n", esc(&e.name)));
+            if !e.aliases.is_empty() {
+                out.push_str(&format!("<li>别名：{}</li>\n", esc(&e.aliases.join("、"))));
+            }
+            out.push_str(&format!("<li>状态：{}</li>\n", esc(entity_status_label(&e.status))));
+            if let Some(first) = &e.first_appearance {
+                out.push_str(&format!("<li>首次出场：{}</li>\n", esc(first)));
+            }
+            if let Some(last) = &e.last_appearance {
+                out.push_str(&format!("<li>最后出场：{}</li>\n", esc(last)));
+            }
+            out.push_str("</ul>\n");
+
+            if e.attributes.as_object().is_some_and(|m| !m.is_empty()) {
+                out.push_str("<h4>属性</h4>\n");
+                attributes_html(&mut out, &e.attributes);
+            }
+
+            if !e.timeline.is_empty() {
+                out.push_str("<h4>时间线</h4>\n<table>\n<tr><th>章节</th><th>事件</th><th>状态变更</th></tr>\n");
+                for t in &e.timeline {
+                    out.push_str(&format!(
+                        "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                        esc(&t.chapter),
+                        esc(&t.event),
+                        esc(t.status_change.as_deref().unwrap_or(""))
+                    ));
+                }
+                out.push_str("</table>\n");
+            }
+            out.push_str("</section>\n");
+        }
+    }
+
+    if !bible.foreshadows.is_empty() {
+        out.push_str("<h2>伏笔</h2>\n<table>\n<tr><th>伏笔</th><th>状态</th><th>埋设</th><th>回收</th></tr>\n");
+        for f in &bible.foreshadows {
+            out.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                esc(&f.description),
+                esc(foreshadow_status_label(&f.status)),
+                esc(f.plant_chapter.as_deref().unwrap_or("")),
+                esc(f.reap_chapter.as_deref().unwrap_or(""))
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
@@ -1,10 +1,24 @@
+pub mod ai;
+pub mod analytics;
+pub mod arc;
+pub mod assist;
 pub mod book;
 pub mod chapter;
+pub mod context;
+pub mod continuity;
 pub mod entity;
+pub mod extraction;
 pub mod foreshadow;
 pub mod io;
+pub mod publish;
+pub mod restructure;
+pub mod session;
 pub mod settings;
 pub mod snapshot;
 pub mod stats;
+pub mod status;
+pub mod summary;
+pub mod usage;
+pub mod vector;
 pub mod volume;
 pub mod window;
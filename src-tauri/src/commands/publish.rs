@@ -0,0 +1,318 @@
+use crate::commands::status;
+use crate::db;
+use crate::db::config;
+use crate::db::models::PublishPlan;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use rusqlite::params;
+use tauri::{AppHandle, Emitter};
+
+/// 计划发布时间格式（本地时间，与 datetime-local 输入框一致）
+const SCHEDULE_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
+    let cfg = config::load_config()?;
+    db::open_book_db(&cfg, storage_path)
+}
+
+/// 发布日历中的章节
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ScheduledChapter {
+    pub id: String,
+    pub volume_id: String,
+    pub name: String,
+    pub status: String,
+    pub word_count: i64,
+    pub scheduled_at: Option<String>,
+    pub published_at: Option<String>,
+}
+
+/// 存稿报告
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BufferReport {
+    /// 已完成未发布的章节数
+    pub buffer_chapters: i64,
+    pub buffer_words: i64,
+    pub chapters_per_day: i64,
+    /// 存稿可支撑的天数，未设发布时间点时为 None
+    pub buffer_days: Option<f64>,
+    pub threshold: i64,
+    /// 存稿少于预警阈值
+    pub low: bool,
+    /// 已过计划时间仍未发布的章节
+    pub overdue: Vec<ScheduledChapter>,
+    /// 下一章计划发布的章节
+    pub next: Option<ScheduledChapter>,
+}
+
+/// 获取发布计划（未设置时为空计划）
+#[tauri::command]
+pub async fn get_publish_plan(storage_path: String) -> Result<PublishPlan, String> {
+    let conn = open_book(&storage_path)?;
+    load_plan(&conn)
+}
+
+/// 设置发布计划：每天的发布时间点（HH:MM）与存稿预警阈值
+#[tauri::command]
+pub async fn set_publish_plan(
+    storage_path: String,
+    release_times: Vec<String>,
+    buffer_threshold: i64,
+) -> Result<PublishPlan, String> {
+    if buffer_threshold < 0 {
+        return Err("预警阈值不能为负数".into());
+    }
+    let mut times = release_times
+        .iter()
+        .map(|t| {
+            NaiveTime::parse_from_str(t.trim(), "%H:%M")
+                .map(|time| time.format("%H:%M").to_string())
+                .map_err(|_| format!("发布时间格式无效: {}", t))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    times.sort();
+    times.dedup();
+
+    let conn = open_book(&storage_path)?;
+    let times_json = serde_json::to_string(&times).map_err(|e| format!("序列化发布时间失败: {}", e))?;
+    conn.execute(
+        "INSERT INTO publish_plan (id, release_times_json, buffer_threshold, updated_at) VALUES (1, ?1, ?2, ?3)
+         ON CONFLICT(id) DO UPDATE SET release_times_json = ?1, buffer_threshold = ?2, updated_at = ?3",
+        params![times_json, buffer_threshold, chrono::Utc::now().to_rfc3339()],
+    )
+    .map_err(|e| format!("保存发布计划失败: {}", e))?;
+    load_plan(&conn)
+}
+
+/// 设置或清除单章的计划发布时间（`scheduled_at` 为本地时间 YYYY-MM-DDTHH:MM）
+#[tauri::command]
+pub async fn schedule_chapter(storage_path: String, id: String, scheduled_at: Option<String>) -> Result<(), String> {
+    let scheduled_at = match scheduled_at {
+        Some(v) => Some(
+            NaiveDateTime::parse_from_str(v.trim(), SCHEDULE_FORMAT)
+                .map_err(|_| format!("发布时间格式无效: {}", v))?
+                .format(SCHEDULE_FORMAT)
+                .to_string(),
+        ),
+        None => None,
+    };
+    let conn = open_book(&storage_path)?;
+    let updated = conn
+        .execute(
+            "UPDATE chapters SET scheduled_at = ?1 WHERE id = ?2",
+            params![scheduled_at, id],
+        )
+        .map_err(|e| format!("设置发布时间失败: {}", e))?;
+    if updated == 0 {
+        return Err("章节不存在".into());
+    }
+    Ok(())
+}
+
+/// 按发布计划从 `start_date` 起为所有未发布章节（按阅读顺序）排期，覆盖原有计划时间
+///
+/// 已经过去的时间点跳过。返回排期后的章节。
+#[tauri::command]
+pub async fn auto_schedule_chapters(storage_path: String, start_date: String) -> Result<Vec<ScheduledChapter>, String> {
+    let start = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+        .map_err(|_| format!("日期格式无效: {}", start_date))?;
+    let mut conn = open_book(&storage_path)?;
+    let plan = load_plan(&conn)?;
+    if plan.release_times.is_empty() {
+        return Err("请先设置每天的发布时间".into());
+    }
+    let times = plan
+        .release_times
+        .iter()
+        .map(|t| NaiveTime::parse_from_str(t, "%H:%M").map_err(|_| format!("发布时间格式无效: {}", t)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let now = chrono::Local::now().naive_local();
+
+    let chapters = query_chapters(
+        &conn,
+        "c.status != 'published' ORDER BY v.sort_order ASC, c.sort_order ASC",
+        [],
+    )?;
+    let mut slots = start
+        .iter_days()
+        .flat_map(|day| times.clone().into_iter().map(move |t| day.and_time(t)))
+        .filter(|slot| *slot >= now);
+
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+    let mut scheduled = Vec::with_capacity(chapters.len());
+    for mut chapter in chapters {
+        let Some(slot) = slots.next() else { break };
+        let slot = slot.format(SCHEDULE_FORMAT).to_string();
+        tx.execute(
+            "UPDATE chapters SET scheduled_at = ?1 WHERE id = ?2",
+            params![slot, chapter.id],
+        )
+        .map_err(|e| format!("设置发布时间失败: {}", e))?;
+        chapter.scheduled_at = Some(slot);
+        scheduled.push(chapter);
+    }
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+    Ok(scheduled)
+}
+
+/// 标记章节已发布（需已完成），返回发布后的存稿报告
+///
+/// 存稿低于预警阈值时推送 `publish-buffer-low` 事件。
+#[tauri::command]
+pub async fn mark_chapter_published(
+    app: AppHandle,
+    storage_path: String,
+    id: String,
+) -> Result<BufferReport, String> {
+    let conn = open_book(&storage_path)?;
+    status::transition(&conn, &id, "published", "user")?;
+
+    let report = buffer_report(&conn)?;
+    if report.low {
+        let _ = app.emit("publish-buffer-low", report.clone());
+    }
+    Ok(report)
+}
+
+/// 获取存稿报告
+#[tauri::command]
+pub async fn get_publish_buffer(storage_path: String) -> Result<BufferReport, String> {
+    let conn = open_book(&storage_path)?;
+    buffer_report(&conn)
+}
+
+/// 获取发布日历：计划或实际发布日期在范围内的章节（按时间升序）
+///
+/// 实际发布时间以 UTC 存储，按本地日期归入范围。
+#[tauri::command]
+pub async fn get_publish_calendar(
+    storage_path: String,
+    start_date: String,
+    end_date: String,
+) -> Result<Vec<ScheduledChapter>, String> {
+    let conn = open_book(&storage_path)?;
+    let in_range = |date: &str| date >= start_date.as_str() && date <= end_date.as_str();
+    let mut chapters: Vec<(String, ScheduledChapter)> = query_chapters(
+        &conn,
+        "c.scheduled_at IS NOT NULL OR c.published_at IS NOT NULL",
+        [],
+    )?
+    .into_iter()
+    .filter_map(|c| {
+        let published = c.published_at.as_deref().and_then(local_time);
+        let hit = [published.as_deref(), c.scheduled_at.as_deref()]
+            .into_iter()
+            .flatten()
+            .any(|t| in_range(t.get(..10).unwrap_or_default()));
+        let key = published.or_else(|| c.scheduled_at.clone())?;
+        hit.then_some((key, c))
+    })
+    .collect();
+    chapters.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(chapters.into_iter().map(|(_, c)| c).collect())
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+/// RFC 3339 时间转为本地时间（与计划发布时间同一格式，便于比较排序）
+fn local_time(timestamp: &str) -> Option<String> {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|t| t.with_timezone(&chrono::Local).format(SCHEDULE_FORMAT).to_string())
+}
+
+fn load_plan(conn: &rusqlite::Connection) -> Result<PublishPlan, String> {
+    let row = conn.query_row(
+        "SELECT release_times_json, buffer_threshold, updated_at FROM publish_plan WHERE id = 1",
+        [],
+        |row| Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?)),
+    );
+    match row {
+        Ok((times_json, buffer_threshold, updated_at)) => Ok(PublishPlan {
+            release_times: serde_json::from_str(&times_json).unwrap_or_default(),
+            buffer_threshold,
+            updated_at: Some(updated_at),
+        }),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(PublishPlan {
+            release_times: Vec::new(),
+            buffer_threshold: 0,
+            updated_at: None,
+        }),
+        Err(e) => Err(format!("读取发布计划失败: {}", e)),
+    }
+}
+
+fn query_chapters<P: rusqlite::Params>(
+    conn: &rusqlite::Connection,
+    condition: &str,
+    params: P,
+) -> Result<Vec<ScheduledChapter>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT c.id, c.volume_id, c.name, c.status, c.word_count, c.scheduled_at, c.published_at
+             FROM chapters c JOIN volumes v ON v.id = c.volume_id
+             WHERE {}",
+            condition
+        ))
+        .map_err(|e| format!("查询章节失败: {}", e))?;
+    let chapters = stmt
+        .query_map(params, |row| {
+            Ok(ScheduledChapter {
+                id: row.get(0)?,
+                volume_id: row.get(1)?,
+                name: row.get(2)?,
+                status: row.get(3)?,
+                word_count: row.get(4)?,
+                scheduled_at: row.get(5)?,
+                published_at: row.get(6)?,
+            })
+        })
+        .map_err(|e| format!("读取章节失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析章节失败: {}", e))?;
+    Ok(chapters)
+}
+
+fn buffer_report(conn: &rusqlite::Connection) -> Result<BufferReport, String> {
+    let plan = load_plan(conn)?;
+    // 存稿：已完成但未发布的章节
+    let buffer = status::buffer_statuses(conn)?;
+    let (buffer_chapters, buffer_words): (i64, i64) = conn
+        .query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(word_count), 0) FROM chapters WHERE status IN ({})",
+                vec!["?"; buffer.len()].join(", ")
+            ),
+            rusqlite::params_from_iter(&buffer),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("统计存稿失败: {}", e))?;
+
+    let now = chrono::Local::now().naive_local().format(SCHEDULE_FORMAT).to_string();
+    let overdue = query_chapters(
+        conn,
+        "c.status != 'published' AND c.scheduled_at IS NOT NULL AND c.scheduled_at < ?1
+         ORDER BY c.scheduled_at ASC",
+        params![now],
+    )?;
+    let next = query_chapters(
+        conn,
+        "c.status != 'published' AND c.scheduled_at >= ?1 ORDER BY c.scheduled_at ASC LIMIT 1",
+        params![now],
+    )?
+    .into_iter()
+    .next();
+
+    let chapters_per_day = plan.release_times.len() as i64;
+    Ok(BufferReport {
+        buffer_chapters,
+        buffer_words,
+        chapters_per_day,
+        buffer_days: (chapters_per_day > 0).then(|| buffer_chapters as f64 / chapters_per_day as f64),
+        threshold: plan.buffer_threshold,
+        low: plan.buffer_threshold > 0 && buffer_chapters < plan.buffer_threshold,
+        overdue,
+        next,
+    })
+}
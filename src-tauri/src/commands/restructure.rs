@@ -0,0 +1,722 @@
+//! 章节拆分与合并
+//!
+//! 两种操作都会为受影响的章节创建快照、重排所在分卷的 sort_order，
+//! 并把伏笔、时间线、实体首次/最后出场等引用改指到对应的章节。
+//! 还原所需的数据写入回收站，从回收站恢复即撤销（与实体合并相同）。
+
+use crate::commands::{arc, chapter, stats, status, vector};
+use crate::db;
+use crate::db::config;
+use crate::db::models::Chapter;
+use crate::text::count::{self, CountMode};
+use rusqlite::params;
+
+/// 合并时章节之间的分隔（与导入导出的段落分隔一致）
+const MERGE_SEPARATOR: &str = "\n\n";
+
+/// 引用章节的列（表, 列），合并时整体改指到保留的章节；伏笔锚点带字符偏移，单独处理
+const CHAPTER_REFS: [(&str, &str); 10] = [
+    ("timeline", "chapter_id"),
+    ("foreshadows", "plant_chapter_id"),
+    ("foreshadows", "reap_chapter_id"),
+    ("foreshadows", "planned_reap_chapter_id"),
+    ("foreshadow_history", "chapter_id"),
+    ("entities", "first_chapter_id"),
+    ("entities", "last_chapter_id"),
+    ("ai_proposals", "chapter_id"),
+    ("rag_arcs", "start_chapter_id"),
+    ("rag_arcs", "end_chapter_id"),
+];
+
+fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
+    let cfg = config::load_config()?;
+    db::open_book_db(&cfg, storage_path)
+}
+
+/// 拆分 / 合并结果
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RestructureResult {
+    /// 拆分出的新章节，或合并后的章节
+    pub chapter: Chapter,
+    /// 还原数据在回收站中的记录 ID（从回收站恢复即可撤销）
+    pub trash_id: String,
+}
+
+/// 在正文的字符偏移 `offset` 处拆分章节，后半部分成为紧随其后的新章节
+///
+/// - 断开处两侧的空白去掉；新章节名默认为「原章节名（续）」，状态为草稿
+/// - 偏移落在后半部分的伏笔锚点移到新章节，对应的埋设/回收章节随之改指
+/// - 只在后半部分出现的实体，其时间线节点与首次出场改指新章节；在后半部分出现过的实体，最后出场改指新章节
+#[tauri::command]
+pub async fn split_chapter(
+    storage_path: String,
+    id: String,
+    offset: i64,
+    name: Option<String>,
+) -> Result<RestructureResult, String> {
+    let mode = stats::count_mode()?;
+    let mut conn = open_book(&storage_path)?;
+    let (new_id, trash_id) = split(&mut conn, &id, offset, name, mode)?;
+    Ok(RestructureResult {
+        chapter: chapter::get_chapter(storage_path, new_id).await?,
+        trash_id,
+    })
+}
+
+/// 执行拆分，返回（新章节 ID, 还原记录 ID）
+fn split(
+    conn: &mut rusqlite::Connection,
+    id: &str,
+    offset: i64,
+    name: Option<String>,
+    mode: CountMode,
+) -> Result<(String, String), String> {
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let before = chapter::reading_order(&tx)?;
+
+    let original = chapter::chapter_json(&tx, id)?;
+    let content = original["content"].as_str().unwrap_or_default();
+    let volume_id = original["volume_id"].as_str().unwrap_or_default();
+    let (head, tail, tail_start) = split_content(content, offset.max(0) as usize)?;
+    let name = name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| format!("{}（续）", original["name"].as_str().unwrap_or_default()));
+
+    // 1. 快照原章节，写入前半部分
+    insert_snapshot(&tx, id, content, "split", &now)?;
+    tx.execute(
+        "UPDATE chapters SET content = ?1, word_count = ?2, updated_at = ?3 WHERE id = ?4",
+        params![head, count::count(&head, mode), now, id],
+    )
+    .map_err(|e| format!("更新章节失败: {}", e))?;
+    status::after_edit(&tx, id, original["status"].as_str().unwrap_or_default())?;
+
+    // 2. 新章节紧随原章节
+    let new_id = uuid::Uuid::new_v4().to_string();
+    tx.execute(
+        "INSERT INTO chapters (id, volume_id, name, content, status, word_count, sort_order, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, 'draft', ?5, 0, ?6, ?6)",
+        params![new_id, volume_id, name, tail, count::count(&tail, mode), now],
+    )
+    .map_err(|e| format!("创建章节失败: {}", e))?;
+    place_after(&tx, volume_id, id, &[new_id.as_str()])?;
+
+    // 3. 引用改指
+    tx.execute(
+        "UPDATE foreshadow_anchors SET chapter_id = ?1, char_offset = char_offset - ?2, updated_at = ?3
+         WHERE chapter_id = ?4 AND char_offset >= ?2",
+        params![new_id, tail_start as i64, now, id],
+    )
+    .map_err(|e| format!("迁移伏笔锚点失败: {}", e))?;
+    for (column, role) in [("plant_chapter_id", "plant"), ("reap_chapter_id", "reap")] {
+        tx.execute(
+            &format!(
+                "UPDATE foreshadows SET {0} = ?1 WHERE {0} = ?2 AND id IN (
+                    SELECT foreshadow_id FROM foreshadow_anchors WHERE chapter_id = ?1 AND role = ?3
+                )",
+                column
+            ),
+            params![new_id, id, role],
+        )
+        .map_err(|e| format!("更新伏笔章节失败: {}", e))?;
+    }
+    for (entity_id, names) in referenced_entities(&tx, id)? {
+        let in_head = names.iter().any(|n| head.contains(n.as_str()));
+        let in_tail = names.iter().any(|n| tail.contains(n.as_str()));
+        if !in_tail {
+            continue;
+        }
+        tx.execute(
+            "UPDATE entities SET last_chapter_id = ?1 WHERE id = ?2 AND last_chapter_id = ?3",
+            params![new_id, entity_id, id],
+        )
+        .map_err(|e| format!("更新实体出场章节失败: {}", e))?;
+        if in_head {
+            continue;
+        }
+        tx.execute(
+            "UPDATE entities SET first_chapter_id = ?1 WHERE id = ?2 AND first_chapter_id = ?3",
+            params![new_id, entity_id, id],
+        )
+        .map_err(|e| format!("更新实体出场章节失败: {}", e))?;
+        tx.execute(
+            "UPDATE timeline SET chapter_id = ?1 WHERE entity_id = ?2 AND chapter_id = ?3",
+            params![new_id, entity_id, id],
+        )
+        .map_err(|e| format!("迁移时间线失败: {}", e))?;
+    }
+
+    vector::refresh_chapter(&tx, id)?;
+    vector::refresh_chapter(&tx, &new_id)?;
+    arc::invalidate_reordered(&tx, &before)?;
+    arc::invalidate_chapter(&tx, id)?;
+
+    // 4. 还原数据进回收站
+    let data = serde_json::json!({
+        "id": new_id,
+        "name": name,
+        "split": {
+            "chapter_id": id,
+            "offset": offset,
+            "original": original,
+        },
+    });
+    let trash_id = insert_trash(&tx, &new_id, &data, &now)?;
+    tx.commit().map_err(|e| format!("提交拆分失败: {}", e))?;
+    Ok((new_id, trash_id))
+}
+
+/// 合并同一分卷内相邻的章节（`ids` 按阅读顺序），并入第一章
+///
+/// - 正文以空行连接，其余章节删除，它们的引用全部改指第一章，伏笔锚点偏移随之平移
+/// - 被合并章节的快照随还原数据保存，撤销时一并恢复
+#[tauri::command]
+pub async fn merge_chapters(storage_path: String, ids: Vec<String>) -> Result<RestructureResult, String> {
+    if ids.len() < 2 {
+        return Err("至少选择两个章节".into());
+    }
+    let mode = stats::count_mode()?;
+    let mut conn = open_book(&storage_path)?;
+    let trash_id = merge(&mut conn, &ids, mode)?;
+    Ok(RestructureResult {
+        chapter: chapter::get_chapter(storage_path, ids[0].clone()).await?,
+        trash_id,
+    })
+}
+
+/// 执行合并，返回还原记录 ID
+fn merge(conn: &mut rusqlite::Connection, ids: &[String], mode: CountMode) -> Result<String, String> {
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let before = chapter::reading_order(&tx)?;
+
+    let rows = ids
+        .iter()
+        .map(|id| chapter::chapter_json(&tx, id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let volume_id = rows[0]["volume_id"].as_str().unwrap_or_default();
+    if rows.iter().any(|row| row["volume_id"].as_str() != Some(volume_id)) {
+        return Err("只能合并同一分卷内的章节".into());
+    }
+    let order = volume_order(&tx, volume_id)?;
+    let start = order.iter().position(|id| *id == ids[0]).unwrap_or_default();
+    if order.get(start..start + ids.len()) != Some(ids) {
+        return Err("只能合并相邻的章节（按阅读顺序选择）".into());
+    }
+
+    let survivor_id = &ids[0];
+    let contents: Vec<&str> = rows.iter().map(|row| row["content"].as_str().unwrap_or_default()).collect();
+    let merged = contents.join(MERGE_SEPARATOR);
+    insert_snapshot(&tx, survivor_id, contents[0], "merge", &now)?;
+
+    // 1. 其余章节的引用改指第一章，保存快照后删除
+    let separator_len = MERGE_SEPARATOR.chars().count() as i64;
+    let mut shift = contents[0].chars().count() as i64 + separator_len;
+    let mut moved = Vec::new();
+    let mut snapshots = Vec::new();
+    for (id, content) in ids.iter().zip(&contents).skip(1) {
+        let refs = repoint(&tx, id, survivor_id, shift)?;
+        moved.push(serde_json::json!({ "chapter_id": id, "refs": refs }));
+        snapshots.extend(chapter_snapshots(&tx, id)?);
+        tx.execute("DELETE FROM snapshots WHERE chapter_id = ?1", params![id])
+            .map_err(|e| format!("删除快照失败: {}", e))?;
+        vector::remove_source(&tx, "chapter", id)?;
+        vector::remove_source(&tx, "summary", id)?;
+        tx.execute("DELETE FROM chapters WHERE id = ?1", params![id])
+            .map_err(|e| format!("删除章节失败: {}", e))?;
+        shift += content.chars().count() as i64 + separator_len;
+    }
+
+    // 2. 写入合并后的正文
+    tx.execute(
+        "UPDATE chapters SET content = ?1, word_count = ?2, updated_at = ?3 WHERE id = ?4",
+        params![merged, count::count(&merged, mode), now, survivor_id],
+    )
+    .map_err(|e| format!("更新章节失败: {}", e))?;
+    status::after_edit(&tx, survivor_id, rows[0]["status"].as_str().unwrap_or_default())?;
+    place_after(&tx, volume_id, survivor_id, &[])?;
+
+    vector::refresh_chapter(&tx, survivor_id)?;
+    arc::invalidate_reordered(&tx, &before)?;
+    arc::invalidate_chapter(&tx, survivor_id)?;
+
+    // 3. 还原数据进回收站
+    let data = serde_json::json!({
+        "id": survivor_id,
+        "name": rows[0]["name"],
+        "merge": {
+            "chapters": rows,
+            "moved": moved,
+            "snapshots": snapshots,
+        },
+    });
+    let trash_id = insert_trash(&tx, survivor_id, &data, &now)?;
+    tx.commit().map_err(|e| format!("提交合并失败: {}", e))?;
+    Ok(trash_id)
+}
+
+/// 撤销拆分：新章节的引用全部改回原章节，删除新章节并还原原章节
+///
+/// 由 `restore_from_trash` 在回收站数据带有 `split` 字段时调用。拆分后任一章节正文被修改时拒绝撤销。
+pub(crate) fn undo_split(conn: &rusqlite::Connection, data: &serde_json::Value, mode: CountMode) -> Result<(), String> {
+    let split = &data["split"];
+    let new_id = data["id"].as_str().unwrap_or_default();
+    let chapter_id = split["chapter_id"].as_str().unwrap_or_default();
+    let original = &split["original"];
+    let (head, tail, tail_start) = split_content(
+        original["content"].as_str().unwrap_or_default(),
+        split["offset"].as_i64().unwrap_or_default().max(0) as usize,
+    )?;
+
+    let (current_head, current_status): (String, String) = conn
+        .query_row(
+            "SELECT content, status FROM chapters WHERE id = ?1",
+            params![chapter_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("获取原章节失败: {}", e))?;
+    let (current_tail, new_volume_id): (String, String) = conn
+        .query_row(
+            "SELECT content, volume_id FROM chapters WHERE id = ?1",
+            params![new_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("获取拆分出的章节失败: {}", e))?;
+    if current_head != head || current_tail != tail {
+        return Err("拆分后的章节已修改，无法撤销（可从快照恢复）".into());
+    }
+
+    repoint(conn, new_id, chapter_id, tail_start as i64)?;
+    conn.execute("DELETE FROM snapshots WHERE chapter_id = ?1", params![new_id])
+        .map_err(|e| format!("删除快照失败: {}", e))?;
+    vector::remove_source(conn, "chapter", new_id)?;
+    vector::remove_source(conn, "summary", new_id)?;
+    conn.execute("DELETE FROM chapters WHERE id = ?1", params![new_id])
+        .map_err(|e| format!("删除章节失败: {}", e))?;
+    place_after(conn, &new_volume_id, "", &[])?;
+
+    restore_fields(conn, original, &current_status, mode)?;
+    vector::refresh_chapter(conn, chapter_id)?;
+    arc::invalidate_chapter(conn, chapter_id)
+}
+
+/// 撤销合并：恢复被合并的章节及其快照与引用，第一章还原为合并前的内容
+///
+/// 由 `restore_from_trash` 在回收站数据带有 `merge` 字段时调用。合并后正文被修改时拒绝撤销。
+pub(crate) fn undo_merge(conn: &rusqlite::Connection, data: &serde_json::Value, mode: CountMode) -> Result<(), String> {
+    let merge = &data["merge"];
+    let survivor_id = data["id"].as_str().unwrap_or_default();
+    let rows = merge["chapters"].as_array().cloned().unwrap_or_default();
+    let Some((survivor, absorbed)) = rows.split_first() else {
+        return Err("合并记录无效".into());
+    };
+    let merged = rows
+        .iter()
+        .map(|row| row["content"].as_str().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(MERGE_SEPARATOR);
+
+    let (current, current_status): (String, String) = conn
+        .query_row(
+            "SELECT content, status FROM chapters WHERE id = ?1",
+            params![survivor_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("获取合并后的章节失败: {}", e))?;
+    if current != merged {
+        return Err("合并后的章节已修改，无法撤销（可从快照恢复）".into());
+    }
+
+    for row in absorbed {
+        chapter::insert_chapter_json(conn, row, mode)?;
+    }
+    for snapshot in merge["snapshots"].as_array().into_iter().flatten() {
+        conn.execute(
+            "INSERT OR IGNORE INTO snapshots (id, chapter_id, snapshot_content, source, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                snapshot["id"].as_str().unwrap_or_default(),
+                snapshot["chapter_id"].as_str().unwrap_or_default(),
+                snapshot["snapshot_content"].as_str().unwrap_or_default(),
+                snapshot["source"].as_str().unwrap_or("user"),
+                snapshot["created_at"].as_str().unwrap_or_default(),
+            ],
+        )
+        .map_err(|e| format!("恢复快照失败: {}", e))?;
+    }
+    for item in merge["moved"].as_array().into_iter().flatten() {
+        restore_refs(conn, &item["refs"], item["chapter_id"].as_str().unwrap_or_default())?;
+    }
+
+    restore_fields(conn, survivor, &current_status, mode)?;
+    let absorbed_ids: Vec<&str> = absorbed.iter().map(|row| row["id"].as_str().unwrap_or_default()).collect();
+    place_after(
+        conn,
+        survivor["volume_id"].as_str().unwrap_or_default(),
+        survivor_id,
+        &absorbed_ids,
+    )?;
+
+    vector::refresh_chapter(conn, survivor_id)?;
+    for id in &absorbed_ids {
+        vector::refresh_chapter(conn, id)?;
+    }
+    arc::invalidate_chapter(conn, survivor_id)
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+/// 按字符偏移拆分正文并去掉断开处两侧的空白，返回（前半, 后半, 后半在原文中的字符偏移）
+fn split_content(content: &str, offset: usize) -> Result<(String, String, usize), String> {
+    let head: String = content.chars().take(offset).collect();
+    let rest: String = content.chars().skip(offset).collect();
+    let tail = rest.trim_start();
+    let tail_start = offset + rest.chars().count() - tail.chars().count();
+    let head = head.trim_end();
+    if head.is_empty() || tail.is_empty() {
+        return Err("拆分位置两侧都需要有正文".into());
+    }
+    Ok((head.to_string(), tail.to_string(), tail_start))
+}
+
+/// 把指向 `from` 的引用全部改指 `to`，伏笔锚点偏移加上 `anchor_shift`；返回被改动的记录，供撤销时还原
+fn repoint(
+    conn: &rusqlite::Connection,
+    from: &str,
+    to: &str,
+    anchor_shift: i64,
+) -> Result<serde_json::Value, String> {
+    let mut moved = serde_json::Map::new();
+    for (table, column) in CHAPTER_REFS {
+        let ids = query_ids(conn, &format!("SELECT id FROM {} WHERE {} = ?1", table, column), from)?;
+        conn.execute(
+            &format!("UPDATE {0} SET {1} = ?1 WHERE {1} = ?2", table, column),
+            params![to, from],
+        )
+        .map_err(|e| format!("更新章节引用失败: {}", e))?;
+        moved.insert(format!("{}.{}", table, column), serde_json::json!(ids));
+    }
+
+    let anchors = {
+        let mut stmt = conn
+            .prepare("SELECT id, char_offset FROM foreshadow_anchors WHERE chapter_id = ?1")
+            .map_err(|e| format!("查询伏笔锚点失败: {}", e))?;
+        let anchors = stmt
+            .query_map(params![from], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+            .map_err(|e| format!("读取伏笔锚点失败: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("解析伏笔锚点失败: {}", e))?;
+        anchors
+    };
+    conn.execute(
+        "UPDATE foreshadow_anchors SET chapter_id = ?1, char_offset = char_offset + ?2 WHERE chapter_id = ?3",
+        params![to, anchor_shift, from],
+    )
+    .map_err(|e| format!("迁移伏笔锚点失败: {}", e))?;
+    moved.insert("foreshadow_anchors".into(), serde_json::json!(anchors));
+    Ok(serde_json::Value::Object(moved))
+}
+
+/// 将 `repoint` 改动的记录指回 `chapter_id`
+fn restore_refs(conn: &rusqlite::Connection, moved: &serde_json::Value, chapter_id: &str) -> Result<(), String> {
+    for (table, column) in CHAPTER_REFS {
+        for id in moved[format!("{}.{}", table, column).as_str()].as_array().into_iter().flatten() {
+            conn.execute(
+                &format!("UPDATE {} SET {} = ?1 WHERE id = ?2", table, column),
+                params![chapter_id, id.as_str().unwrap_or_default()],
+            )
+            .map_err(|e| format!("还原章节引用失败: {}", e))?;
+        }
+    }
+    for anchor in moved["foreshadow_anchors"].as_array().into_iter().flatten() {
+        conn.execute(
+            "UPDATE foreshadow_anchors SET chapter_id = ?1, char_offset = ?2 WHERE id = ?3",
+            params![chapter_id, anchor[1].as_i64().unwrap_or_default(), anchor[0].as_str().unwrap_or_default()],
+        )
+        .map_err(|e| format!("还原伏笔锚点失败: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 还原章节正文、摘要与状态（状态有变化时记录为系统变更）
+fn restore_fields(
+    conn: &rusqlite::Connection,
+    row: &serde_json::Value,
+    current_status: &str,
+    mode: CountMode,
+) -> Result<(), String> {
+    let id = row["id"].as_str().unwrap_or_default();
+    let content = row["content"].as_str().unwrap_or_default();
+    let status_key = row["status"].as_str().unwrap_or("draft");
+    conn.execute(
+        "UPDATE chapters SET content = ?1, word_count = ?2, l2_summary = ?3, l3_title = ?4, status = ?5,
+         published_at = ?6, updated_at = ?7 WHERE id = ?8",
+        params![
+            content,
+            count::count(content, mode),
+            row["l2_summary"].as_str(),
+            row["l3_title"].as_str(),
+            status_key,
+            row["published_at"].as_str(),
+            chrono::Utc::now().to_rfc3339(),
+            id,
+        ],
+    )
+    .map_err(|e| format!("还原章节失败: {}", e))?;
+    if status_key != current_status {
+        status::record(conn, id, Some(current_status), status_key, "system")?;
+    }
+    Ok(())
+}
+
+/// 把 `inserted` 按顺序放到 `after_id` 之后，并把分卷内的 sort_order 重排为 0..n
+///
+/// `inserted` 为空时只重排。
+fn place_after(conn: &rusqlite::Connection, volume_id: &str, after_id: &str, inserted: &[&str]) -> Result<(), String> {
+    let mut order: Vec<String> = volume_order(conn, volume_id)?
+        .into_iter()
+        .filter(|id| !inserted.contains(&id.as_str()))
+        .collect();
+    let position = order.iter().position(|id| id == after_id).map_or(order.len(), |p| p + 1);
+    order.splice(position..position, inserted.iter().map(|id| id.to_string()));
+    for (i, id) in order.iter().enumerate() {
+        conn.execute(
+            "UPDATE chapters SET sort_order = ?1 WHERE id = ?2",
+            params![i as i64, id],
+        )
+        .map_err(|e| format!("排序章节失败: {}", e))?;
+    }
+    Ok(())
+}
+
+fn volume_order(conn: &rusqlite::Connection, volume_id: &str) -> Result<Vec<String>, String> {
+    query_ids(
+        conn,
+        "SELECT id FROM chapters WHERE volume_id = ?1 ORDER BY sort_order ASC, created_at ASC",
+        volume_id,
+    )
+}
+
+/// 原章节关联的实体（首次/最后出场或时间线节点在该章）及其名称与别名
+fn referenced_entities(conn: &rusqlite::Connection, chapter_id: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT e.id, e.name, (SELECT json_group_array(alias) FROM entity_aliases WHERE entity_id = e.id)
+             FROM entities e
+             WHERE e.first_chapter_id = ?1 OR e.last_chapter_id = ?1
+                OR e.id IN (SELECT entity_id FROM timeline WHERE chapter_id = ?1)",
+        )
+        .map_err(|e| format!("查询实体失败: {}", e))?;
+    let rows = stmt
+        .query_map(params![chapter_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })
+        .map_err(|e| format!("读取实体失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析实体失败: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, name, aliases)| {
+            let mut names: Vec<String> = serde_json::from_str(&aliases).unwrap_or_default();
+            names.push(name);
+            names.retain(|n| !n.is_empty());
+            (id, names)
+        })
+        .collect())
+}
+
+fn chapter_snapshots(conn: &rusqlite::Connection, chapter_id: &str) -> Result<Vec<serde_json::Value>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT json_object('id', id, 'chapter_id', chapter_id, 'snapshot_content', snapshot_content,
+             'source', source, 'created_at', created_at) FROM snapshots WHERE chapter_id = ?1",
+        )
+        .map_err(|e| format!("查询快照失败: {}", e))?;
+    let snapshots = stmt
+        .query_map(params![chapter_id], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("读取快照失败: {}", e))?
+        .map(|json| {
+            json.map_err(|e| format!("解析快照失败: {}", e))
+                .and_then(|json| serde_json::from_str(&json).map_err(|e| format!("解析快照失败: {}", e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(snapshots)
+}
+
+fn insert_snapshot(
+    conn: &rusqlite::Connection,
+    chapter_id: &str,
+    content: &str,
+    source: &str,
+    now: &str,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO snapshots (id, chapter_id, snapshot_content, source, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![uuid::Uuid::new_v4().to_string(), chapter_id, content, source, now],
+    )
+    .map_err(|e| format!("创建快照失败: {}", e))?;
+    Ok(())
+}
+
+fn insert_trash(
+    conn: &rusqlite::Connection,
+    original_id: &str,
+    data: &serde_json::Value,
+    now: &str,
+) -> Result<String, String> {
+    let trash_id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO trash (id, original_table, original_id, data_json, deleted_at, deleted_by) VALUES (?1, 'chapters', ?2, ?3, ?4, 'user')",
+        params![trash_id, original_id, data.to_string(), now],
+    )
+    .map_err(|e| format!("写入还原记录失败: {}", e))?;
+    Ok(trash_id)
+}
+
+fn query_ids(conn: &rusqlite::Connection, sql: &str, key: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| format!("查询失败: {}", e))?;
+    let ids = stmt
+        .query_map(params![key], |row| row.get(0))
+        .map_err(|e| format!("读取失败: {}", e))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| format!("解析失败: {}", e))?;
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{snapshot, volume};
+    use crate::db::TestBook;
+
+    /// 分卷内的章节 ID 与 sort_order（按 sort_order）
+    fn sorted(conn: &rusqlite::Connection, volume_id: &str) -> Vec<(String, i64)> {
+        let mut stmt = conn
+            .prepare("SELECT id, sort_order FROM chapters WHERE volume_id = ?1 ORDER BY sort_order")
+            .unwrap();
+        let rows = stmt
+            .query_map([volume_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        rows
+    }
+
+    fn text(conn: &rusqlite::Connection, sql: &str, key: &str) -> String {
+        conn.query_row(sql, [key], |row| row.get(0)).unwrap()
+    }
+
+    fn anchor(conn: &rusqlite::Connection) -> (String, i64) {
+        conn.query_row("SELECT chapter_id, char_offset FROM foreshadow_anchors WHERE id = 'a'", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap()
+    }
+
+    /// 两个章节的测试书籍；第一章后半部分的「李四」带时间线节点与伏笔锚点
+    async fn test_book() -> (TestBook, String, String, String) {
+        let book = TestBook::new();
+        let volume = volume::create_volume(book.to_string(), "第一卷".into()).await.unwrap();
+        let first = chapter::create_chapter(book.to_string(), volume.id.clone(), "第一章".into()).await.unwrap();
+        let second = chapter::create_chapter(book.to_string(), volume.id.clone(), "第二章".into()).await.unwrap();
+        let conn = open_book(&book).unwrap();
+        chapter::save_content(&conn, &first.id, "他走进城门。\n\n李四在城中等他。", "user", CountMode::default()).unwrap();
+        chapter::save_content(&conn, &second.id, "次日，李四离开。", "user", CountMode::default()).unwrap();
+        conn.execute_batch(&format!(
+            "INSERT INTO entities (id, name, entity_type, first_chapter_id, last_chapter_id, created_at, updated_at)
+             VALUES ('e', '李四', 'character', '{0}', '{1}', '', '');
+             INSERT INTO timeline (id, entity_id, chapter_id, event, created_at) VALUES ('t1', 'e', '{0}', '等候', '');
+             INSERT INTO timeline (id, entity_id, chapter_id, event, created_at) VALUES ('t2', 'e', '{1}', '离开', '');
+             INSERT INTO foreshadows (id, description, plant_chapter_id, created_at, updated_at) VALUES ('f', '等候', '{0}', '', '');
+             INSERT INTO foreshadow_anchors (id, foreshadow_id, role, chapter_id, quote, char_offset, created_at, updated_at)
+             VALUES ('a', 'f', 'plant', '{0}', '李四在城中等他', 8, '', '');",
+            first.id, second.id
+        ))
+        .unwrap();
+        (book, volume.id, first.id, second.id)
+    }
+
+    #[test]
+    fn split_content_trims_whitespace_at_the_break() {
+        let content = "甲乙。\n\n  丙丁";
+        assert_eq!(split_content(content, 3).unwrap(), ("甲乙。".into(), "丙丁".into(), 7));
+        assert_eq!(split_content(content, 5).unwrap(), ("甲乙。".into(), "丙丁".into(), 7));
+        assert_eq!(split_content("甲乙", 1).unwrap(), ("甲".into(), "乙".into(), 1));
+        for offset in [0, 2, 10] {
+            assert!(split_content("甲乙", offset).is_err());
+        }
+        assert!(split_content("甲\n\n", 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn place_after_reorders_the_volume() {
+        let (book, volume_id, first, second) = test_book().await;
+        let third = chapter::create_chapter(book.to_string(), volume_id.clone(), "第三章".into()).await.unwrap().id;
+        let conn = open_book(&book).unwrap();
+
+        place_after(&conn, &volume_id, &first, &[third.as_str()]).unwrap();
+        assert_eq!(sorted(&conn, &volume_id), vec![(first.clone(), 0), (third.clone(), 1), (second.clone(), 2)]);
+        // 找不到 after_id 时放到末尾
+        place_after(&conn, &volume_id, "missing", &[first.as_str()]).unwrap();
+        assert_eq!(sorted(&conn, &volume_id), vec![(third.clone(), 0), (second.clone(), 1), (first.clone(), 2)]);
+        // 为空时只重排
+        conn.execute("UPDATE chapters SET sort_order = sort_order * 10 + 5", []).unwrap();
+        place_after(&conn, &volume_id, "", &[]).unwrap();
+        assert_eq!(sorted(&conn, &volume_id), vec![(third, 0), (second, 1), (first, 2)]);
+    }
+
+    #[tokio::test]
+    async fn split_then_undo_restores_the_chapter() {
+        let (book, volume_id, first, second) = test_book().await;
+        let mut conn = open_book(&book).unwrap();
+        let mode = CountMode::default();
+
+        let (new_id, trash_id) = split(&mut conn, &first, 6, None, mode).unwrap();
+        let content = "SELECT content FROM chapters WHERE id = ?1";
+        assert_eq!(text(&conn, content, &first), "他走进城门。");
+        assert_eq!(text(&conn, content, &new_id), "李四在城中等他。");
+        assert_eq!(sorted(&conn, &volume_id), vec![(first.clone(), 0), (new_id.clone(), 1), (second.clone(), 2)]);
+        assert_eq!(anchor(&conn), (new_id.clone(), 0));
+        assert_eq!(text(&conn, "SELECT chapter_id FROM timeline WHERE id = ?1", "t1"), new_id);
+        assert_eq!(text(&conn, "SELECT first_chapter_id FROM entities WHERE id = ?1", "e"), new_id);
+
+        snapshot::restore_trash_item(&mut conn, &book, &trash_id, mode).unwrap();
+        assert_eq!(text(&conn, content, &first), "他走进城门。\n\n李四在城中等他。");
+        assert_eq!(sorted(&conn, &volume_id), vec![(first.clone(), 0), (second, 1)]);
+        assert_eq!(anchor(&conn), (first.clone(), 8));
+        assert_eq!(text(&conn, "SELECT chapter_id FROM timeline WHERE id = ?1", "t1"), first);
+        assert_eq!(text(&conn, "SELECT first_chapter_id FROM entities WHERE id = ?1", "e"), first);
+    }
+
+    #[tokio::test]
+    async fn merge_then_undo_restores_the_chapters() {
+        let (book, volume_id, first, second) = test_book().await;
+        let mut conn = open_book(&book).unwrap();
+        let mode = CountMode::default();
+        conn.execute("UPDATE foreshadow_anchors SET chapter_id = ?1, char_offset = 3", [&second]).unwrap();
+
+        let trash_id = merge(&mut conn, &[first.clone(), second.clone()], mode).unwrap();
+        let content = "SELECT content FROM chapters WHERE id = ?1";
+        assert_eq!(text(&conn, content, &first), "他走进城门。\n\n李四在城中等他。\n\n次日，李四离开。");
+        assert_eq!(sorted(&conn, &volume_id), vec![(first.clone(), 0)]);
+        assert_eq!(anchor(&conn), (first.clone(), 21));
+        assert_eq!(text(&conn, "SELECT chapter_id FROM timeline WHERE id = ?1", "t2"), first);
+        assert_eq!(text(&conn, "SELECT last_chapter_id FROM entities WHERE id = ?1", "e"), first);
+
+        snapshot::restore_trash_item(&mut conn, &book, &trash_id, mode).unwrap();
+        assert_eq!(text(&conn, content, &first), "他走进城门。\n\n李四在城中等他。");
+        assert_eq!(text(&conn, content, &second), "次日，李四离开。");
+        assert_eq!(sorted(&conn, &volume_id), vec![(first, 0), (second.clone(), 1)]);
+        assert_eq!(anchor(&conn), (second.clone(), 3));
+        assert_eq!(text(&conn, "SELECT chapter_id FROM timeline WHERE id = ?1", "t2"), second);
+        assert_eq!(text(&conn, "SELECT last_chapter_id FROM entities WHERE id = ?1", "e"), second);
+        let snapshots: i64 = conn
+            .query_row("SELECT COUNT(*) FROM snapshots WHERE chapter_id = ?1", [&second], |row| row.get(0))
+            .unwrap();
+        assert!(snapshots > 0);
+    }
+}
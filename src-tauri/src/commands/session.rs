@@ -0,0 +1,364 @@
+//! 写作会话
+//!
+//! 会话由后端计时：开始、每次保存章节、停止都算一次活动，相邻两次活动的间隔计入有效时长并
+//! 同步累加到每日统计。距最近活动超过空闲超时（settings 的 `session_idle_minutes`）时，
+//! 会话在最近活动处结束，之后的时间不计入。后台任务定期检查空闲并推送 `writing-session`
+//! 事件；开启番茄钟时在每段工作 / 休息结束时推送 `pomodoro` 事件。
+
+use crate::commands::{book, stats};
+use crate::db;
+use crate::db::models::WritingSession;
+use chrono::{DateTime, Local};
+use rusqlite::params;
+use tauri::{AppHandle, Emitter};
+
+/// 未设置 `session_idle_minutes` 时的空闲超时（分钟）
+const DEFAULT_IDLE_MINUTES: i64 = 5;
+
+/// 开启番茄钟但未指定休息时长时的默认值（分钟）
+const DEFAULT_BREAK_MINUTES: i64 = 5;
+
+/// 后台检查空闲与番茄钟的间隔
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+const SESSION_COLUMNS: &str = "id, book_id, chapter_id, started_at, ended_at, last_activity_at, duration_seconds,
+     words_added, words_deleted, end_reason, pomodoro_work_minutes, pomodoro_break_minutes";
+
+/// `writing-session` 事件：会话因空闲自动结束
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SessionEvent {
+    /// idle
+    pub kind: String,
+    pub session: WritingSession,
+}
+
+/// `pomodoro` 事件：一段工作或休息结束
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PomodoroEvent {
+    pub session_id: String,
+    /// work_end / break_end
+    pub kind: String,
+    /// 第几个番茄（从 1 开始）
+    pub round: i64,
+}
+
+/// 开始写作会话（已有进行中的会话时先将其结束）
+///
+/// `pomodoro_work_minutes` 大于 0 时开启番茄钟。
+#[tauri::command]
+pub async fn start_writing_session(
+    app: AppHandle,
+    storage_path: String,
+    chapter_id: Option<String>,
+    pomodoro_work_minutes: Option<i64>,
+    pomodoro_break_minutes: Option<i64>,
+) -> Result<WritingSession, String> {
+    let work = pomodoro_work_minutes.unwrap_or(0);
+    let rest = match pomodoro_break_minutes {
+        Some(m) => m,
+        None if work > 0 => DEFAULT_BREAK_MINUTES,
+        None => 0,
+    };
+    if work < 0 || rest < 0 {
+        return Err("番茄钟时长不能为负数".into());
+    }
+    if work > 0 && rest == 0 {
+        return Err("开启番茄钟时休息时长必须大于 0".into());
+    }
+
+    let conn = db::init_global_db()?;
+    let now = Local::now();
+    if let Some(active) = active_session(&conn, &now)? {
+        finish(&conn, &active, &now, "manual")?;
+    }
+
+    let session = WritingSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        book_id: book::book_id_for(&conn, &storage_path)?,
+        chapter_id,
+        started_at: now.to_rfc3339(),
+        ended_at: None,
+        last_activity_at: now.to_rfc3339(),
+        duration_seconds: 0,
+        words_added: 0,
+        words_deleted: 0,
+        end_reason: None,
+        pomodoro_work_minutes: work,
+        pomodoro_break_minutes: rest,
+    };
+    conn.execute(
+        "INSERT INTO writing_sessions (id, book_id, chapter_id, started_at, last_activity_at,
+         pomodoro_work_minutes, pomodoro_break_minutes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            session.id,
+            session.book_id,
+            session.chapter_id,
+            session.started_at,
+            session.last_activity_at,
+            session.pomodoro_work_minutes,
+            session.pomodoro_break_minutes,
+        ],
+    )
+    .map_err(|e| format!("创建写作会话失败: {}", e))?;
+
+    watch(app, session.clone());
+    Ok(session)
+}
+
+/// 停止进行中的写作会话，返回结束后的会话（没有进行中的会话时为 None）
+#[tauri::command]
+pub async fn stop_writing_session() -> Result<Option<WritingSession>, String> {
+    let conn = db::init_global_db()?;
+    let now = Local::now();
+    match active_session(&conn, &now)? {
+        Some(active) => {
+            finish(&conn, &active, &now, "manual")?;
+            load_session(&conn, &active.id)
+        }
+        None => Ok(None),
+    }
+}
+
+/// 获取进行中的写作会话（已空闲超时的会话在此结束并返回 None）
+#[tauri::command]
+pub async fn get_active_writing_session() -> Result<Option<WritingSession>, String> {
+    let conn = db::init_global_db()?;
+    active_session(&conn, &Local::now())
+}
+
+/// 获取写作会话历史（按开始日期筛选，`book_id` 为空时返回所有书，按开始时间倒序）
+#[tauri::command]
+pub async fn list_writing_sessions(
+    start_date: String,
+    end_date: String,
+    book_id: Option<String>,
+) -> Result<Vec<WritingSession>, String> {
+    let conn = db::init_global_db()?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM writing_sessions
+             WHERE substr(started_at, 1, 10) >= ?1 AND substr(started_at, 1, 10) <= ?2
+               AND (?3 IS NULL OR book_id = ?3)
+             ORDER BY started_at DESC",
+            SESSION_COLUMNS
+        ))
+        .map_err(|e| format!("查询写作会话失败: {}", e))?;
+    let sessions = stmt
+        .query_map(params![start_date, end_date, book_id], row_to_session)
+        .map_err(|e| format!("读取写作会话失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析写作会话失败: {}", e))?;
+    Ok(sessions)
+}
+
+/// 保存章节时记入进行中的会话：计入时长、刷新活动时间、累加增删字数
+///
+/// 只记入同一本书的会话；保存的是其他书的章节时不改动会话。
+pub(crate) fn record_save(
+    conn: &rusqlite::Connection,
+    book_id: Option<&str>,
+    chapter_id: &str,
+    delta: i64,
+) -> Result<(), String> {
+    let now = Local::now();
+    let Some(session) = active_session(conn, &now)? else {
+        return Ok(());
+    };
+    if session.book_id.as_deref() != book_id {
+        return Ok(());
+    }
+    credit(conn, &session, &now)?;
+    conn.execute(
+        "UPDATE writing_sessions SET chapter_id = ?1,
+         words_added = words_added + ?2, words_deleted = words_deleted + ?3
+         WHERE id = ?4",
+        params![chapter_id, delta.max(0), (-delta).max(0), session.id],
+    )
+    .map_err(|e| format!("更新写作会话失败: {}", e))?;
+    Ok(())
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+fn row_to_session(row: &rusqlite::Row) -> rusqlite::Result<WritingSession> {
+    Ok(WritingSession {
+        id: row.get(0)?,
+        book_id: row.get(1)?,
+        chapter_id: row.get(2)?,
+        started_at: row.get(3)?,
+        ended_at: row.get(4)?,
+        last_activity_at: row.get(5)?,
+        duration_seconds: row.get(6)?,
+        words_added: row.get(7)?,
+        words_deleted: row.get(8)?,
+        end_reason: row.get(9)?,
+        pomodoro_work_minutes: row.get(10)?,
+        pomodoro_break_minutes: row.get(11)?,
+    })
+}
+
+fn load_session(conn: &rusqlite::Connection, id: &str) -> Result<Option<WritingSession>, String> {
+    match conn.query_row(
+        &format!("SELECT {} FROM writing_sessions WHERE id = ?1", SESSION_COLUMNS),
+        params![id],
+        row_to_session,
+    ) {
+        Ok(session) => Ok(Some(session)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(format!("查询写作会话失败: {}", e)),
+    }
+}
+
+/// 进行中的会话；距最近活动已超过空闲超时的会话在最近活动处结束，返回 None
+fn active_session(
+    conn: &rusqlite::Connection,
+    now: &DateTime<Local>,
+) -> Result<Option<WritingSession>, String> {
+    let session = match conn.query_row(
+        &format!(
+            "SELECT {} FROM writing_sessions WHERE ended_at IS NULL ORDER BY started_at DESC LIMIT 1",
+            SESSION_COLUMNS
+        ),
+        [],
+        row_to_session,
+    ) {
+        Ok(session) => session,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(e) => return Err(format!("查询写作会话失败: {}", e)),
+    };
+
+    if (*now - parse_time(&session.last_activity_at)?).num_seconds() <= idle_timeout_seconds(conn) {
+        return Ok(Some(session));
+    }
+    conn.execute(
+        "UPDATE writing_sessions SET ended_at = last_activity_at, end_reason = 'idle' WHERE id = ?1",
+        params![session.id],
+    )
+    .map_err(|e| format!("结束写作会话失败: {}", e))?;
+    Ok(None)
+}
+
+/// 把距最近活动的时长计入会话与当日统计，并刷新最近活动时间
+fn credit(
+    conn: &rusqlite::Connection,
+    session: &WritingSession,
+    now: &DateTime<Local>,
+) -> Result<(), String> {
+    let elapsed = (*now - parse_time(&session.last_activity_at)?).num_seconds().max(0);
+    conn.execute(
+        "UPDATE writing_sessions SET duration_seconds = duration_seconds + ?1, last_activity_at = ?2 WHERE id = ?3",
+        params![elapsed, now.to_rfc3339(), session.id],
+    )
+    .map_err(|e| format!("更新写作会话失败: {}", e))?;
+    if elapsed > 0 {
+        stats::add_duration(conn, session.book_id.as_deref(), &now.format("%Y-%m-%d").to_string(), elapsed)?;
+    }
+    Ok(())
+}
+
+fn finish(
+    conn: &rusqlite::Connection,
+    session: &WritingSession,
+    now: &DateTime<Local>,
+    reason: &str,
+) -> Result<(), String> {
+    credit(conn, session, now)?;
+    conn.execute(
+        "UPDATE writing_sessions SET ended_at = ?1, end_reason = ?2 WHERE id = ?3",
+        params![now.to_rfc3339(), reason, session.id],
+    )
+    .map_err(|e| format!("结束写作会话失败: {}", e))?;
+    Ok(())
+}
+
+fn idle_timeout_seconds(conn: &rusqlite::Connection) -> i64 {
+    conn.query_row(
+        "SELECT value FROM settings WHERE key = 'session_idle_minutes'",
+        [],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|v| v.parse::<i64>().ok())
+    .filter(|m| *m > 0)
+    .unwrap_or(DEFAULT_IDLE_MINUTES)
+        * 60
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Local>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Local))
+        .map_err(|e| format!("解析会话时间失败: {}", e))
+}
+
+/// 后台检查会话是否空闲结束，并在番茄钟每段结束时推送事件；会话结束后退出
+fn watch(app: AppHandle, session: WritingSession) {
+    tauri::async_runtime::spawn(async move {
+        let Ok(started) = parse_time(&session.started_at) else {
+            return;
+        };
+        let conn = match db::open_global_db() {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::warn!("检查写作会话失败: {}", e);
+                return;
+            }
+        };
+        let mut phases_done = 0;
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+            let now = Local::now();
+            match active_session(&conn, &now) {
+                Ok(Some(active)) if active.id == session.id => {}
+                Ok(_) => {
+                    if let Ok(Some(ended)) = load_session(&conn, &session.id) {
+                        if ended.end_reason.as_deref() == Some("idle") {
+                            let _ = app.emit(
+                                "writing-session",
+                                SessionEvent {
+                                    kind: "idle".into(),
+                                    session: ended,
+                                },
+                            );
+                        }
+                    }
+                    return;
+                }
+                Err(e) => {
+                    log::warn!("检查写作会话失败: {}", e);
+                    return;
+                }
+            }
+            for event in pomodoro_events(&session, (now - started).num_seconds(), &mut phases_done) {
+                let _ = app.emit("pomodoro", event);
+            }
+        }
+    });
+}
+
+/// 开始后 `elapsed` 秒时新结束的番茄钟阶段（工作、休息交替）
+fn pomodoro_events(session: &WritingSession, elapsed: i64, phases_done: &mut i64) -> Vec<PomodoroEvent> {
+    let work = session.pomodoro_work_minutes * 60;
+    let rest = session.pomodoro_break_minutes * 60;
+    if work <= 0 {
+        return Vec::new();
+    }
+    let cycle = work + rest;
+    let mut completed = elapsed / cycle * 2;
+    if elapsed % cycle >= work {
+        completed += 1;
+    }
+
+    let mut events = Vec::new();
+    while *phases_done < completed {
+        *phases_done += 1;
+        events.push(PomodoroEvent {
+            session_id: session.id.clone(),
+            kind: if *phases_done % 2 == 1 { "work_end" } else { "break_end" }.into(),
+            round: (*phases_done + 1) / 2,
+        });
+    }
+    events
+}
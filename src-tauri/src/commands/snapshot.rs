@@ -1,6 +1,8 @@
+use crate::commands::{arc, chapter, entity, foreshadow, restructure, stats, vector};
 use crate::db;
 use crate::db::config;
 use crate::db::models::{Snapshot, TrashItem};
+use crate::text::count::{self, CountMode};
 use rusqlite::params;
 
 fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
@@ -18,7 +20,7 @@ pub async fn list_snapshots(storage_path: String, chapter_id: String) -> Result<
     let conn = open_book(&storage_path)?;
     let mut stmt = conn
         .prepare(
-            "SELECT id, chapter_id, snapshot_content, created_at
+            "SELECT id, chapter_id, snapshot_content, source, created_at
              FROM snapshots WHERE chapter_id = ?1 ORDER BY created_at DESC",
         )
         .map_err(|e| format!("查询快照失败: {}", e))?;
@@ -29,7 +31,8 @@ pub async fn list_snapshots(storage_path: String, chapter_id: String) -> Result<
                 id: row.get(0)?,
                 chapter_id: row.get(1)?,
                 snapshot_content: row.get(2)?,
-                created_at: row.get(3)?,
+                source: row.get(3)?,
+                created_at: row.get(4)?,
             })
         })
         .map_err(|e| format!("读取快照失败: {}", e))?
@@ -42,6 +45,7 @@ pub async fn list_snapshots(storage_path: String, chapter_id: String) -> Result<
 /// 从快照恢复章节内容
 #[tauri::command]
 pub async fn restore_snapshot(storage_path: String, snapshot_id: String) -> Result<(), String> {
+    let mode = stats::count_mode()?;
     let conn = open_book(&storage_path)?;
     let now = chrono::Utc::now().to_rfc3339();
 
@@ -54,7 +58,10 @@ pub async fn restore_snapshot(storage_path: String, snapshot_id: String) -> Resu
         )
         .map_err(|e| format!("读取快照失败: {}", e))?;
 
-    let word_count = content.chars().count() as i64;
+    let word_count = count::count(&content, mode);
+    let previous: i64 = conn
+        .query_row("SELECT word_count FROM chapters WHERE id = ?1", params![chapter_id], |row| row.get(0))
+        .map_err(|e| format!("获取章节失败: {}", e))?;
 
     // 写回章节
     conn.execute(
@@ -63,6 +70,10 @@ pub async fn restore_snapshot(storage_path: String, snapshot_id: String) -> Resu
     )
     .map_err(|e| format!("恢复快照失败: {}", e))?;
 
+    arc::invalidate_chapter(&conn, &chapter_id)?;
+    vector::refresh_chapter(&conn, &chapter_id)?;
+    stats::log_writing(&conn, &storage_path, &chapter_id, word_count - previous, "restore")?;
+
     Ok(())
 }
 
@@ -122,12 +133,24 @@ pub async fn list_trash(storage_path: String) -> Result<Vec<TrashItem>, String>
     Ok(items)
 }
 
-/// 从回收站恢复记录
+/// 从回收站恢复记录（整个恢复在一个事务内完成，任一步失败则不留下部分恢复的数据）
 #[tauri::command]
 pub async fn restore_from_trash(storage_path: String, trash_id: String) -> Result<(), String> {
-    let conn = open_book(&storage_path)?;
+    let mode = stats::count_mode()?;
+    let mut conn = open_book(&storage_path)?;
+    restore_trash_item(&mut conn, &storage_path, &trash_id, mode)
+}
 
-    let (original_table, data_json): (String, String) = conn
+/// 恢复一条回收站记录（`restore_from_trash` 的事务部分，字数按 `mode` 统计）
+pub(crate) fn restore_trash_item(
+    conn: &mut rusqlite::Connection,
+    storage_path: &str,
+    trash_id: &str,
+    mode: CountMode,
+) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+
+    let (original_table, data_json): (String, String) = tx
         .query_row(
             "SELECT original_table, data_json FROM trash WHERE id = ?1",
             params![trash_id],
@@ -138,19 +161,30 @@ pub async fn restore_from_trash(storage_path: String, trash_id: String) -> Resul
     let data: serde_json::Value = serde_json::from_str(&data_json)
         .map_err(|e| format!("解析回收站数据失败: {}", e))?;
 
+    let before = chapter::reading_order(&tx)?;
+
     // 根据原始表名恢复数据
     match original_table.as_str() {
-        "chapters" => restore_chapter(&conn, &data)?,
-        "volumes" => restore_volume(&conn, &data)?,
-        "entities" => restore_entity(&conn, &data)?,
+        "chapters" if data.get("split").is_some() => restructure::undo_split(&tx, &data, mode)?,
+        "chapters" if data.get("merge").is_some() => restructure::undo_merge(&tx, &data, mode)?,
+        "chapters" => restore_chapter(&tx, storage_path, &data, mode)?,
+        "volumes" => restore_volume(&tx, &data)?,
+        "entities" if data.get("merge").is_some() => entity::undo_merge(&tx, &data)?,
+        "entities" => restore_entity(&tx, &data)?,
+        "foreshadows" => restore_foreshadow(&tx, &data)?,
         _ => return Err(format!("不支持恢复表: {}", original_table)),
     }
 
+    // 恢复的章节可能重新落入剧情弧范围
+    if matches!(original_table.as_str(), "chapters" | "volumes") {
+        arc::invalidate_reordered(&tx, &before)?;
+    }
+
     // 删除回收站记录
-    conn.execute("DELETE FROM trash WHERE id = ?1", params![trash_id])
+    tx.execute("DELETE FROM trash WHERE id = ?1", params![trash_id])
         .map_err(|e| format!("删除回收站记录失败: {}", e))?;
 
-    Ok(())
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))
 }
 
 /// 清空过期的回收站记录
@@ -174,35 +208,25 @@ pub async fn clean_expired_trash(storage_path: String, retention_days: i64) -> R
 // 恢复辅助函数
 // ============================================================================
 
-fn restore_chapter(conn: &rusqlite::Connection, data: &serde_json::Value) -> Result<(), String> {
-    conn.execute(
-        "INSERT OR REPLACE INTO chapters (id, volume_id, name, content, l2_summary, l3_title, status, word_count, sort_order, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
-        params![
-            data["id"].as_str().unwrap_or_default(),
-            data["volume_id"].as_str().unwrap_or_default(),
-            data["name"].as_str().unwrap_or_default(),
-            data["content"].as_str().unwrap_or_default(),
-            data["l2_summary"].as_str(),
-            data["l3_title"].as_str(),
-            data["status"].as_str().unwrap_or("draft"),
-            data["word_count"].as_i64().unwrap_or(0),
-            data["sort_order"].as_i64().unwrap_or(0),
-            data["created_at"].as_str().unwrap_or_default(),
-            data["updated_at"].as_str().unwrap_or_default(),
-        ],
-    )
-    .map_err(|e| format!("恢复章节失败: {}", e))?;
+fn restore_chapter(
+    conn: &rusqlite::Connection,
+    storage_path: &str,
+    data: &serde_json::Value,
+    mode: CountMode,
+) -> Result<(), String> {
+    let word_count = chapter::insert_chapter_json(conn, data, mode)?;
+    stats::log_writing(conn, storage_path, data["id"].as_str().unwrap_or_default(), word_count, "restore")?;
     Ok(())
 }
 
 fn restore_volume(conn: &rusqlite::Connection, data: &serde_json::Value) -> Result<(), String> {
     conn.execute(
-        "INSERT OR REPLACE INTO volumes (id, name, sort_order, created_at) VALUES (?1, ?2, ?3, ?4)",
+        "INSERT OR REPLACE INTO volumes (id, name, sort_order, copied_from, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
         params![
             data["id"].as_str().unwrap_or_default(),
             data["name"].as_str().unwrap_or_default(),
             data["sort_order"].as_i64().unwrap_or(0),
+            data["copied_from"].as_str(),
             data["created_at"].as_str().unwrap_or_default(),
         ],
     )
@@ -212,8 +236,8 @@ fn restore_volume(conn: &rusqlite::Connection, data: &serde_json::Value) -> Resu
 
 fn restore_entity(conn: &rusqlite::Connection, data: &serde_json::Value) -> Result<(), String> {
     conn.execute(
-        "INSERT OR REPLACE INTO entities (id, name, entity_type, attributes_json, status, inbox, first_chapter_id, last_chapter_id, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        "INSERT OR REPLACE INTO entities (id, name, entity_type, attributes_json, status, inbox, first_chapter_id, last_chapter_id, created_by, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
         params![
             data["id"].as_str().unwrap_or_default(),
             data["name"].as_str().unwrap_or_default(),
@@ -223,10 +247,96 @@ fn restore_entity(conn: &rusqlite::Connection, data: &serde_json::Value) -> Resu
             data["inbox"].as_i64().unwrap_or(0),
             data["first_chapter_id"].as_str(),
             data["last_chapter_id"].as_str(),
+            data["created_by"].as_str().unwrap_or("user"),
             data["created_at"].as_str().unwrap_or_default(),
             data["updated_at"].as_str().unwrap_or_default(),
         ],
     )
     .map_err(|e| format!("恢复实体失败: {}", e))?;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    for alias in data["aliases"].as_array().into_iter().flatten() {
+        conn.execute(
+            "INSERT INTO entity_aliases (id, entity_id, alias, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                uuid::Uuid::new_v4().to_string(),
+                data["id"].as_str().unwrap_or_default(),
+                alias.as_str().unwrap_or_default(),
+                now,
+            ],
+        )
+        .map_err(|e| format!("恢复别名失败: {}", e))?;
+    }
+    for foreshadow_id in data["foreshadow_ids"].as_array().into_iter().flatten() {
+        conn.execute(
+            "INSERT OR IGNORE INTO foreshadow_entities (foreshadow_id, entity_id, created_at) VALUES (?1, ?2, ?3)",
+            params![
+                foreshadow_id.as_str().unwrap_or_default(),
+                data["id"].as_str().unwrap_or_default(),
+                now,
+            ],
+        )
+        .map_err(|e| format!("恢复伏笔关联失败: {}", e))?;
+    }
     Ok(())
 }
+
+fn restore_foreshadow(conn: &rusqlite::Connection, data: &serde_json::Value) -> Result<(), String> {
+    let id = data["id"].as_str().unwrap_or_default();
+    let status = data["status"].as_str().unwrap_or("open");
+    conn.execute(
+        "INSERT OR REPLACE INTO foreshadows (id, description, plant_chapter_id, reap_chapter_id, status,
+         planned_reap_chapter_id, deadline_chapters, priority, tags_json, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            id,
+            data["description"].as_str().unwrap_or_default(),
+            data["plant_chapter_id"].as_str(),
+            data["reap_chapter_id"].as_str(),
+            status,
+            data["planned_reap_chapter_id"].as_str(),
+            data["deadline_chapters"].as_i64(),
+            data["priority"].as_i64().unwrap_or(1),
+            data["tags_json"].as_str().unwrap_or("[]"),
+            data["created_at"].as_str().unwrap_or_default(),
+            data["updated_at"].as_str().unwrap_or_default(),
+        ],
+    )
+    .map_err(|e| format!("恢复伏笔失败: {}", e))?;
+
+    for anchor in data["anchors"].as_array().into_iter().flatten() {
+        conn.execute(
+            "INSERT OR REPLACE INTO foreshadow_anchors (id, foreshadow_id, role, chapter_id, quote, char_offset,
+             context_before, context_after, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                anchor["id"].as_str().unwrap_or_default(),
+                id,
+                anchor["role"].as_str().unwrap_or_default(),
+                anchor["chapter_id"].as_str().unwrap_or_default(),
+                anchor["quote"].as_str().unwrap_or_default(),
+                anchor["char_offset"].as_i64().unwrap_or_default(),
+                anchor["context_before"].as_str().unwrap_or_default(),
+                anchor["context_after"].as_str().unwrap_or_default(),
+                anchor["created_at"].as_str().unwrap_or_default(),
+                anchor["updated_at"].as_str().unwrap_or_default(),
+            ],
+        )
+        .map_err(|e| format!("恢复伏笔锚点失败: {}", e))?;
+    }
+    // 删除期间已被删除的实体不再关联
+    for link in data["entities"].as_array().into_iter().flatten() {
+        conn.execute(
+            "INSERT OR IGNORE INTO foreshadow_entities (foreshadow_id, entity_id, created_at)
+             SELECT ?1, id, ?3 FROM entities WHERE id = ?2",
+            params![
+                id,
+                link["entity_id"].as_str().unwrap_or_default(),
+                link["created_at"].as_str().unwrap_or_default(),
+            ],
+        )
+        .map_err(|e| format!("恢复伏笔关联失败: {}", e))?;
+    }
+
+    foreshadow::record_history(conn, id, Some("deleted"), status, None, None)
+}
@@ -1,6 +1,27 @@
+use crate::commands::{book, session};
 use crate::db;
-use crate::db::models::DailyStat;
+use crate::db::config;
+use crate::db::models::{BookDailyStat, DailyGoalRule, DailyStat};
+use crate::text::count::{self, CountMode};
+use chrono::{Datelike, Timelike};
 use rusqlite::params;
+use std::collections::HashMap;
+
+/// 不计入写作字数的来源：导入的文本、从快照或回收站恢复的内容
+const UNCOUNTED_SOURCES: [&str; 2] = ["import", "restore"];
+
+/// 全局目标规则的 scope
+const GLOBAL_SCOPE: &str = "global";
+
+/// `daily-goal-met` 事件：保存后当天字数首次达到目标
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GoalReached {
+    /// 书籍目标为书籍 ID，全局目标（所有书合计）为 None
+    pub book_id: Option<String>,
+    pub date: String,
+    pub goal: i64,
+    pub word_count: i64,
+}
 
 /// 获取每日统计（指定日期范围）
 #[tauri::command]
@@ -33,30 +54,123 @@ pub async fn get_daily_stats(
     Ok(stats)
 }
 
-/// 更新今日字数统计（增量：在现有基础上加 delta）
+/// 获取每本书的每日统计（指定日期范围，`book_id` 为空时返回所有书）
+#[tauri::command]
+pub async fn get_book_daily_stats(
+    book_id: Option<String>,
+    start_date: String,
+    end_date: String,
+) -> Result<Vec<BookDailyStat>, String> {
+    let conn = db::init_global_db()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT book_id, date, word_count, duration_seconds, daily_goal
+             FROM book_daily_stats
+             WHERE date >= ?1 AND date <= ?2 AND (?3 IS NULL OR book_id = ?3)
+             ORDER BY date ASC, book_id ASC",
+        )
+        .map_err(|e| format!("查询书籍统计失败: {}", e))?;
+
+    let stats = stmt
+        .query_map(params![start_date, end_date, book_id], |row| {
+            Ok(BookDailyStat {
+                book_id: row.get(0)?,
+                date: row.get(1)?,
+                word_count: row.get(2)?,
+                duration_seconds: row.get(3)?,
+                daily_goal: row.get(4)?,
+            })
+        })
+        .map_err(|e| format!("读取书籍统计失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析书籍统计失败: {}", e))?;
+
+    Ok(stats)
+}
+
+/// 累加写作时长（增量：在现有基础上加 delta）
+///
+/// 字数不再由前端上报，而是在保存章节时由写作日志自动累加（见 `log_writing`）。
+/// 传入 `storage_path` 时同时累加到该书的每日统计；`date` 为今天时计入当前小时的统计。
+/// 使用写作会话（见 `session` 模块）时时长由后端累加，无需再上报。
 #[tauri::command]
 pub async fn update_daily_stats(
     date: String,
-    word_count_delta: i64,
     duration_delta: i64,
+    storage_path: Option<String>,
 ) -> Result<(), String> {
     let conn = db::init_global_db()?;
-    let id = uuid::Uuid::new_v4().to_string();
+    let book_id = match storage_path {
+        Some(sp) => book::book_id_for(&conn, &sp)?,
+        None => None,
+    };
+    add_duration(&conn, book_id.as_deref(), &date, duration_delta)
+}
 
-    conn.execute(
-        "INSERT INTO daily_stats (id, date, word_count, duration_seconds, daily_goal)
-         VALUES (?1, ?2, ?3, ?4, 0)
-         ON CONFLICT(date) DO UPDATE SET
-            word_count = word_count + ?3,
-            duration_seconds = duration_seconds + ?4",
-        params![id, date, word_count_delta, duration_delta],
-    )
-    .map_err(|e| format!("更新统计失败: {}", e))?;
+/// 从所有书籍的写作日志重新汇总每日字数（合计与每本书）
+///
+/// 只覆盖日志中出现过的日期，日志启用前的历史统计保持不变；按小时统计的字数一并重建。
+/// 新补出的日期按当前规则记录当天目标，已有记录保留当时的目标。
+/// 返回合计统计更新的天数。
+#[tauri::command]
+pub async fn rebuild_daily_stats() -> Result<usize, String> {
+    let cfg = config::load_config()?;
+    let conn = db::init_global_db()?;
+    let mut stmt = conn
+        .prepare("SELECT id, storage_path FROM books")
+        .map_err(|e| format!("查询书籍失败: {}", e))?;
+    let books = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("读取书籍失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析书籍失败: {}", e))?;
 
-    Ok(())
+    let mut totals: HashMap<String, i64> = HashMap::new();
+    let mut hourly: HashMap<(String, i64), i64> = HashMap::new();
+    for (book_id, storage_path) in books {
+        // 磁盘上已不存在的书籍跳过，避免重新创建空库
+        if !config::book_db_path(&cfg, &storage_path).exists() {
+            continue;
+        }
+        let book = db::open_book_db(&cfg, &storage_path)?;
+        for (date, words) in daily_written(&book)? {
+            let goal = effective_goal(&conn, Some(&book_id), &date)?;
+            conn.execute(
+                "INSERT INTO book_daily_stats (book_id, date, word_count, duration_seconds, daily_goal)
+                 VALUES (?1, ?2, ?3, 0, ?4)
+                 ON CONFLICT(book_id, date) DO UPDATE SET word_count = ?3",
+                params![book_id, date, words, goal],
+            )
+            .map_err(|e| format!("更新书籍统计失败: {}", e))?;
+            *totals.entry(date).or_default() += words;
+        }
+        for (date, hour, words) in hourly_written(&book)? {
+            *hourly.entry((date, hour)).or_default() += words;
+        }
+    }
+
+    for ((date, hour), words) in &hourly {
+        conn.execute(
+            "INSERT INTO hourly_stats (date, hour, word_count, duration_seconds) VALUES (?1, ?2, ?3, 0)
+             ON CONFLICT(date, hour) DO UPDATE SET word_count = ?3",
+            params![date, hour, words],
+        )
+        .map_err(|e| format!("更新按小时统计失败: {}", e))?;
+    }
+
+    for (date, words) in &totals {
+        conn.execute(
+            "INSERT INTO daily_stats (id, date, word_count, duration_seconds, daily_goal)
+             VALUES (?1, ?2, ?3, 0, ?4)
+             ON CONFLICT(date) DO UPDATE SET word_count = ?3",
+            params![uuid::Uuid::new_v4().to_string(), date, words, effective_goal(&conn, None, date)?],
+        )
+        .map_err(|e| format!("更新统计失败: {}", e))?;
+    }
+    Ok(totals.len())
 }
 
-/// 设置每日目标字数
+/// 设置每日目标字数（全局默认，今天的统计随即改用新目标）
 #[tauri::command]
 pub async fn set_daily_goal(goal: i64) -> Result<(), String> {
     let conn = db::init_global_db()?;
@@ -65,5 +179,408 @@ pub async fn set_daily_goal(goal: i64) -> Result<(), String> {
         params![goal.to_string()],
     )
     .map_err(|e| format!("设置目标失败: {}", e))?;
+    snapshot_today_goals(&conn)
+}
+
+// ============================================================================
+// 目标规则
+// ============================================================================
+
+/// 设置书籍或按星期的目标（`weekday` 为 1-7 即周一至周日，为空表示每天）
+///
+/// 适用顺序：书籍按星期 → 书籍每天 → 全局按星期 → 全局默认（`set_daily_goal`）。
+/// `goal` 不大于 0 时删除该规则，改为沿用下一级目标。
+#[tauri::command]
+pub async fn set_daily_goal_rule(book_id: Option<String>, weekday: Option<i64>, goal: i64) -> Result<(), String> {
+    let weekday = weekday.unwrap_or(0);
+    if !(0..=7).contains(&weekday) {
+        return Err(format!("星期无效: {}", weekday));
+    }
+    if book_id.is_none() && weekday == 0 {
+        return set_daily_goal(goal.max(0)).await;
+    }
+
+    let conn = db::init_global_db()?;
+    let scope = match book_id {
+        Some(id) => {
+            let exists: i64 = conn
+                .query_row("SELECT COUNT(*) FROM books WHERE id = ?1", params![id], |row| row.get(0))
+                .map_err(|e| format!("查询书籍失败: {}", e))?;
+            if exists == 0 {
+                return Err("书籍不存在".into());
+            }
+            id
+        }
+        None => GLOBAL_SCOPE.to_string(),
+    };
+    if goal > 0 {
+        conn.execute(
+            "INSERT INTO daily_goals (scope, weekday, goal, updated_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(scope, weekday) DO UPDATE SET goal = ?3, updated_at = ?4",
+            params![scope, weekday, goal, chrono::Utc::now().to_rfc3339()],
+        )
+        .map_err(|e| format!("设置目标失败: {}", e))?;
+    } else {
+        conn.execute(
+            "DELETE FROM daily_goals WHERE scope = ?1 AND weekday = ?2",
+            params![scope, weekday],
+        )
+        .map_err(|e| format!("删除目标失败: {}", e))?;
+    }
+    snapshot_today_goals(&conn)
+}
+
+/// 获取全部目标规则（全局在前）
+#[tauri::command]
+pub async fn list_daily_goal_rules() -> Result<Vec<DailyGoalRule>, String> {
+    let conn = db::init_global_db()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT scope, weekday, goal, updated_at FROM daily_goals
+             ORDER BY scope != ?1, scope, weekday",
+        )
+        .map_err(|e| format!("查询目标失败: {}", e))?;
+    let rules = stmt
+        .query_map(params![GLOBAL_SCOPE], |row| {
+            Ok(DailyGoalRule {
+                scope: row.get(0)?,
+                weekday: row.get(1)?,
+                goal: row.get(2)?,
+                updated_at: row.get(3)?,
+            })
+        })
+        .map_err(|e| format!("读取目标失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析目标失败: {}", e))?;
+    Ok(rules)
+}
+
+/// 获取某天适用的目标字数（`date` 为空时为今天，`book_id` 为空时为全局目标）
+#[tauri::command]
+pub async fn get_daily_goal(book_id: Option<String>, date: Option<String>) -> Result<i64, String> {
+    let conn = db::init_global_db()?;
+    let date = date.unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+    effective_goal(&conn, book_id.as_deref(), &date)
+}
+
+/// 某天适用的目标字数：书籍按星期 → 书籍每天 → 全局按星期 → 全局默认
+pub(crate) fn effective_goal(
+    global: &rusqlite::Connection,
+    book_id: Option<&str>,
+    date: &str,
+) -> Result<i64, String> {
+    let weekday = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|d| d.weekday().number_from_monday() as i64)
+        .unwrap_or(0);
+    let rule = global.query_row(
+        "SELECT goal FROM daily_goals
+         WHERE scope IN (?1, ?2) AND weekday IN (?3, 0)
+         ORDER BY scope = ?2, weekday = 0 LIMIT 1",
+        params![book_id.unwrap_or(GLOBAL_SCOPE), GLOBAL_SCOPE, weekday],
+        |row| row.get(0),
+    );
+    match rule {
+        Ok(goal) => return Ok(goal),
+        Err(rusqlite::Error::QueryReturnedNoRows) => {}
+        Err(e) => return Err(format!("查询目标失败: {}", e)),
+    }
+    let value: String = match global.query_row(
+        "SELECT value FROM settings WHERE key = 'daily_goal'",
+        [],
+        |row| row.get(0),
+    ) {
+        Ok(v) => v,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(0),
+        Err(e) => return Err(format!("读取目标失败: {}", e)),
+    };
+    Ok(value.trim().parse().unwrap_or(0))
+}
+
+/// 目标变化后按新目标更新今天的统计（历史日期保持当时的目标）
+fn snapshot_today_goals(global: &rusqlite::Connection) -> Result<(), String> {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    global
+        .execute(
+            "UPDATE daily_stats SET daily_goal = ?1 WHERE date = ?2",
+            params![effective_goal(global, None, &today)?, today],
+        )
+        .map_err(|e| format!("更新统计失败: {}", e))?;
+
+    let mut stmt = global
+        .prepare("SELECT book_id FROM book_daily_stats WHERE date = ?1")
+        .map_err(|e| format!("查询书籍统计失败: {}", e))?;
+    let book_ids = stmt
+        .query_map(params![today], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("读取书籍统计失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析书籍统计失败: {}", e))?;
+    for book_id in book_ids {
+        global
+            .execute(
+                "UPDATE book_daily_stats SET daily_goal = ?1 WHERE book_id = ?2 AND date = ?3",
+                params![effective_goal(global, Some(&book_id), &today)?, book_id, today],
+            )
+            .map_err(|e| format!("更新书籍统计失败: {}", e))?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// 字数统计模式
+// ============================================================================
+
+/// 重新统计字数的结果
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecountResult {
+    pub chapters: usize,
+    /// 字数发生变化的章节数
+    pub changed: usize,
+    pub total_words: i64,
+}
+
+/// 设置字数统计模式（all / non_whitespace / cjk_words / qidian / fanqie）
+///
+/// 只影响之后的保存；已有章节需调用 `recompute_word_counts` 重新统计。
+#[tauri::command]
+pub async fn set_word_count_mode(mode: String) -> Result<(), String> {
+    if CountMode::parse(&mode).is_none() {
+        return Err(format!("未知的字数统计模式: {}", mode));
+    }
+    let conn = db::init_global_db()?;
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES ('word_count_mode', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = ?1",
+        params![mode],
+    )
+    .map_err(|e| format!("设置字数统计模式失败: {}", e))?;
+    Ok(())
+}
+
+/// 按当前模式重新统计本书全部章节的字数
+///
+/// 口径变化不是写作，不记入写作日志和每日统计。
+#[tauri::command]
+pub async fn recompute_word_counts(storage_path: String) -> Result<RecountResult, String> {
+    let mode = count_mode()?;
+    let cfg = config::load_config()?;
+    let mut conn = db::open_book_db(&cfg, &storage_path)?;
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+
+    let chapters = {
+        let mut stmt = tx
+            .prepare("SELECT id, content, word_count FROM chapters")
+            .map_err(|e| format!("查询章节失败: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?))
+            })
+            .map_err(|e| format!("读取章节失败: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("解析章节失败: {}", e))?;
+        rows
+    };
+
+    let mut result = RecountResult {
+        chapters: chapters.len(),
+        changed: 0,
+        total_words: 0,
+    };
+    for (id, content, old) in chapters {
+        let words = count::count(&content, mode);
+        result.total_words += words;
+        if words != old {
+            tx.execute("UPDATE chapters SET word_count = ?1 WHERE id = ?2", params![words, id])
+                .map_err(|e| format!("更新字数失败: {}", e))?;
+            result.changed += 1;
+        }
+    }
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+    Ok(result)
+}
+
+/// 当前字数统计模式（未设置或无法识别时为 all）
+///
+/// 只读取设置，调用方在一次命令内读取一次后传给需要统计字数的函数。
+pub(crate) fn count_mode() -> Result<CountMode, String> {
+    let conn = db::open_global_db()?;
+    let value: Option<String> = match conn.query_row(
+        "SELECT value FROM settings WHERE key = 'word_count_mode'",
+        [],
+        |row| row.get(0),
+    ) {
+        Ok(v) => Some(v),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(format!("读取字数统计模式失败: {}", e)),
+    };
+    Ok(value.as_deref().and_then(CountMode::parse).unwrap_or_default())
+}
+
+// ============================================================================
+// 写作日志
+// ============================================================================
+
+/// 记录一次章节字数净变化到 book.db 写作日志
+///
+/// 计入写作的来源同时累加到 global.db 的当日统计（合计与该书）并记入进行中的写作会话；
+/// 导入、恢复只记日志不计字数。返回本次保存后首次达到的目标（全局、该书）。
+pub(crate) fn log_writing(
+    conn: &rusqlite::Connection,
+    storage_path: &str,
+    chapter_id: &str,
+    delta: i64,
+    source: &str,
+) -> Result<Vec<GoalReached>, String> {
+    if delta == 0 {
+        return Ok(Vec::new());
+    }
+    let now = chrono::Local::now();
+    let date = now.format("%Y-%m-%d").to_string();
+    conn.execute(
+        "INSERT INTO writing_log (id, chapter_id, date, delta, source, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![uuid::Uuid::new_v4().to_string(), chapter_id, date, delta, source, now.to_rfc3339()],
+    )
+    .map_err(|e| format!("记录写作日志失败: {}", e))?;
+
+    if UNCOUNTED_SOURCES.contains(&source) {
+        return Ok(Vec::new());
+    }
+    let global = db::open_global_db()?;
+    let mut reached = Vec::new();
+    let goal = effective_goal(&global, None, &date)?;
+    let total: i64 = global
+        .query_row(
+            "INSERT INTO daily_stats (id, date, word_count, duration_seconds, daily_goal)
+             VALUES (?1, ?2, ?3, 0, ?4)
+             ON CONFLICT(date) DO UPDATE SET word_count = word_count + ?3, daily_goal = ?4
+             RETURNING word_count",
+            params![uuid::Uuid::new_v4().to_string(), date, delta, goal],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("更新统计失败: {}", e))?;
+    if crossed(goal, total, delta) {
+        reached.push(GoalReached {
+            book_id: None,
+            date: date.clone(),
+            goal,
+            word_count: total,
+        });
+    }
+
+    let book_id = book::book_id_for(&global, storage_path)?;
+    if let Some(book_id) = &book_id {
+        let goal = effective_goal(&global, Some(book_id), &date)?;
+        let total: i64 = global
+            .query_row(
+                "INSERT INTO book_daily_stats (book_id, date, word_count, duration_seconds, daily_goal)
+                 VALUES (?1, ?2, ?3, 0, ?4)
+                 ON CONFLICT(book_id, date) DO UPDATE SET word_count = word_count + ?3, daily_goal = ?4
+                 RETURNING word_count",
+                params![book_id, date, delta, goal],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("更新书籍统计失败: {}", e))?;
+        if crossed(goal, total, delta) {
+            reached.push(GoalReached {
+                book_id: Some(book_id.clone()),
+                date: date.clone(),
+                goal,
+                word_count: total,
+            });
+        }
+    }
+    add_hourly(&global, &now, delta, 0)?;
+    session::record_save(&global, book_id.as_deref(), chapter_id, delta)?;
+    Ok(reached)
+}
+
+/// 本次变化是否让字数从目标以下升到目标及以上
+fn crossed(goal: i64, total: i64, delta: i64) -> bool {
+    goal > 0 && total >= goal && total - delta < goal
+}
+
+/// 累加写作时长到某天的统计（合计、该书；当天的同时计入当前小时）
+pub(crate) fn add_duration(
+    global: &rusqlite::Connection,
+    book_id: Option<&str>,
+    date: &str,
+    seconds: i64,
+) -> Result<(), String> {
+    global
+        .execute(
+            "INSERT INTO daily_stats (id, date, word_count, duration_seconds, daily_goal)
+             VALUES (?1, ?2, 0, ?3, ?4)
+             ON CONFLICT(date) DO UPDATE SET duration_seconds = duration_seconds + ?3",
+            params![uuid::Uuid::new_v4().to_string(), date, seconds, effective_goal(global, None, date)?],
+        )
+        .map_err(|e| format!("更新统计失败: {}", e))?;
+
+    let now = chrono::Local::now();
+    if date == now.format("%Y-%m-%d").to_string() {
+        add_hourly(global, &now, 0, seconds)?;
+    }
+
+    if let Some(book_id) = book_id {
+        global
+            .execute(
+                "INSERT INTO book_daily_stats (book_id, date, word_count, duration_seconds, daily_goal)
+                 VALUES (?1, ?2, 0, ?3, ?4)
+                 ON CONFLICT(book_id, date) DO UPDATE SET duration_seconds = duration_seconds + ?3",
+                params![book_id, date, seconds, effective_goal(global, Some(book_id), date)?],
+            )
+            .map_err(|e| format!("更新书籍统计失败: {}", e))?;
+    }
     Ok(())
 }
+
+/// 累加当前小时的字数与时长
+///
+/// 时长按上报时刻归入当前小时，跨整点的一段写作会整体计入后一个小时。
+fn add_hourly(
+    global: &rusqlite::Connection,
+    now: &chrono::DateTime<chrono::Local>,
+    words: i64,
+    seconds: i64,
+) -> Result<(), String> {
+    global
+        .execute(
+            "INSERT INTO hourly_stats (date, hour, word_count, duration_seconds) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(date, hour) DO UPDATE SET
+                word_count = word_count + ?3, duration_seconds = duration_seconds + ?4",
+            params![now.format("%Y-%m-%d").to_string(), now.hour() as i64, words, seconds],
+        )
+        .map_err(|e| format!("更新按小时统计失败: {}", e))?;
+    Ok(())
+}
+
+/// 按日期汇总一本书计入写作的字数
+fn daily_written(conn: &rusqlite::Connection) -> Result<Vec<(String, i64)>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT date, SUM(delta) FROM writing_log WHERE source NOT IN ({}) GROUP BY date",
+            UNCOUNTED_SOURCES.iter().map(|s| format!("'{}'", s)).collect::<Vec<_>>().join(", ")
+        ))
+        .map_err(|e| format!("查询写作日志失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("读取写作日志失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析写作日志失败: {}", e))?;
+    Ok(rows)
+}
+
+/// 按日期和小时汇总一本书计入写作的字数（created_at 为本地时间）
+fn hourly_written(conn: &rusqlite::Connection) -> Result<Vec<(String, i64, i64)>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT date, CAST(substr(created_at, 12, 2) AS INTEGER) AS hour, SUM(delta)
+             FROM writing_log WHERE source NOT IN ({}) GROUP BY date, hour",
+            UNCOUNTED_SOURCES.iter().map(|s| format!("'{}'", s)).collect::<Vec<_>>().join(", ")
+        ))
+        .map_err(|e| format!("查询写作日志失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| format!("读取写作日志失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析写作日志失败: {}", e))?;
+    Ok(rows)
+}
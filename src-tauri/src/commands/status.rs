@@ -0,0 +1,377 @@
+//! 章节状态
+//!
+//! 内置状态及允许的流转：
+//! - draft 草稿 → revising / complete
+//! - revising 修改中 → draft / complete
+//! - complete 已完成 → revising / dirty / published
+//! - dirty 完成后又修改（摘要待更新）→ complete / revising / published
+//! - published 已发布 → revising / complete（撤回发布）
+//!
+//! 自定义状态视为写作中的阶段，可与 draft / revising / complete 及其它自定义状态互转，
+//! 发布前须先标记完成。编辑已完成的章节自动变为 dirty，重新生成摘要后自动变回 complete。
+//! 每次变更都记录到 `chapter_status_history`。
+
+use crate::db;
+use crate::db::config;
+use crate::db::models::{ChapterStatusChange, ChapterStatusDef};
+use rusqlite::params;
+
+/// 自定义状态 key 前缀
+const CUSTOM_PREFIX: &str = "custom:";
+
+/// 内置状态的流转
+const BUILTIN_TRANSITIONS: [(&str, &[&str]); 5] = [
+    ("draft", &["revising", "complete"]),
+    ("revising", &["draft", "complete"]),
+    ("complete", &["revising", "dirty", "published"]),
+    ("dirty", &["complete", "revising", "published"]),
+    ("published", &["revising", "complete"]),
+];
+
+/// 可与自定义状态互转的内置状态
+const CUSTOM_PEERS: [&str; 3] = ["draft", "revising", "complete"];
+
+/// 删除自定义状态后，使用该状态的章节改为此状态
+const FALLBACK_STATUS: &str = "draft";
+
+fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
+    let cfg = config::load_config()?;
+    db::open_book_db(&cfg, storage_path)
+}
+
+/// 获取全部章节状态（内置在前）
+#[tauri::command]
+pub async fn list_chapter_statuses(storage_path: String) -> Result<Vec<ChapterStatusDef>, String> {
+    let conn = open_book(&storage_path)?;
+    load_statuses(&conn)
+}
+
+/// 新建自定义状态
+#[tauri::command]
+pub async fn create_chapter_status(
+    storage_path: String,
+    label: String,
+    color: String,
+) -> Result<ChapterStatusDef, String> {
+    let label = validate_label(&label)?;
+    validate_color(&color)?;
+    let conn = open_book(&storage_path)?;
+    let sort_order: i64 = conn
+        .query_row("SELECT COALESCE(MAX(sort_order), -1) + 1 FROM chapter_statuses", [], |row| row.get(0))
+        .map_err(|e| format!("查询排序失败: {}", e))?;
+    let key = format!("{}{}", CUSTOM_PREFIX, &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    conn.execute(
+        "INSERT INTO chapter_statuses (key, label, color, builtin, sort_order, created_at)
+         VALUES (?1, ?2, ?3, 0, ?4, ?5)",
+        params![key, label, color, sort_order, chrono::Utc::now().to_rfc3339()],
+    )
+    .map_err(|e| format!("创建章节状态失败: {}", e))?;
+    Ok(ChapterStatusDef {
+        key,
+        label,
+        color,
+        builtin: false,
+        sort_order,
+    })
+}
+
+/// 修改状态的名称或颜色（内置状态也可修改）
+#[tauri::command]
+pub async fn update_chapter_status_def(
+    storage_path: String,
+    key: String,
+    label: Option<String>,
+    color: Option<String>,
+) -> Result<(), String> {
+    let conn = open_book(&storage_path)?;
+    ensure_defined(&conn, &key)?;
+    if let Some(label) = label {
+        conn.execute(
+            "UPDATE chapter_statuses SET label = ?1 WHERE key = ?2",
+            params![validate_label(&label)?, key],
+        )
+        .map_err(|e| format!("更新章节状态失败: {}", e))?;
+    }
+    if let Some(color) = color {
+        validate_color(&color)?;
+        conn.execute(
+            "UPDATE chapter_statuses SET color = ?1 WHERE key = ?2",
+            params![color, key],
+        )
+        .map_err(|e| format!("更新章节状态失败: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 删除自定义状态，使用该状态的章节改为草稿。返回受影响的章节数
+#[tauri::command]
+pub async fn delete_chapter_status(storage_path: String, key: String) -> Result<usize, String> {
+    if !key.starts_with(CUSTOM_PREFIX) {
+        return Err("内置状态不能删除".into());
+    }
+    let mut conn = open_book(&storage_path)?;
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+    let chapter_ids = {
+        let mut stmt = tx
+            .prepare("SELECT id FROM chapters WHERE status = ?1")
+            .map_err(|e| format!("查询章节失败: {}", e))?;
+        let ids = stmt
+            .query_map(params![key], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("读取章节失败: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("解析章节失败: {}", e))?;
+        ids
+    };
+    for id in &chapter_ids {
+        apply(&tx, id, Some(&key), FALLBACK_STATUS, "system")?;
+    }
+    tx.execute("DELETE FROM chapter_statuses WHERE key = ?1", params![key])
+        .map_err(|e| format!("删除章节状态失败: {}", e))?;
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+    Ok(chapter_ids.len())
+}
+
+/// 章节当前状态可以改为哪些状态
+#[tauri::command]
+pub async fn get_status_transitions(storage_path: String, chapter_id: String) -> Result<Vec<String>, String> {
+    let conn = open_book(&storage_path)?;
+    let from = current_status(&conn, &chapter_id)?;
+    Ok(load_statuses(&conn)?
+        .into_iter()
+        .map(|s| s.key)
+        .filter(|to| *to != from && allowed(&from, to))
+        .collect())
+}
+
+/// 获取单章的状态变更历史（按时间升序）
+#[tauri::command]
+pub async fn get_chapter_status_history(
+    storage_path: String,
+    chapter_id: String,
+) -> Result<Vec<ChapterStatusChange>, String> {
+    let conn = open_book(&storage_path)?;
+    query_changes(
+        &conn,
+        "h.chapter_id = ?1 ORDER BY h.created_at ASC",
+        params![chapter_id],
+    )
+}
+
+/// 获取日期范围内的状态变更（按时间倒序），`to_status` 过滤目标状态
+///
+/// 变更时间以 UTC 存储，按本地日期归入 `start_date` ~ `end_date`。
+/// 例如本周完成的章节：`to_status` 传 complete，日期传本周一至周日。
+#[tauri::command]
+pub async fn list_status_changes(
+    storage_path: String,
+    to_status: Option<String>,
+    start_date: String,
+    end_date: String,
+) -> Result<Vec<ChapterStatusChange>, String> {
+    let conn = open_book(&storage_path)?;
+    let mut changes = query_changes(
+        &conn,
+        "(?1 IS NULL OR h.to_status = ?1) ORDER BY h.created_at DESC",
+        params![to_status],
+    )?;
+    changes.retain(|c| local_date(&c.created_at).is_some_and(|d| d >= start_date && d <= end_date));
+    Ok(changes)
+}
+
+// ============================================================================
+// 状态流转
+// ============================================================================
+
+/// 按状态模型变更章节状态；状态未变时不做任何事，返回是否发生了变更
+pub(crate) fn transition(
+    conn: &rusqlite::Connection,
+    chapter_id: &str,
+    to: &str,
+    source: &str,
+) -> Result<bool, String> {
+    let from = current_status(conn, chapter_id)?;
+    if from == to {
+        return Ok(false);
+    }
+    ensure_defined(conn, to)?;
+    if !allowed(&from, to) {
+        return Err(format!(
+            "章节状态不能从「{}」改为「{}」",
+            label_of(conn, &from),
+            label_of(conn, to)
+        ));
+    }
+    apply(conn, chapter_id, Some(&from), to, source)?;
+    Ok(true)
+}
+
+/// 编辑正文后的自动流转：已完成的章节变为 dirty
+pub(crate) fn after_edit(conn: &rusqlite::Connection, chapter_id: &str, from: &str) -> Result<(), String> {
+    if from == "complete" {
+        apply(conn, chapter_id, Some(from), "dirty", "system")?;
+    }
+    Ok(())
+}
+
+/// 记录一次已经写入 chapters 的状态变更
+pub(crate) fn record(
+    conn: &rusqlite::Connection,
+    chapter_id: &str,
+    from: Option<&str>,
+    to: &str,
+    source: &str,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO chapter_status_history (id, chapter_id, from_status, to_status, source, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            chapter_id,
+            from,
+            to,
+            source,
+            chrono::Utc::now().to_rfc3339()
+        ],
+    )
+    .map_err(|e| format!("记录状态变更失败: {}", e))?;
+    Ok(())
+}
+
+/// 写入状态（发布时记录发布时间，离开已发布时清除）并记录历史，不校验流转
+fn apply(
+    conn: &rusqlite::Connection,
+    chapter_id: &str,
+    from: Option<&str>,
+    to: &str,
+    source: &str,
+) -> Result<(), String> {
+    conn.execute(
+        "UPDATE chapters SET status = ?1, updated_at = ?2,
+         published_at = CASE WHEN ?1 = 'published' THEN COALESCE(published_at, ?2) ELSE NULL END
+         WHERE id = ?3",
+        params![to, chrono::Utc::now().to_rfc3339(), chapter_id],
+    )
+    .map_err(|e| format!("更新章节状态失败: {}", e))?;
+    record(conn, chapter_id, from, to, source)
+}
+
+/// 是否允许从 `from` 改为 `to`；不在内置列表中的状态按自定义状态处理
+fn allowed(from: &str, to: &str) -> bool {
+    let builtin = |s: &str| BUILTIN_TRANSITIONS.iter().find(|(key, _)| *key == s).map(|(_, targets)| *targets);
+    match (builtin(from), builtin(to)) {
+        (Some(targets), Some(_)) => targets.contains(&to),
+        (Some(_), None) => CUSTOM_PEERS.contains(&from),
+        (None, Some(_)) => CUSTOM_PEERS.contains(&to),
+        (None, None) => true,
+    }
+}
+
+/// 计入存稿的状态：可直接流转到 published 的状态（complete / dirty）
+pub(crate) fn buffer_statuses(conn: &rusqlite::Connection) -> Result<Vec<String>, String> {
+    Ok(load_statuses(conn)?
+        .into_iter()
+        .map(|s| s.key)
+        .filter(|key| key != "published" && allowed(key, "published"))
+        .collect())
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+fn current_status(conn: &rusqlite::Connection, chapter_id: &str) -> Result<String, String> {
+    conn.query_row("SELECT status FROM chapters WHERE id = ?1", params![chapter_id], |row| row.get(0))
+        .map_err(|e| format!("获取章节失败: {}", e))
+}
+
+fn ensure_defined(conn: &rusqlite::Connection, key: &str) -> Result<(), String> {
+    let exists: i64 = conn
+        .query_row("SELECT COUNT(*) FROM chapter_statuses WHERE key = ?1", params![key], |row| row.get(0))
+        .map_err(|e| format!("查询章节状态失败: {}", e))?;
+    if exists == 0 {
+        return Err(format!("未知的章节状态: {}", key));
+    }
+    Ok(())
+}
+
+fn label_of(conn: &rusqlite::Connection, key: &str) -> String {
+    conn.query_row("SELECT label FROM chapter_statuses WHERE key = ?1", params![key], |row| row.get(0))
+        .unwrap_or_else(|_| key.to_string())
+}
+
+fn load_statuses(conn: &rusqlite::Connection) -> Result<Vec<ChapterStatusDef>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT key, label, color, builtin, sort_order FROM chapter_statuses
+             ORDER BY builtin DESC, sort_order ASC",
+        )
+        .map_err(|e| format!("查询章节状态失败: {}", e))?;
+    let statuses = stmt
+        .query_map([], |row| {
+            Ok(ChapterStatusDef {
+                key: row.get(0)?,
+                label: row.get(1)?,
+                color: row.get(2)?,
+                builtin: row.get(3)?,
+                sort_order: row.get(4)?,
+            })
+        })
+        .map_err(|e| format!("读取章节状态失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析章节状态失败: {}", e))?;
+    Ok(statuses)
+}
+
+/// RFC 3339 时间所在的本地日期（YYYY-MM-DD）
+fn local_date(timestamp: &str) -> Option<String> {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string())
+}
+
+fn query_changes<P: rusqlite::Params>(
+    conn: &rusqlite::Connection,
+    condition: &str,
+    params: P,
+) -> Result<Vec<ChapterStatusChange>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT h.id, h.chapter_id, c.name, h.from_status, h.to_status, h.source, h.created_at
+             FROM chapter_status_history h LEFT JOIN chapters c ON c.id = h.chapter_id
+             WHERE {}",
+            condition
+        ))
+        .map_err(|e| format!("查询状态历史失败: {}", e))?;
+    let changes = stmt
+        .query_map(params, |row| {
+            Ok(ChapterStatusChange {
+                id: row.get(0)?,
+                chapter_id: row.get(1)?,
+                chapter_name: row.get(2)?,
+                from_status: row.get(3)?,
+                to_status: row.get(4)?,
+                source: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })
+        .map_err(|e| format!("读取状态历史失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析状态历史失败: {}", e))?;
+    Ok(changes)
+}
+
+fn validate_label(label: &str) -> Result<String, String> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err("状态名称不能为空".into());
+    }
+    Ok(label.to_string())
+}
+
+fn validate_color(color: &str) -> Result<(), String> {
+    let hex = color.strip_prefix('#').unwrap_or_default();
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("颜色格式无效: {}", color));
+    }
+    Ok(())
+}
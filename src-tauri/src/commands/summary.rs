@@ -0,0 +1,379 @@
+use crate::commands::{ai, chapter, status, vector};
+use crate::db;
+use crate::db::config;
+use crate::llm::cancel::{self, CancelToken};
+use crate::llm::provider::{ChatProvider, CANCELLED};
+use crate::llm::summary;
+use rusqlite::params;
+use tauri::{AppHandle, Emitter};
+
+fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
+    let cfg = config::load_config()?;
+    db::open_book_db(&cfg, storage_path)
+}
+
+/// 单章摘要生成结果
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ChapterSummary {
+    pub chapter_id: String,
+    pub l2_summary: String,
+    pub l3_title: String,
+    /// 是否已从 dirty 恢复为 complete
+    pub marked_complete: bool,
+}
+
+/// 摘要队列进度事件（事件名 `summary-queue`）
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SummaryQueueEvent {
+    pub queue_id: String,
+    /// chapter_done / chapter_failed / finished / cancelled / error（队列无法启动）
+    pub kind: String,
+    pub chapter_id: Option<String>,
+    pub chapter_name: Option<String>,
+    /// 已处理章节数（含失败）
+    pub processed: usize,
+    pub total: usize,
+    pub error: Option<String>,
+}
+
+/// 摘要队列执行结果
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct SummaryQueueResult {
+    pub total: usize,
+    pub succeeded: usize,
+    /// (章节 ID, 错误信息)
+    pub failed: Vec<(String, String)>,
+    pub cancelled: bool,
+}
+
+/// 为章节生成 L2 摘要并写回
+#[tauri::command]
+pub async fn generate_chapter_summary(
+    app: AppHandle,
+    storage_path: String,
+    request_id: String,
+    chapter_id: String,
+) -> Result<String, String> {
+    let provider = ai::metered_provider(&app, Some(&storage_path), "summary")?;
+    let registration = cancel::register(&request_id);
+
+    let conn = open_book(&storage_path)?;
+    let (name, content, _) = load_chapter_text(&conn, &chapter_id)?;
+    let l2 = summary::generate_summary(&provider, &name, &content, &registration.token).await?;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE chapters SET l2_summary = ?1, updated_at = ?2 WHERE id = ?3",
+        params![l2, now, chapter_id],
+    )
+    .map_err(|e| format!("保存摘要失败: {}", e))?;
+    vector::refresh_chapter(&conn, &chapter_id)?;
+
+    Ok(l2)
+}
+
+/// 为章节生成 L3 标题并写回（已有 L2 摘要时据摘要生成，否则据正文）
+#[tauri::command]
+pub async fn generate_chapter_title(
+    app: AppHandle,
+    storage_path: String,
+    request_id: String,
+    chapter_id: String,
+) -> Result<String, String> {
+    let provider = ai::metered_provider(&app, Some(&storage_path), "summary")?;
+    let registration = cancel::register(&request_id);
+
+    let conn = open_book(&storage_path)?;
+    let (name, content, l2) = load_chapter_text(&conn, &chapter_id)?;
+    let source = l2.filter(|s| !s.trim().is_empty()).unwrap_or(content);
+    let l3 = summary::generate_title(&provider, &name, &source, &registration.token).await?;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE chapters SET l3_title = ?1, updated_at = ?2 WHERE id = ?3",
+        params![l3, now, chapter_id],
+    )
+    .map_err(|e| format!("保存标题失败: {}", e))?;
+
+    Ok(l3)
+}
+
+/// 后台重新生成全部 dirty 章节的摘要与标题
+///
+/// 立即返回队列 ID，进度通过 `summary-queue` 事件推送；可用 `cancel_ai_request`
+/// 传入队列 ID 取消。同一本书同时只允许一个队列。
+#[tauri::command]
+pub async fn regenerate_dirty_chapters(app: AppHandle, storage_path: String) -> Result<String, String> {
+    let provider = ai::metered_provider(&app, Some(&storage_path), "summary")?;
+    let queue_id = format!("summary-queue:{}", storage_path);
+    let registration = cancel::try_register(&queue_id).ok_or("摘要队列已在运行")?;
+
+    let id = queue_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut on_progress = |event: SummaryQueueEvent| {
+            let _ = app.emit("summary-queue", event);
+        };
+        if let Err(e) = run_dirty_queue(&provider, &storage_path, &id, &registration.token, &mut on_progress).await {
+            let _ = app.emit(
+                "summary-queue",
+                SummaryQueueEvent {
+                    queue_id: id.clone(),
+                    kind: "error".into(),
+                    chapter_id: None,
+                    chapter_name: None,
+                    processed: 0,
+                    total: 0,
+                    error: Some(e),
+                },
+            );
+        }
+        drop(registration);
+    });
+
+    Ok(queue_id)
+}
+
+/// 生成单章 L2 摘要 + L3 标题并写回
+///
+/// 章节在生成期间未被修改（正文不变）且仍为 dirty 时，标记为 complete。
+pub async fn summarize_chapter(
+    provider: &dyn ChatProvider,
+    storage_path: &str,
+    chapter_id: &str,
+    cancel: &CancelToken,
+) -> Result<ChapterSummary, String> {
+    let (name, content) = {
+        let conn = open_book(storage_path)?;
+        let (name, content, _) = load_chapter_text(&conn, chapter_id)?;
+        (name, content)
+    };
+    if content.trim().is_empty() {
+        return Err("章节正文为空".into());
+    }
+
+    let l2 = summary::generate_summary(provider, &name, &content, cancel).await?;
+    let l3 = summary::generate_title(provider, &name, &l2, cancel).await?;
+
+    let conn = open_book(storage_path)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE chapters SET l2_summary = ?1, l3_title = ?2, updated_at = ?3 WHERE id = ?4",
+        params![l2, l3, now, chapter_id],
+    )
+    .map_err(|e| format!("保存摘要失败: {}", e))?;
+    vector::refresh_chapter(&conn, chapter_id)?;
+    let marked = conn
+        .execute(
+            "UPDATE chapters SET status = 'complete' WHERE id = ?1 AND status = 'dirty' AND content = ?2",
+            params![chapter_id, content],
+        )
+        .map_err(|e| format!("更新章节状态失败: {}", e))?;
+    if marked > 0 {
+        status::record(&conn, chapter_id, Some("dirty"), "complete", "system")?;
+    }
+
+    Ok(ChapterSummary {
+        chapter_id: chapter_id.to_string(),
+        l2_summary: l2,
+        l3_title: l3,
+        marked_complete: marked > 0,
+    })
+}
+
+/// 按阅读顺序处理全部 dirty 章节；单章失败只记录，不中断队列
+pub async fn run_dirty_queue(
+    provider: &dyn ChatProvider,
+    storage_path: &str,
+    queue_id: &str,
+    cancel: &CancelToken,
+    on_progress: &mut (dyn FnMut(SummaryQueueEvent) + Send),
+) -> Result<SummaryQueueResult, String> {
+    let queue = {
+        let conn = open_book(storage_path)?;
+        dirty_chapters(&conn)?
+    };
+
+    let mut result = SummaryQueueResult {
+        total: queue.len(),
+        ..Default::default()
+    };
+    let event = |kind: &str, chapter: Option<&(String, String)>, processed: usize, error: Option<String>| SummaryQueueEvent {
+        queue_id: queue_id.to_string(),
+        kind: kind.to_string(),
+        chapter_id: chapter.map(|c| c.0.clone()),
+        chapter_name: chapter.map(|c| c.1.clone()),
+        processed,
+        total: queue.len(),
+        error,
+    };
+
+    for (i, chapter) in queue.iter().enumerate() {
+        if cancel.is_cancelled() {
+            result.cancelled = true;
+            break;
+        }
+        match summarize_chapter(provider, storage_path, &chapter.0, cancel).await {
+            Ok(_) => {
+                result.succeeded += 1;
+                on_progress(event("chapter_done", Some(chapter), i + 1, None));
+            }
+            Err(e) if e == CANCELLED => {
+                result.cancelled = true;
+                break;
+            }
+            Err(e) => {
+                result.failed.push((chapter.0.clone(), e.clone()));
+                on_progress(event("chapter_failed", Some(chapter), i + 1, Some(e)));
+            }
+        }
+    }
+
+    let processed = result.succeeded + result.failed.len();
+    let kind = if result.cancelled { "cancelled" } else { "finished" };
+    on_progress(event(kind, None, processed, None));
+
+    Ok(result)
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+/// (章节名, 正文, L2 摘要)
+fn load_chapter_text(conn: &rusqlite::Connection, chapter_id: &str) -> Result<(String, String, Option<String>), String> {
+    conn.query_row(
+        "SELECT name, content, l2_summary FROM chapters WHERE id = ?1",
+        params![chapter_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .map_err(|e| format!("获取章节失败: {}", e))
+}
+
+/// dirty 章节 (ID, 章节名)，按阅读顺序
+fn dirty_chapters(conn: &rusqlite::Connection) -> Result<Vec<(String, String)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name FROM chapters WHERE status = 'dirty'")
+        .map_err(|e| format!("查询待更新章节失败: {}", e))?;
+    let dirty = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("读取待更新章节失败: {}", e))?
+        .collect::<Result<std::collections::HashMap<String, String>, _>>()
+        .map_err(|e| format!("解析待更新章节失败: {}", e))?;
+
+    Ok(chapter::reading_order(conn)?
+        .into_iter()
+        .filter_map(|id| dirty.get(&id).map(|name| (id.clone(), name.clone())))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::volume;
+    use crate::db::TestBook;
+    use crate::llm::provider::fake::FakeProvider;
+    use crate::llm::provider::ChatRequest;
+
+    /// 创建若干 dirty 章节，返回按阅读顺序的章节 ID
+    async fn dirty_book(storage_path: &str, names: &[&str]) -> Vec<String> {
+        let volume = volume::create_volume(storage_path.into(), "第一卷".into()).await.unwrap();
+        let conn = open_book(storage_path).unwrap();
+        let mut ids = Vec::new();
+        for name in names {
+            let chapter = chapter::create_chapter(storage_path.into(), volume.id.clone(), name.to_string())
+                .await
+                .unwrap();
+            conn.execute(
+                "UPDATE chapters SET content = ?1, status = 'dirty' WHERE id = ?2",
+                params![format!("{}的正文。", name), chapter.id],
+            )
+            .unwrap();
+            ids.push(chapter.id);
+        }
+        ids
+    }
+
+    fn chapter_status(storage_path: &str, id: &str) -> String {
+        let conn = open_book(storage_path).unwrap();
+        conn.query_row("SELECT status FROM chapters WHERE id = ?1", [id], |row| row.get(0)).unwrap()
+    }
+
+    /// 标题请求回复"标题"，摘要请求回复"摘要。"；正文含 `fail_on` 的章节返回错误
+    fn canned(request: &ChatRequest, fail_on: &str) -> Result<String, String> {
+        if request.messages[1].content.contains(fail_on) {
+            return Err("模型出错".into());
+        }
+        Ok(if request.messages[0].content.contains("起标题") { "标题" } else { "摘要。" }.into())
+    }
+
+    #[tokio::test]
+    async fn queue_continues_after_failure() {
+        let book = TestBook::new();
+        let ids = dirty_book(&book, &["甲", "乙", "丙"]).await;
+        let provider = FakeProvider::new(|request| canned(request, "乙的正文"));
+
+        let mut events = Vec::new();
+        let mut on_progress = |e: SummaryQueueEvent| events.push(e);
+        let result = run_dirty_queue(&provider, &book, "q", &CancelToken::new(), &mut on_progress).await.unwrap();
+
+        assert_eq!((result.total, result.succeeded, result.cancelled), (3, 2, false));
+        assert_eq!(result.failed, vec![(ids[1].clone(), "模型出错".to_string())]);
+        let kinds: Vec<_> = events.iter().map(|e| (e.kind.as_str(), e.processed)).collect();
+        assert_eq!(kinds, vec![("chapter_done", 1), ("chapter_failed", 2), ("chapter_done", 3), ("finished", 3)]);
+        assert_eq!(events[1].chapter_id.as_deref(), Some(ids[1].as_str()));
+        assert_eq!(events[1].error.as_deref(), Some("模型出错"));
+
+        assert_eq!(chapter_status(&book, &ids[0]), "complete");
+        assert_eq!(chapter_status(&book, &ids[1]), "dirty");
+        assert_eq!(chapter_status(&book, &ids[2]), "complete");
+    }
+
+    #[tokio::test]
+    async fn edited_chapter_stays_dirty() {
+        let book = TestBook::new();
+        let ids = dirty_book(&book, &["甲", "乙"]).await;
+
+        // 生成摘要期间作者修改了「乙」的正文
+        let (path, edited) = (book.to_string(), ids[1].clone());
+        let provider = FakeProvider::new(move |request| {
+            if request.messages[1].content.contains("乙的正文") {
+                let conn = open_book(&path).unwrap();
+                conn.execute("UPDATE chapters SET content = '改过的正文。' WHERE id = ?1", [&edited])
+                    .unwrap();
+            }
+            canned(request, "\0")
+        });
+
+        let token = CancelToken::new();
+        let unchanged = summarize_chapter(&provider, &book, &ids[0], &token).await.unwrap();
+        assert!(unchanged.marked_complete);
+        assert_eq!(chapter_status(&book, &ids[0]), "complete");
+
+        let changed = summarize_chapter(&provider, &book, &ids[1], &token).await.unwrap();
+        assert!(!changed.marked_complete);
+        assert_eq!(changed.l2_summary, "摘要。");
+        assert_eq!(chapter_status(&book, &ids[1]), "dirty");
+    }
+
+    #[tokio::test]
+    async fn cancelled_queue_stops() {
+        let book = TestBook::new();
+        let ids = dirty_book(&book, &["甲", "乙"]).await;
+        let token = CancelToken::new();
+        let trigger = token.clone();
+        let provider = FakeProvider::new(move |request| {
+            trigger.cancel();
+            canned(request, "\0")
+        });
+
+        let mut events = Vec::new();
+        let mut on_progress = |e: SummaryQueueEvent| events.push(e);
+        let result = run_dirty_queue(&provider, &book, "q", &token, &mut on_progress).await.unwrap();
+
+        assert!(result.cancelled);
+        assert_eq!((result.succeeded, result.failed.len()), (0, 0));
+        let kinds: Vec<_> = events.iter().map(|e| e.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["cancelled"]);
+        assert!(ids.iter().all(|id| chapter_status(&book, id) == "dirty"));
+    }
+}
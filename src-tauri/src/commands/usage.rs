@@ -0,0 +1,171 @@
+use crate::db;
+use crate::db::models::{AiBudget, AiUsage};
+use crate::llm::usage::{self, GLOBAL_SCOPE};
+use rusqlite::params;
+
+/// 默认返回的调用记录条数
+const DEFAULT_LIMIT: i64 = 200;
+
+/// 按维度汇总的 AI 用量
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AiUsageSummary {
+    /// 日期 / 书籍 ID / 功能 / 模型；不属于某本书的调用按书籍汇总时为空串
+    pub key: String,
+    /// 显示名：按书籍汇总时为书名，其余同 key
+    pub label: String,
+    pub calls: i64,
+    /// 失败、取消、被预算拦截的调用数
+    pub failed: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+    pub avg_latency_ms: i64,
+}
+
+/// 获取 AI 调用记录（指定日期范围，按时间倒序）
+#[tauri::command]
+pub async fn list_ai_usage(
+    start_date: String,
+    end_date: String,
+    book_id: Option<String>,
+    feature: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<AiUsage>, String> {
+    let conn = db::init_global_db()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, date, book_id, feature, model, prompt_tokens, completion_tokens, estimated,
+                    latency_ms, status, error, created_at
+             FROM ai_usage
+             WHERE date >= ?1 AND date <= ?2 AND (?3 IS NULL OR book_id = ?3) AND (?4 IS NULL OR feature = ?4)
+             ORDER BY created_at DESC LIMIT ?5",
+        )
+        .map_err(|e| format!("查询 AI 调用记录失败: {}", e))?;
+    let records = stmt
+        .query_map(
+            params![start_date, end_date, book_id, feature, limit.unwrap_or(DEFAULT_LIMIT)],
+            |row| {
+                Ok(AiUsage {
+                    id: row.get(0)?,
+                    date: row.get(1)?,
+                    book_id: row.get(2)?,
+                    feature: row.get(3)?,
+                    model: row.get(4)?,
+                    prompt_tokens: row.get(5)?,
+                    completion_tokens: row.get(6)?,
+                    estimated: row.get(7)?,
+                    latency_ms: row.get(8)?,
+                    status: row.get(9)?,
+                    error: row.get(10)?,
+                    created_at: row.get(11)?,
+                })
+            },
+        )
+        .map_err(|e| format!("读取 AI 调用记录失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析 AI 调用记录失败: {}", e))?;
+    Ok(records)
+}
+
+/// 按日期 / 书籍 / 功能 / 模型汇总 AI 用量（`group_by` 为 day / book / feature / model）
+#[tauri::command]
+pub async fn get_ai_usage_summary(
+    start_date: String,
+    end_date: String,
+    group_by: String,
+) -> Result<Vec<AiUsageSummary>, String> {
+    let column = match group_by.as_str() {
+        "day" => "u.date",
+        "book" => "COALESCE(u.book_id, '')",
+        "feature" => "u.feature",
+        "model" => "u.model",
+        other => return Err(format!("未知的汇总维度: {}", other)),
+    };
+    let label = if group_by == "book" {
+        "COALESCE(MAX(b.name), '')"
+    } else {
+        column
+    };
+    let order = if group_by == "day" { "key ASC" } else { "total_tokens DESC" };
+
+    let conn = db::init_global_db()?;
+    let sql = format!(
+        "SELECT {column} AS key, {label},
+                COUNT(*), SUM(CASE WHEN u.status = 'ok' THEN 0 ELSE 1 END),
+                SUM(u.prompt_tokens), SUM(u.completion_tokens),
+                SUM(u.prompt_tokens + u.completion_tokens) AS total_tokens,
+                CAST(AVG(u.latency_ms) AS INTEGER)
+         FROM ai_usage u LEFT JOIN books b ON b.id = u.book_id
+         WHERE u.date >= ?1 AND u.date <= ?2
+         GROUP BY key ORDER BY {order}"
+    );
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| format!("查询 AI 用量失败: {}", e))?;
+    let summary = stmt
+        .query_map(params![start_date, end_date], |row| {
+            Ok(AiUsageSummary {
+                key: row.get(0)?,
+                label: row.get(1)?,
+                calls: row.get(2)?,
+                failed: row.get(3)?,
+                prompt_tokens: row.get(4)?,
+                completion_tokens: row.get(5)?,
+                total_tokens: row.get(6)?,
+                avg_latency_ms: row.get(7)?,
+            })
+        })
+        .map_err(|e| format!("读取 AI 用量失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析 AI 用量失败: {}", e))?;
+    Ok(summary)
+}
+
+/// 获取全部每月预算及本月已用量
+#[tauri::command]
+pub async fn list_ai_budgets() -> Result<Vec<AiBudget>, String> {
+    let conn = db::init_global_db()?;
+    usage::list_budgets(&conn)
+}
+
+/// 设置每月 token 预算（`scope` 为 global 或书籍 ID，`action` 为 warn / block）
+#[tauri::command]
+pub async fn set_ai_budget(scope: String, monthly_tokens: i64, action: String) -> Result<AiBudget, String> {
+    if !matches!(action.as_str(), "warn" | "block") {
+        return Err(format!("未知的超额处理方式: {}", action));
+    }
+    if monthly_tokens <= 0 {
+        return Err("预算必须大于 0".into());
+    }
+    let conn = db::init_global_db()?;
+    if scope != GLOBAL_SCOPE {
+        let exists: i64 = conn
+            .query_row("SELECT COUNT(*) FROM books WHERE id = ?1", params![scope], |row| row.get(0))
+            .map_err(|e| format!("查询书籍失败: {}", e))?;
+        if exists == 0 {
+            return Err("书籍不存在".into());
+        }
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO ai_budgets (scope, monthly_tokens, action, updated_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(scope) DO UPDATE SET monthly_tokens = ?2, action = ?3, updated_at = ?4",
+        params![scope, monthly_tokens, action, now],
+    )
+    .map_err(|e| format!("保存 AI 预算失败: {}", e))?;
+
+    usage::list_budgets(&conn)?
+        .into_iter()
+        .find(|b| b.scope == scope)
+        .ok_or_else(|| "保存 AI 预算失败".to_string())
+}
+
+/// 删除每月预算
+#[tauri::command]
+pub async fn delete_ai_budget(scope: String) -> Result<(), String> {
+    let conn = db::init_global_db()?;
+    conn.execute("DELETE FROM ai_budgets WHERE scope = ?1", params![scope])
+        .map_err(|e| format!("删除 AI 预算失败: {}", e))?;
+    Ok(())
+}
@@ -0,0 +1,434 @@
+use crate::commands::{ai, chapter, context as book_context};
+use crate::db;
+use crate::db::config;
+use crate::llm;
+use crate::llm::cancel::{self, CancelToken};
+use crate::llm::embedding::{self, EmbeddingProvider};
+use crate::llm::usage::UsageContext;
+use crate::text::chunk;
+use rusqlite::params;
+use std::collections::{HashMap, HashSet};
+use tauri::AppHandle;
+
+fn open_book(storage_path: &str) -> Result<rusqlite::Connection, String> {
+    let cfg = config::load_config()?;
+    db::open_book_db(&cfg, storage_path)
+}
+
+/// 分块目标字数
+const CHUNK_CHARS: usize = 300;
+/// 每次请求计算向量的分块数
+const EMBED_BATCH: usize = 32;
+/// 默认返回条数
+const DEFAULT_LIMIT: usize = 10;
+
+/// 语义检索命中的段落
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SemanticHit {
+    /// chapter / summary / entity
+    pub source_type: String,
+    pub source_id: String,
+    /// 章节正文与摘要为所在章节 ID，实体为 None
+    pub chapter_id: Option<String>,
+    /// 章节名或实体名
+    pub title: String,
+    pub content: String,
+    /// 段落在来源文本中的字符偏移
+    pub start_offset: i64,
+    pub end_offset: i64,
+    pub score: f32,
+}
+
+/// 向量索引状态
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct VectorIndexStatus {
+    pub model: String,
+    pub total_chunks: i64,
+    /// 已用当前模型计算过向量的分块数
+    pub embedded: i64,
+    pub pending: i64,
+}
+
+/// 语义检索（如"主角第一次听说玉佩是在哪里"），按相似度降序返回段落
+///
+/// 检索前先同步索引：补齐新增/修改的分块，切换过 embedding 模型时重新计算。
+/// `source_types` 为空时检索全部来源。
+#[tauri::command]
+pub async fn semantic_search(
+    app: AppHandle,
+    storage_path: String,
+    request_id: String,
+    query: String,
+    limit: Option<usize>,
+    source_types: Option<Vec<String>>,
+) -> Result<Vec<SemanticHit>, String> {
+    let embedder = llm::configured_embedder(ai::usage_context(&app, Some(&storage_path), "embedding")?)?;
+    let registration = cancel::register(&request_id);
+    search(
+        embedder.as_ref(),
+        &storage_path,
+        &query,
+        limit.unwrap_or(DEFAULT_LIMIT),
+        source_types.as_deref(),
+        &registration.token,
+    )
+    .await
+}
+
+/// 同步并补算向量索引（可用 `cancel_ai_request` 取消，已算好的部分保留）
+#[tauri::command]
+pub async fn update_vector_index(
+    app: AppHandle,
+    storage_path: String,
+    request_id: String,
+) -> Result<VectorIndexStatus, String> {
+    let embedder = llm::configured_embedder(ai::usage_context(&app, Some(&storage_path), "embedding")?)?;
+    let registration = cancel::register(&request_id);
+    sync_sources(&open_book(&storage_path)?)?;
+    embed_pending(embedder.as_ref(), &storage_path, &registration.token).await?;
+    index_status(&open_book(&storage_path)?, &embedder.model_id())
+}
+
+/// 获取向量索引状态（不修改索引）
+#[tauri::command]
+pub async fn get_vector_index_status(storage_path: String) -> Result<VectorIndexStatus, String> {
+    let embedder = llm::configured_embedder(UsageContext::default())?;
+    index_status(&open_book(&storage_path)?, &embedder.model_id())
+}
+
+/// 同步索引后检索（embedder 可替换为测试用实现）
+pub async fn search(
+    embedder: &dyn EmbeddingProvider,
+    storage_path: &str,
+    query: &str,
+    limit: usize,
+    source_types: Option<&[String]>,
+    cancel: &CancelToken,
+) -> Result<Vec<SemanticHit>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    sync_sources(&open_book(storage_path)?)?;
+    embed_pending(embedder, storage_path, cancel).await?;
+    let query_vector = embedder
+        .embed(&[query.to_string()], cancel)
+        .await?
+        .pop()
+        .ok_or("向量接口未返回结果")?;
+
+    let conn = open_book(storage_path)?;
+    let model = embedder.model_id();
+    let mut stmt = conn
+        .prepare(
+            "SELECT source_type, source_id, content, start_offset, end_offset, vector
+             FROM vector_chunks WHERE model = ?1 AND vector IS NOT NULL",
+        )
+        .map_err(|e| format!("查询向量索引失败: {}", e))?;
+    let rows = stmt
+        .query_map(params![model], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, Vec<u8>>(5)?,
+            ))
+        })
+        .map_err(|e| format!("读取向量索引失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析向量索引失败: {}", e))?;
+
+    let mut scored: Vec<(f32, String, String, String, i64, i64)> = rows
+        .into_iter()
+        .filter(|(source_type, ..)| source_types.is_none_or(|types| types.iter().any(|t| t == source_type)))
+        .map(|(source_type, source_id, content, start, end, blob)| {
+            let score = embedding::cosine(&query_vector, &embedding::from_blob(&blob));
+            (score, source_type, source_id, content, start, end)
+        })
+        .filter(|(score, ..)| *score > 0.0)
+        .collect();
+
+    // 同分时按阅读顺序靠前优先（"第一次"出现的位置）
+    let order: HashMap<String, usize> = chapter::reading_order(&conn)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, id)| (id, i))
+        .collect();
+    scored.sort_by(|a, b| {
+        b.0.total_cmp(&a.0)
+            .then_with(|| order.get(&a.2).unwrap_or(&usize::MAX).cmp(order.get(&b.2).unwrap_or(&usize::MAX)))
+            .then_with(|| a.4.cmp(&b.4))
+    });
+    scored.truncate(limit);
+
+    let titles = source_titles(&conn)?;
+    Ok(scored
+        .into_iter()
+        .map(|(score, source_type, source_id, content, start_offset, end_offset)| SemanticHit {
+            chapter_id: (source_type != "entity").then(|| source_id.clone()),
+            title: titles.get(&source_id).cloned().unwrap_or_default(),
+            source_type,
+            source_id,
+            content,
+            start_offset,
+            end_offset,
+            score,
+        })
+        .collect())
+}
+
+/// 为没有向量（或向量来自其他模型）的分块计算向量，返回本次计算的分块数
+pub async fn embed_pending(
+    embedder: &dyn EmbeddingProvider,
+    storage_path: &str,
+    cancel: &CancelToken,
+) -> Result<usize, String> {
+    let conn = open_book(storage_path)?;
+    let model = embedder.model_id();
+    let mut done = 0;
+    loop {
+        if cancel.is_cancelled() {
+            return Err(llm::provider::CANCELLED.into());
+        }
+        let batch: Vec<(String, String)> = {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, content FROM vector_chunks
+                     WHERE vector IS NULL OR model IS NOT ?1 LIMIT ?2",
+                )
+                .map_err(|e| format!("查询待索引分块失败: {}", e))?;
+            let rows = stmt
+                .query_map(params![model, EMBED_BATCH as i64], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| format!("读取待索引分块失败: {}", e))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("解析待索引分块失败: {}", e))?;
+            rows
+        };
+        if batch.is_empty() {
+            return Ok(done);
+        }
+
+        let texts: Vec<String> = batch.iter().map(|(_, content)| content.clone()).collect();
+        let vectors = embedder.embed(&texts, cancel).await?;
+        if vectors.len() != batch.len() {
+            return Err("向量接口返回的结果数量不完整".into());
+        }
+        let now = chrono::Utc::now().to_rfc3339();
+        for ((id, _), vector) in batch.iter().zip(vectors) {
+            conn.execute(
+                "UPDATE vector_chunks SET model = ?1, vector = ?2, updated_at = ?3 WHERE id = ?4",
+                params![model, embedding::to_blob(&vector), now, id],
+            )
+            .map_err(|e| format!("写入向量失败: {}", e))?;
+        }
+        done += batch.len();
+    }
+}
+
+/// 按所有来源的当前文本更新分块，删除来源已不存在的分块
+pub fn sync_sources(conn: &rusqlite::Connection) -> Result<(), String> {
+    let mut live: HashSet<(String, String)> = HashSet::new();
+
+    let mut stmt = conn
+        .prepare("SELECT id FROM chapters")
+        .map_err(|e| format!("查询章节失败: {}", e))?;
+    let chapter_ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("读取章节失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析章节失败: {}", e))?;
+    for id in chapter_ids {
+        refresh_chapter(conn, &id)?;
+        live.insert(("chapter".into(), id.clone()));
+        live.insert(("summary".into(), id));
+    }
+
+    for (id, text) in entity_texts(conn)? {
+        refresh_source(conn, "entity", &id, &text)?;
+        live.insert(("entity".into(), id));
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT source_type, source_id FROM vector_chunks")
+        .map_err(|e| format!("查询向量索引失败: {}", e))?;
+    let indexed = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("读取向量索引失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析向量索引失败: {}", e))?;
+    for (source_type, source_id) in indexed {
+        if !live.contains(&(source_type.clone(), source_id.clone())) {
+            remove_source(conn, &source_type, &source_id)?;
+        }
+    }
+    Ok(())
+}
+
+/// 章节正文或摘要变化后更新其分块（保存章节时调用）
+///
+/// 内容未变的分块保留已有向量，只有新增或修改的分块需要重新计算。
+pub(crate) fn refresh_chapter(conn: &rusqlite::Connection, chapter_id: &str) -> Result<(), String> {
+    let (content, l2_summary): (String, Option<String>) = conn
+        .query_row(
+            "SELECT content, l2_summary FROM chapters WHERE id = ?1",
+            params![chapter_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("获取章节失败: {}", e))?;
+    refresh_source(conn, "chapter", chapter_id, &content)?;
+    refresh_source(conn, "summary", chapter_id, l2_summary.as_deref().unwrap_or_default())
+}
+
+/// 删除某个来源的全部分块
+pub(crate) fn remove_source(conn: &rusqlite::Connection, source_type: &str, source_id: &str) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM vector_chunks WHERE source_type = ?1 AND source_id = ?2",
+        params![source_type, source_id],
+    )
+    .map_err(|e| format!("删除向量索引失败: {}", e))?;
+    Ok(())
+}
+
+// ============================================================================
+// 辅助函数
+// ============================================================================
+
+/// (chunk_index, start_offset, end_offset)
+type ChunkPosition = (i64, i64, i64);
+
+fn refresh_source(conn: &rusqlite::Connection, source_type: &str, source_id: &str, text: &str) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, content, chunk_index, start_offset, end_offset FROM vector_chunks
+             WHERE source_type = ?1 AND source_id = ?2",
+        )
+        .map_err(|e| format!("查询向量索引失败: {}", e))?;
+    let existing = stmt
+        .query_map(params![source_type, source_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                (row.get::<_, i64>(2)?, row.get::<_, i64>(3)?, row.get::<_, i64>(4)?),
+            ))
+        })
+        .map_err(|e| format!("读取向量索引失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析向量索引失败: {}", e))?;
+
+    // 内容 → 可复用的已有分块
+    let mut reusable: HashMap<String, Vec<(String, ChunkPosition)>> = HashMap::new();
+    for (id, content, position) in existing {
+        reusable.entry(content).or_default().push((id, position));
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    for (index, piece) in chunk::split(text, CHUNK_CHARS).into_iter().enumerate() {
+        let position = (index as i64, piece.start as i64, piece.end as i64);
+        match reusable.get_mut(&piece.text).and_then(|ids| ids.pop()) {
+            Some((_, old)) if old == position => {}
+            Some((id, _)) => {
+                conn.execute(
+                    "UPDATE vector_chunks SET chunk_index = ?1, start_offset = ?2, end_offset = ?3 WHERE id = ?4",
+                    params![position.0, position.1, position.2, id],
+                )
+                .map_err(|e| format!("更新向量索引失败: {}", e))?;
+            }
+            None => {
+                conn.execute(
+                    "INSERT INTO vector_chunks (id, source_type, source_id, chunk_index, start_offset, end_offset, content, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        uuid::Uuid::new_v4().to_string(),
+                        source_type,
+                        source_id,
+                        position.0,
+                        position.1,
+                        position.2,
+                        piece.text,
+                        now,
+                    ],
+                )
+                .map_err(|e| format!("写入向量索引失败: {}", e))?;
+            }
+        }
+    }
+
+    for (id, _) in reusable.into_values().flatten() {
+        conn.execute("DELETE FROM vector_chunks WHERE id = ?1", params![id])
+            .map_err(|e| format!("删除向量索引失败: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 实体设定的索引文本：名称、别名、展开后的属性（不含 Inbox 中的实体）
+fn entity_texts(conn: &rusqlite::Connection) -> Result<Vec<(String, String)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name, attributes_json FROM entities WHERE inbox = 0")
+        .map_err(|e| format!("查询实体失败: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })
+        .map_err(|e| format!("读取实体失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析实体失败: {}", e))?;
+
+    let mut stmt = conn
+        .prepare("SELECT entity_id, alias FROM entity_aliases")
+        .map_err(|e| format!("查询别名失败: {}", e))?;
+    let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+    for row in stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("读取别名失败: {}", e))?
+    {
+        let (entity_id, alias) = row.map_err(|e| format!("解析别名失败: {}", e))?;
+        aliases.entry(entity_id).or_default().push(alias);
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, name, attributes_json)| {
+            let mut lines = vec![name];
+            if let Some(aliases) = aliases.get(&id) {
+                lines.push(format!("别名：{}", aliases.join("、")));
+            }
+            let attributes = serde_json::from_str(&attributes_json).unwrap_or(serde_json::Value::Null);
+            let mut flat = Vec::new();
+            book_context::flatten_attributes("", &attributes, &mut flat);
+            lines.extend(flat.into_iter().map(|(key, value)| format!("{}：{}", key, value)));
+            (id, lines.join("\n"))
+        })
+        .collect())
+}
+
+/// 来源 ID → 显示标题（章节名 / 实体名）
+fn source_titles(conn: &rusqlite::Connection) -> Result<HashMap<String, String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name FROM chapters UNION ALL SELECT id, name FROM entities")
+        .map_err(|e| format!("查询标题失败: {}", e))?;
+    let titles = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("读取标题失败: {}", e))?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| format!("解析标题失败: {}", e))?;
+    Ok(titles)
+}
+
+fn index_status(conn: &rusqlite::Connection, model: &str) -> Result<VectorIndexStatus, String> {
+    let (total_chunks, embedded): (i64, i64) = conn
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(CASE WHEN vector IS NOT NULL AND model = ?1 THEN 1 ELSE 0 END), 0)
+             FROM vector_chunks",
+            params![model],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("统计向量索引失败: {}", e))?;
+    Ok(VectorIndexStatus {
+        model: model.to_string(),
+        total_chunks,
+        embedded,
+        pending: total_chunks - embedded,
+    })
+}
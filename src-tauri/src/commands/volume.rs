@@ -1,3 +1,4 @@
+use crate::commands::{arc, chapter};
 use crate::db;
 use crate::db::config;
 use crate::db::models::Volume;
@@ -31,6 +32,7 @@ pub async fn create_volume(storage_path: String, name: String) -> Result<Volume,
         id,
         name,
         sort_order: max_order + 1,
+        copied_from: None,
         created_at: now,
     })
 }
@@ -40,7 +42,7 @@ pub async fn create_volume(storage_path: String, name: String) -> Result<Volume,
 pub async fn list_volumes(storage_path: String) -> Result<Vec<Volume>, String> {
     let conn = open_book(&storage_path)?;
     let mut stmt = conn
-        .prepare("SELECT id, name, sort_order, created_at FROM volumes ORDER BY sort_order ASC")
+        .prepare("SELECT id, name, sort_order, copied_from, created_at FROM volumes ORDER BY sort_order ASC")
         .map_err(|e| format!("查询分卷失败: {}", e))?;
 
     let volumes = stmt
@@ -49,7 +51,8 @@ pub async fn list_volumes(storage_path: String) -> Result<Vec<Volume>, String> {
                 id: row.get(0)?,
                 name: row.get(1)?,
                 sort_order: row.get(2)?,
-                created_at: row.get(3)?,
+                copied_from: row.get(3)?,
+                created_at: row.get(4)?,
             })
         })
         .map_err(|e| format!("读取分卷失败: {}", e))?
@@ -72,6 +75,7 @@ pub async fn rename_volume(storage_path: String, id: String, name: String) -> Re
 #[tauri::command]
 pub async fn reorder_volumes(storage_path: String, ids: Vec<String>) -> Result<(), String> {
     let conn = open_book(&storage_path)?;
+    let before = chapter::reading_order(&conn)?;
     for (i, id) in ids.iter().enumerate() {
         conn.execute(
             "UPDATE volumes SET sort_order = ?1 WHERE id = ?2",
@@ -79,46 +83,113 @@ pub async fn reorder_volumes(storage_path: String, ids: Vec<String>) -> Result<(
         )
         .map_err(|e| format!("排序分卷失败: {}", e))?;
     }
+    arc::invalidate_reordered(&conn, &before)?;
     Ok(())
 }
 
-/// 删除分卷（将分卷及其下所有章节移入回收站）
+/// 复制分卷及其下所有章节（正文与摘要，不含快照），新分卷紧随原分卷
+///
+/// 新分卷名默认为「原分卷名（副本）」，章节保持原有顺序与名称，均记录复制来源。
+#[tauri::command]
+pub async fn duplicate_volume(storage_path: String, id: String, name: Option<String>) -> Result<Volume, String> {
+    let mut conn = open_book(&storage_path)?;
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let before = chapter::reading_order(&tx)?;
+
+    let (source_name, sort_order): (String, i64) = tx
+        .query_row(
+            "SELECT name, sort_order FROM volumes WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("获取分卷失败: {}", e))?;
+    tx.execute(
+        "UPDATE volumes SET sort_order = sort_order + 1 WHERE sort_order > ?1",
+        params![sort_order],
+    )
+    .map_err(|e| format!("排序分卷失败: {}", e))?;
+
+    let volume = Volume {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| format!("{}（副本）", source_name)),
+        sort_order: sort_order + 1,
+        copied_from: Some(id.clone()),
+        created_at: now,
+    };
+    tx.execute(
+        "INSERT INTO volumes (id, name, sort_order, copied_from, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![volume.id, volume.name, volume.sort_order, volume.copied_from, volume.created_at],
+    )
+    .map_err(|e| format!("创建分卷失败: {}", e))?;
+
+    let chapters: Vec<(String, String, i64)> = {
+        let mut stmt = tx
+            .prepare("SELECT id, name, sort_order FROM chapters WHERE volume_id = ?1 ORDER BY sort_order ASC")
+            .map_err(|e| format!("查询章节失败: {}", e))?;
+        let rows = stmt
+            .query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|e| format!("读取章节失败: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("解析章节失败: {}", e))?;
+        rows
+    };
+    for (chapter_id, chapter_name, chapter_order) in &chapters {
+        chapter::copy_chapter(&tx, chapter_id, &volume.id, chapter_name, *chapter_order)?;
+    }
+
+    arc::invalidate_reordered(&tx, &before)?;
+    tx.commit().map_err(|e| format!("提交复制失败: {}", e))?;
+    Ok(volume)
+}
+
+/// 删除分卷（将分卷及其下所有章节移入回收站，在一个事务内完成）
 #[tauri::command]
 pub async fn delete_volume(storage_path: String, id: String) -> Result<(), String> {
-    let conn = open_book(&storage_path)?;
+    let mut conn = open_book(&storage_path)?;
+    let tx = conn.transaction().map_err(|e| format!("开启事务失败: {}", e))?;
     let now = chrono::Utc::now().to_rfc3339();
 
     // 先把该卷下所有章节移入回收站
-    let mut stmt = conn
-        .prepare("SELECT id, volume_id, name, content, l2_summary, l3_title, status, word_count, sort_order, created_at, updated_at FROM chapters WHERE volume_id = ?1")
-        .map_err(|e| format!("查询章节失败: {}", e))?;
-
-    let chapters: Vec<(String, String)> = stmt
-        .query_map(params![id], |row| {
-            let ch_id: String = row.get(0)?;
-            // 序列化整行为 JSON
-            let data = serde_json::json!({
-                "id": ch_id,
-                "volume_id": row.get::<_, String>(1)?,
-                "name": row.get::<_, String>(2)?,
-                "content": row.get::<_, String>(3)?,
-                "l2_summary": row.get::<_, Option<String>>(4)?,
-                "l3_title": row.get::<_, Option<String>>(5)?,
-                "status": row.get::<_, String>(6)?,
-                "word_count": row.get::<_, i64>(7)?,
-                "sort_order": row.get::<_, i64>(8)?,
-                "created_at": row.get::<_, String>(9)?,
-                "updated_at": row.get::<_, String>(10)?,
-            });
-            Ok((ch_id, data.to_string()))
-        })
-        .map_err(|e| format!("读取章节失败: {}", e))?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("解析章节失败: {}", e))?;
+    let chapters: Vec<(String, String)> = {
+        let mut stmt = tx
+            .prepare("SELECT id, volume_id, name, content, l2_summary, l3_title, status, word_count, sort_order, created_at, updated_at, scheduled_at, published_at, copied_from FROM chapters WHERE volume_id = ?1")
+            .map_err(|e| format!("查询章节失败: {}", e))?;
+
+        let rows = stmt
+            .query_map(params![id], |row| {
+                let ch_id: String = row.get(0)?;
+                // 序列化整行为 JSON
+                let data = serde_json::json!({
+                    "id": ch_id,
+                    "volume_id": row.get::<_, String>(1)?,
+                    "name": row.get::<_, String>(2)?,
+                    "content": row.get::<_, String>(3)?,
+                    "l2_summary": row.get::<_, Option<String>>(4)?,
+                    "l3_title": row.get::<_, Option<String>>(5)?,
+                    "status": row.get::<_, String>(6)?,
+                    "word_count": row.get::<_, i64>(7)?,
+                    "sort_order": row.get::<_, i64>(8)?,
+                    "created_at": row.get::<_, String>(9)?,
+                    "updated_at": row.get::<_, String>(10)?,
+                    "scheduled_at": row.get::<_, Option<String>>(11)?,
+                    "published_at": row.get::<_, Option<String>>(12)?,
+                    "copied_from": row.get::<_, Option<String>>(13)?,
+                });
+                Ok((ch_id, data.to_string()))
+            })
+            .map_err(|e| format!("读取章节失败: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("解析章节失败: {}", e))?;
+        rows
+    };
 
     for (ch_id, data_json) in &chapters {
         let trash_id = uuid::Uuid::new_v4().to_string();
-        conn.execute(
+        tx.execute(
             "INSERT INTO trash (id, original_table, original_id, data_json, deleted_at, deleted_by) VALUES (?1, 'chapters', ?2, ?3, ?4, 'user')",
             params![trash_id, ch_id, data_json, now],
         )
@@ -126,27 +197,30 @@ pub async fn delete_volume(storage_path: String, id: String) -> Result<(), Strin
     }
 
     // 删除章节
-    conn.execute("DELETE FROM chapters WHERE volume_id = ?1", params![id])
+    for (ch_id, _) in &chapters {
+        arc::detach_chapter(&tx, ch_id)?;
+    }
+    tx.execute("DELETE FROM chapters WHERE volume_id = ?1", params![id])
         .map_err(|e| format!("删除章节失败: {}", e))?;
 
     // 将分卷本身移入回收站
-    let vol_data: String = conn
+    let vol_data: String = tx
         .query_row(
-            "SELECT json_object('id', id, 'name', name, 'sort_order', sort_order, 'created_at', created_at) FROM volumes WHERE id = ?1",
+            "SELECT json_object('id', id, 'name', name, 'sort_order', sort_order, 'copied_from', copied_from, 'created_at', created_at) FROM volumes WHERE id = ?1",
             params![id],
             |row| row.get(0),
         )
         .map_err(|e| format!("序列化分卷失败: {}", e))?;
 
     let trash_id = uuid::Uuid::new_v4().to_string();
-    conn.execute(
+    tx.execute(
         "INSERT INTO trash (id, original_table, original_id, data_json, deleted_at, deleted_by) VALUES (?1, 'volumes', ?2, ?3, ?4, 'user')",
         params![trash_id, id, vol_data, now],
     )
     .map_err(|e| format!("分卷移入回收站失败: {}", e))?;
 
-    conn.execute("DELETE FROM volumes WHERE id = ?1", params![id])
+    tx.execute("DELETE FROM volumes WHERE id = ?1", params![id])
         .map_err(|e| format!("删除分卷失败: {}", e))?;
 
-    Ok(())
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))
 }
@@ -1,16 +1,19 @@
 use rusqlite::Connection;
 
 /// 当前程序内置的 book.db schema 版本号
-const CURRENT_VERSION: u32 = 1;
+const CURRENT_VERSION: u32 = 14;
 
 /// 初始化 book.db：建表 + 执行迁移
 pub fn initialize(conn: &Connection) -> Result<(), String> {
-    let version = get_user_version(conn)?;
+    let mut version = get_user_version(conn)?;
 
+    // 新库先建 v1 表，再依次执行后续迁移
     if version == 0 {
         create_tables_v1(conn)?;
-        set_user_version(conn, CURRENT_VERSION)?;
-    } else if version < CURRENT_VERSION {
+        set_user_version(conn, 1)?;
+        version = 1;
+    }
+    if version < CURRENT_VERSION {
         migrate(conn, version)?;
     }
 
@@ -131,11 +134,61 @@ fn create_tables_v1(conn: &Connection) -> Result<(), String> {
 // ============================================================================
 
 fn migrate(conn: &Connection, from_version: u32) -> Result<(), String> {
-    let current = from_version;
+    let mut current = from_version;
     while current < CURRENT_VERSION {
         match current {
-            // 未来版本在此添加迁移：
-            // 1 => { migrate_v1_to_v2(conn)?; current = 2; }
+            1 => {
+                migrate_v1_to_v2(conn)?;
+                current = 2;
+            }
+            2 => {
+                migrate_v2_to_v3(conn)?;
+                current = 3;
+            }
+            3 => {
+                migrate_v3_to_v4(conn)?;
+                current = 4;
+            }
+            4 => {
+                migrate_v4_to_v5(conn)?;
+                current = 5;
+            }
+            5 => {
+                migrate_v5_to_v6(conn)?;
+                current = 6;
+            }
+            6 => {
+                migrate_v6_to_v7(conn)?;
+                current = 7;
+            }
+            7 => {
+                migrate_v7_to_v8(conn)?;
+                current = 8;
+            }
+            8 => {
+                migrate_v8_to_v9(conn)?;
+                current = 9;
+            }
+            9 => {
+                migrate_v9_to_v10(conn)?;
+                current = 10;
+            }
+            10 => {
+                migrate_v10_to_v11(conn)?;
+                current = 11;
+            }
+            11 => {
+                migrate_v11_to_v12(conn)?;
+                current = 12;
+            }
+            12 => {
+                migrate_v12_to_v13(conn)?;
+                current = 13;
+            }
+            13 => {
+                migrate_v13_to_v14(conn)?;
+                current = 14;
+            }
             _ => {
                 return Err(format!(
                     "book.db 版本 {} 无对应迁移脚本，目标版本 {}",
@@ -148,6 +201,263 @@ fn migrate(conn: &Connection, from_version: u32) -> Result<(), String> {
     Ok(())
 }
 
+/// v1 → v2: 实体别名表（合并实体时被合并方的名称转为别名）
+fn migrate_v1_to_v2(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS entity_aliases (
+            id          TEXT PRIMARY KEY,
+            entity_id   TEXT NOT NULL REFERENCES entities(id),
+            alias       TEXT NOT NULL,
+            created_at  TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_entity_aliases_entity ON entity_aliases(entity_id);
+        ",
+    )
+    .map_err(|e| format!("迁移 v1→v2 失败: {}", e))
+}
+
+/// v2 → v3: 连续性检查的忽略记录（忽略后的问题不再出现在报告中）
+fn migrate_v2_to_v3(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS continuity_dismissals (
+            finding_key     TEXT PRIMARY KEY,
+            note            TEXT,
+            dismissed_at    TEXT NOT NULL
+        );
+        ",
+    )
+    .map_err(|e| format!("迁移 v2→v3 失败: {}", e))
+}
+
+/// v3 → v4: 伏笔状态变更历史（不设外键，伏笔进回收站后历史仍保留）
+fn migrate_v3_to_v4(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS foreshadow_history (
+            id              TEXT PRIMARY KEY,
+            foreshadow_id   TEXT NOT NULL,
+            from_status     TEXT,
+            to_status       TEXT NOT NULL,
+            chapter_id      TEXT,
+            note            TEXT,
+            created_at      TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_foreshadow_history_foreshadow ON foreshadow_history(foreshadow_id);
+        ",
+    )
+    .map_err(|e| format!("迁移 v3→v4 失败: {}", e))
+}
+
+/// v4 → v5: 伏笔计划回收章节 / "N 章内回收"期限、优先级、标签
+fn migrate_v4_to_v5(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        ALTER TABLE foreshadows ADD COLUMN planned_reap_chapter_id TEXT REFERENCES chapters(id);
+        ALTER TABLE foreshadows ADD COLUMN deadline_chapters INTEGER;
+        ALTER TABLE foreshadows ADD COLUMN priority INTEGER NOT NULL DEFAULT 1;
+        ALTER TABLE foreshadows ADD COLUMN tags_json TEXT NOT NULL DEFAULT '[]';
+        ",
+    )
+    .map_err(|e| format!("迁移 v4→v5 失败: {}", e))
+}
+
+/// v5 → v6: 伏笔文本锚点（埋设/回收原文位置）与伏笔-实体关联
+fn migrate_v5_to_v6(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS foreshadow_anchors (
+            id              TEXT PRIMARY KEY,
+            foreshadow_id   TEXT NOT NULL,
+            role            TEXT NOT NULL,
+            chapter_id      TEXT NOT NULL,
+            quote           TEXT NOT NULL,
+            char_offset     INTEGER NOT NULL,
+            context_before  TEXT NOT NULL DEFAULT '',
+            context_after   TEXT NOT NULL DEFAULT '',
+            created_at      TEXT NOT NULL,
+            updated_at      TEXT NOT NULL,
+            UNIQUE(foreshadow_id, role)
+        );
+        CREATE INDEX IF NOT EXISTS idx_foreshadow_anchors_chapter ON foreshadow_anchors(chapter_id);
+
+        CREATE TABLE IF NOT EXISTS foreshadow_entities (
+            foreshadow_id   TEXT NOT NULL,
+            entity_id       TEXT NOT NULL REFERENCES entities(id),
+            created_at      TEXT NOT NULL,
+            PRIMARY KEY (foreshadow_id, entity_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_foreshadow_entities_entity ON foreshadow_entities(entity_id);
+        ",
+    )
+    .map_err(|e| format!("迁移 v5→v6 失败: {}", e))
+}
+
+/// v6 → v7: 剧情弧失效标记（范围内章节被编辑、重排或删除后需重新生成概要）
+fn migrate_v6_to_v7(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        ALTER TABLE rag_arcs ADD COLUMN stale INTEGER NOT NULL DEFAULT 0;
+        ",
+    )
+    .map_err(|e| format!("迁移 v6→v7 失败: {}", e))
+}
+
+/// v7 → v8: 快照来源标记（用户编辑 / 采纳 AI 输出）与本书的 AI 提示词模板
+fn migrate_v7_to_v8(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        ALTER TABLE snapshots ADD COLUMN source TEXT NOT NULL DEFAULT 'user';
+
+        CREATE TABLE IF NOT EXISTS prompt_templates (
+            kind        TEXT PRIMARY KEY,
+            template    TEXT NOT NULL,
+            updated_at  TEXT NOT NULL
+        );
+        ",
+    )
+    .map_err(|e| format!("迁移 v7→v8 失败: {}", e))
+}
+
+/// v8 → v9: AI 抽取的待确认变更队列；实体与时间线记录创建者（user / ai）
+fn migrate_v8_to_v9(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS ai_proposals (
+            id              TEXT PRIMARY KEY,
+            chapter_id      TEXT NOT NULL,
+            kind            TEXT NOT NULL,
+            entity_id       TEXT NOT NULL,
+            payload_json    TEXT NOT NULL,
+            status          TEXT NOT NULL DEFAULT 'pending',
+            created_at      TEXT NOT NULL,
+            resolved_at     TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_ai_proposals_status ON ai_proposals(status);
+
+        ALTER TABLE entities ADD COLUMN created_by TEXT NOT NULL DEFAULT 'user';
+        ALTER TABLE timeline ADD COLUMN created_by TEXT NOT NULL DEFAULT 'user';
+        ",
+    )
+    .map_err(|e| format!("迁移 v8→v9 失败: {}", e))
+}
+
+/// v9 → v10: 语义检索的分块向量索引（章节正文、L2 摘要、实体设定）
+///
+/// `vector` 为 f32 小端序列，`model` 记录生成向量的 embedding 模型；
+/// 正文变化后新分块的 `vector` 为 NULL，等待下次检索时补算。
+fn migrate_v9_to_v10(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS vector_chunks (
+            id              TEXT PRIMARY KEY,
+            source_type     TEXT NOT NULL,
+            source_id       TEXT NOT NULL,
+            chunk_index     INTEGER NOT NULL,
+            start_offset    INTEGER NOT NULL,
+            end_offset      INTEGER NOT NULL,
+            content         TEXT NOT NULL,
+            model           TEXT,
+            vector          BLOB,
+            updated_at      TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_vector_chunks_source ON vector_chunks(source_type, source_id);
+        ",
+    )
+    .map_err(|e| format!("迁移 v9→v10 失败: {}", e))
+}
+
+/// v10 → v11: 写作日志，每次保存记录章节字数的净变化
+///
+/// `source` 为 user / ai / replace / import / restore 等，`date` 为本地日期。
+/// 不加外键：章节删除后日志仍需保留用于统计。
+fn migrate_v10_to_v11(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS writing_log (
+            id          TEXT PRIMARY KEY,
+            chapter_id  TEXT NOT NULL,
+            date        TEXT NOT NULL,
+            delta       INTEGER NOT NULL,
+            source      TEXT NOT NULL,
+            created_at  TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_writing_log_date ON writing_log(date);
+        ",
+    )
+    .map_err(|e| format!("迁移 v10→v11 失败: {}", e))
+}
+
+/// v11 → v12: 连载发布计划
+///
+/// 章节增加计划发布时间（本地时间 `YYYY-MM-DDTHH:MM`）与实际发布时间，状态增加 published；
+/// `publish_plan` 只有一行，记录每天的发布时间点与存稿预警阈值。
+fn migrate_v11_to_v12(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        ALTER TABLE chapters ADD COLUMN scheduled_at TEXT;
+        ALTER TABLE chapters ADD COLUMN published_at TEXT;
+        CREATE INDEX IF NOT EXISTS idx_chapters_scheduled ON chapters(scheduled_at);
+
+        CREATE TABLE IF NOT EXISTS publish_plan (
+            id                  INTEGER PRIMARY KEY CHECK (id = 1),
+            release_times_json  TEXT NOT NULL DEFAULT '[]',
+            buffer_threshold    INTEGER NOT NULL DEFAULT 0,
+            updated_at          TEXT NOT NULL
+        );
+        ",
+    )
+    .map_err(|e| format!("迁移 v11→v12 失败: {}", e))
+}
+
+/// v12 → v13: 章节状态定义（内置 + 自定义，带颜色）与状态变更历史
+///
+/// 历史不加外键：章节删除后仍保留用于统计。
+fn migrate_v12_to_v13(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS chapter_statuses (
+            key         TEXT PRIMARY KEY,
+            label       TEXT NOT NULL,
+            color       TEXT NOT NULL,
+            builtin     INTEGER NOT NULL DEFAULT 0,
+            sort_order  INTEGER NOT NULL DEFAULT 0,
+            created_at  TEXT NOT NULL
+        );
+        INSERT OR IGNORE INTO chapter_statuses (key, label, color, builtin, sort_order, created_at) VALUES
+            ('draft', '草稿', '#9ca3af', 1, 0, strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            ('revising', '修改中', '#f59e0b', 1, 1, strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            ('complete', '已完成', '#10b981', 1, 2, strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            ('dirty', '待更新摘要', '#f97316', 1, 3, strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+            ('published', '已发布', '#3b82f6', 1, 4, strftime('%Y-%m-%dT%H:%M:%SZ', 'now'));
+
+        CREATE TABLE IF NOT EXISTS chapter_status_history (
+            id          TEXT PRIMARY KEY,
+            chapter_id  TEXT NOT NULL,
+            from_status TEXT,
+            to_status   TEXT NOT NULL,
+            source      TEXT NOT NULL,
+            created_at  TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_status_history_chapter ON chapter_status_history(chapter_id);
+        CREATE INDEX IF NOT EXISTS idx_status_history_to ON chapter_status_history(to_status, created_at);
+        ",
+    )
+    .map_err(|e| format!("迁移 v12→v13 失败: {}", e))
+}
+
+/// v13 → v14: 复制章节/分卷时记录来源（不设外键，来源删除后仍保留）
+fn migrate_v13_to_v14(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        ALTER TABLE chapters ADD COLUMN copied_from TEXT;
+        ALTER TABLE volumes ADD COLUMN copied_from TEXT;
+        ",
+    )
+    .map_err(|e| format!("迁移 v13→v14 失败: {}", e))
+}
+
 // ============================================================================
 // PRAGMA user_version 辅助
 // ============================================================================
@@ -1,16 +1,19 @@
 use rusqlite::Connection;
 
 /// 当前程序内置的 global.db schema 版本号
-const CURRENT_VERSION: u32 = 2;
+const CURRENT_VERSION: u32 = 11;
 
 /// 初始化 global.db：建表 + 执行迁移
 pub fn initialize(conn: &Connection) -> Result<(), String> {
-    let version = get_user_version(conn)?;
+    let mut version = get_user_version(conn)?;
 
+    // 新库的 v1 建表语句已包含 v2 的 deleted_at 列，之后依次执行后续迁移
     if version == 0 {
         create_tables_v1(conn)?;
-        set_user_version(conn, CURRENT_VERSION)?;
-    } else if version < CURRENT_VERSION {
+        set_user_version(conn, 2)?;
+        version = 2;
+    }
+    if version < CURRENT_VERSION {
         migrate(conn, version)?;
     }
 
@@ -90,6 +93,42 @@ fn migrate(conn: &Connection, from_version: u32) -> Result<(), String> {
                 migrate_v1_to_v2(conn)?;
                 current = 2;
             }
+            2 => {
+                migrate_v2_to_v3(conn)?;
+                current = 3;
+            }
+            3 => {
+                migrate_v3_to_v4(conn)?;
+                current = 4;
+            }
+            4 => {
+                migrate_v4_to_v5(conn)?;
+                current = 5;
+            }
+            5 => {
+                migrate_v5_to_v6(conn)?;
+                current = 6;
+            }
+            6 => {
+                migrate_v6_to_v7(conn)?;
+                current = 7;
+            }
+            7 => {
+                migrate_v7_to_v8(conn)?;
+                current = 8;
+            }
+            8 => {
+                migrate_v8_to_v9(conn)?;
+                current = 9;
+            }
+            9 => {
+                migrate_v9_to_v10(conn)?;
+                current = 10;
+            }
+            10 => {
+                migrate_v10_to_v11(conn)?;
+                current = 11;
+            }
             _ => {
                 return Err(format!(
                     "global.db 版本 {} 无对应迁移脚本，目标版本 {}",
@@ -108,6 +147,160 @@ fn migrate_v1_to_v2(conn: &Connection) -> Result<(), String> {
         .map_err(|e| format!("迁移 v1→v2 失败: {}", e))
 }
 
+/// v2 → v3: AI 服务默认设置（OpenAI 兼容接口，默认指向本机 Ollama）
+fn migrate_v2_to_v3(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        INSERT OR IGNORE INTO settings (key, value) VALUES ('ai_base_url', 'http://127.0.0.1:11434/v1');
+        INSERT OR IGNORE INTO settings (key, value) VALUES ('ai_model', '');
+        INSERT OR IGNORE INTO settings (key, value) VALUES ('ai_api_key', '');
+        INSERT OR IGNORE INTO settings (key, value) VALUES ('ai_timeout_secs', '120');
+        INSERT OR IGNORE INTO settings (key, value) VALUES ('ai_max_retries', '2');
+        ",
+    )
+    .map_err(|e| format!("迁移 v2→v3 失败: {}", e))
+}
+
+/// v3 → v4: 续写上下文设置（token 预算、原文章数、CJK 估算系数）
+fn migrate_v3_to_v4(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        INSERT OR IGNORE INTO settings (key, value) VALUES ('ai_context_budget', '8000');
+        INSERT OR IGNORE INTO settings (key, value) VALUES ('ai_context_recent_chapters', '3');
+        INSERT OR IGNORE INTO settings (key, value) VALUES ('ai_tokens_per_cjk', '1.0');
+        ",
+    )
+    .map_err(|e| format!("迁移 v3→v4 失败: {}", e))
+}
+
+/// v4 → v5: 向量检索的 embedding 模型（留空使用本地 n-gram 哈希向量）
+fn migrate_v4_to_v5(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch("INSERT OR IGNORE INTO settings (key, value) VALUES ('ai_embedding_model', '');")
+        .map_err(|e| format!("迁移 v4→v5 失败: {}", e))
+}
+
+/// v5 → v6: AI 调用记录与每月 token 预算
+///
+/// `ai_budgets.scope` 为 global（所有书合计）或书籍 ID；`action` 为 warn / block。
+fn migrate_v5_to_v6(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS ai_usage (
+            id                  TEXT PRIMARY KEY,
+            date                TEXT NOT NULL,
+            book_id             TEXT,
+            feature             TEXT NOT NULL,
+            model               TEXT NOT NULL DEFAULT '',
+            prompt_tokens       INTEGER NOT NULL DEFAULT 0,
+            completion_tokens   INTEGER NOT NULL DEFAULT 0,
+            estimated           INTEGER NOT NULL DEFAULT 0,
+            latency_ms          INTEGER NOT NULL DEFAULT 0,
+            status              TEXT NOT NULL,
+            error               TEXT,
+            created_at          TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_ai_usage_date ON ai_usage(date);
+        CREATE INDEX IF NOT EXISTS idx_ai_usage_book ON ai_usage(book_id);
+
+        CREATE TABLE IF NOT EXISTS ai_budgets (
+            scope           TEXT PRIMARY KEY,
+            monthly_tokens  INTEGER NOT NULL,
+            action          TEXT NOT NULL DEFAULT 'warn',
+            updated_at      TEXT NOT NULL
+        );
+        ",
+    )
+    .map_err(|e| format!("迁移 v5→v6 失败: {}", e))
+}
+
+/// v6 → v7: 字数统计模式（默认 all，与旧版一致）
+fn migrate_v6_to_v7(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch("INSERT OR IGNORE INTO settings (key, value) VALUES ('word_count_mode', 'all');")
+        .map_err(|e| format!("迁移 v6→v7 失败: {}", e))
+}
+
+/// v7 → v8: 每本书的每日写作统计（daily_stats 为所有书合计）
+fn migrate_v7_to_v8(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS book_daily_stats (
+            book_id             TEXT NOT NULL,
+            date                TEXT NOT NULL,
+            word_count          INTEGER NOT NULL DEFAULT 0,
+            duration_seconds    INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (book_id, date)
+        );
+        CREATE INDEX IF NOT EXISTS idx_book_daily_stats_date ON book_daily_stats(date);
+        ",
+    )
+    .map_err(|e| format!("迁移 v7→v8 失败: {}", e))
+}
+
+/// v8 → v9: 按小时汇总的写作统计（本地时间），用于分析一天中的写作时段
+fn migrate_v8_to_v9(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS hourly_stats (
+            date                TEXT NOT NULL,
+            hour                INTEGER NOT NULL,
+            word_count          INTEGER NOT NULL DEFAULT 0,
+            duration_seconds    INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (date, hour)
+        );
+        ",
+    )
+    .map_err(|e| format!("迁移 v8→v9 失败: {}", e))
+}
+
+/// v9 → v10: 写作会话（后端计时，按保存活动判断空闲）
+fn migrate_v9_to_v10(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS writing_sessions (
+            id                      TEXT PRIMARY KEY,
+            book_id                 TEXT,
+            chapter_id              TEXT,
+            started_at              TEXT NOT NULL,
+            ended_at                TEXT,
+            last_activity_at        TEXT NOT NULL,
+            duration_seconds        INTEGER NOT NULL DEFAULT 0,
+            words_added             INTEGER NOT NULL DEFAULT 0,
+            words_deleted           INTEGER NOT NULL DEFAULT 0,
+            end_reason              TEXT,
+            pomodoro_work_minutes   INTEGER NOT NULL DEFAULT 0,
+            pomodoro_break_minutes  INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX IF NOT EXISTS idx_writing_sessions_started ON writing_sessions(started_at);
+        INSERT OR IGNORE INTO settings (key, value) VALUES ('session_idle_minutes', '5');
+        ",
+    )
+    .map_err(|e| format!("迁移 v9→v10 失败: {}", e))
+}
+
+/// v10 → v11: 每本书及按星期的目标字数；每本书的每日统计记录当天目标
+///
+/// 全局默认目标仍存在 settings 的 daily_goal。weekday 0 表示每天，1-7 为周一至周日。
+/// 此前的统计没有记录目标，按迁移时的全局目标补齐。
+fn migrate_v10_to_v11(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS daily_goals (
+            scope       TEXT NOT NULL,
+            weekday     INTEGER NOT NULL DEFAULT 0,
+            goal        INTEGER NOT NULL,
+            updated_at  TEXT NOT NULL,
+            PRIMARY KEY (scope, weekday)
+        );
+        ALTER TABLE book_daily_stats ADD COLUMN daily_goal INTEGER NOT NULL DEFAULT 0;
+        UPDATE daily_stats SET daily_goal =
+            COALESCE((SELECT CAST(value AS INTEGER) FROM settings WHERE key = 'daily_goal'), 0);
+        UPDATE book_daily_stats SET daily_goal =
+            COALESCE((SELECT CAST(value AS INTEGER) FROM settings WHERE key = 'daily_goal'), 0);
+        ",
+    )
+    .map_err(|e| format!("迁移 v10→v11 失败: {}", e))
+}
+
 // ============================================================================
 // PRAGMA user_version 辅助
 // ============================================================================
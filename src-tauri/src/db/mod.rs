@@ -24,6 +24,14 @@ pub fn init_global_db() -> Result<Connection, String> {
     Ok(conn)
 }
 
+/// 打开 global.db（不保存配置、不执行迁移，供频繁调用的后台路径使用；启动时已由 init_global_db 初始化）
+pub fn open_global_db() -> Result<Connection, String> {
+    let cfg = config::load_config()?;
+    let db_path = config::global_db_path(&cfg);
+    Connection::open(&db_path)
+        .map_err(|e| format!("打开 global.db 失败 ({}): {}", db_path.display(), e))
+}
+
 /// 打开某本书的 book.db（如果不存在则创建并初始化）
 pub fn open_book_db(cfg: &AppConfig, storage_path: &str) -> Result<Connection, String> {
     let db_path = config::book_db_path(cfg, storage_path);
@@ -40,3 +48,31 @@ pub fn open_book_db(cfg: &AppConfig, storage_path: &str) -> Result<Connection, S
     book::initialize(&conn)?;
     Ok(conn)
 }
+
+/// 测试用的临时书籍目录（storage_path 为绝对路径时 book.db 直接建在该目录），离开作用域时删除
+#[cfg(test)]
+pub(crate) struct TestBook(String);
+
+#[cfg(test)]
+impl TestBook {
+    pub(crate) fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("xinzuo-test-{}", uuid::Uuid::new_v4()));
+        Self(dir.to_string_lossy().to_string())
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestBook {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestBook {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
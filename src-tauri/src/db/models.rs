@@ -34,9 +34,91 @@ pub struct DailyStat {
     pub date: String,
     pub word_count: i64,
     pub duration_seconds: i64,
+    /// 当天适用的全局目标字数（记录当时的目标，之后修改目标不影响历史）
     pub daily_goal: i64,
 }
 
+/// 单本书的每日写作统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookDailyStat {
+    pub book_id: String,
+    /// 日期格式：YYYY-MM-DD
+    pub date: String,
+    pub word_count: i64,
+    pub duration_seconds: i64,
+    /// 当天适用的目标字数（书籍目标优先于全局目标）
+    pub daily_goal: i64,
+}
+
+/// 每日目标规则（全局默认目标存在 settings 的 daily_goal，不在此列）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyGoalRule {
+    /// global 或书籍 ID
+    pub scope: String,
+    /// 0 为每天，1-7 为周一至周日
+    pub weekday: i64,
+    pub goal: i64,
+    pub updated_at: String,
+}
+
+/// 写作会话
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WritingSession {
+    pub id: String,
+    pub book_id: Option<String>,
+    /// 最近保存的章节
+    pub chapter_id: Option<String>,
+    pub started_at: String,
+    /// 进行中为 None
+    pub ended_at: Option<String>,
+    /// 最近一次保存（或开始）的时间，用于判断空闲
+    pub last_activity_at: String,
+    /// 有效写作时长（不含空闲）
+    pub duration_seconds: i64,
+    pub words_added: i64,
+    pub words_deleted: i64,
+    /// manual / idle
+    pub end_reason: Option<String>,
+    /// 番茄钟工作 / 休息分钟数，0 为未开启
+    pub pomodoro_work_minutes: i64,
+    pub pomodoro_break_minutes: i64,
+}
+
+/// 一次 AI 接口调用的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiUsage {
+    pub id: String,
+    /// 日期格式：YYYY-MM-DD（本地时间）
+    pub date: String,
+    /// 不属于某本书的调用（如设置页的测试对话）为 None
+    pub book_id: Option<String>,
+    /// chat / summary / arc_summary / continuation / rewrite / extraction / embedding
+    pub feature: String,
+    pub model: String,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    /// 服务端未返回用量、token 数为本地估算
+    pub estimated: bool,
+    pub latency_ms: i64,
+    /// ok / error / cancelled / blocked
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: String,
+}
+
+/// 每月 token 预算
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiBudget {
+    /// global（所有书合计）或书籍 ID
+    pub scope: String,
+    pub monthly_tokens: i64,
+    /// 超出后的处理：warn 仅提醒 / block 拒绝新的调用
+    pub action: String,
+    /// 本月已用 token
+    pub used_tokens: i64,
+    pub exceeded: bool,
+}
+
 /// 跨书实体暂存架（从一本书复制角色/道具到另一本书的中转站）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EntityShelfItem {
@@ -61,6 +143,8 @@ pub struct Volume {
     pub id: String,
     pub name: String,
     pub sort_order: i64,
+    /// 复制来源分卷 ID
+    pub copied_from: Option<String>,
     pub created_at: String,
 }
 
@@ -76,10 +160,16 @@ pub struct Chapter {
     pub l2_summary: Option<String>,
     /// L3 章节标题（AI 生成，≤20字）
     pub l3_title: Option<String>,
-    /// draft / complete / dirty
+    /// draft / revising / complete / dirty / published 或自定义状态（见 `ChapterStatusDef`）
     pub status: String,
     pub word_count: i64,
     pub sort_order: i64,
+    /// 计划发布时间（本地时间 YYYY-MM-DDTHH:MM）
+    pub scheduled_at: Option<String>,
+    /// 实际发布时间
+    pub published_at: Option<String>,
+    /// 复制来源章节 ID
+    pub copied_from: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -116,6 +206,15 @@ pub struct TimelineNode {
     pub created_at: String,
 }
 
+/// 实体别名（如"林动(少年)"合并进"林动"后成为其别名）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityAlias {
+    pub id: String,
+    pub entity_id: String,
+    pub alias: String,
+    pub created_at: String,
+}
+
 /// 伏笔
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Foreshadow {
@@ -127,6 +226,48 @@ pub struct Foreshadow {
     pub reap_chapter_id: Option<String>,
     /// open / resolved
     pub status: String,
+    /// 计划回收章节 ID
+    pub planned_reap_chapter_id: Option<String>,
+    /// 期限：埋设后 N 章内回收
+    pub deadline_chapters: Option<i64>,
+    /// 优先级：0 低 / 1 普通 / 2 高
+    pub priority: i64,
+    /// 标签 JSON 数组
+    pub tags_json: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// 伏笔状态变更记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForeshadowHistory {
+    pub id: String,
+    pub foreshadow_id: String,
+    /// 变更前状态，创建时为 None
+    pub from_status: Option<String>,
+    /// open / resolved / deleted
+    pub to_status: String,
+    /// 回收时关联的章节
+    pub chapter_id: Option<String>,
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
+/// 伏笔文本锚点（埋设/回收处的原文引用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForeshadowAnchor {
+    pub id: String,
+    pub foreshadow_id: String,
+    /// plant / reap
+    pub role: String,
+    pub chapter_id: String,
+    /// 引用原文
+    pub quote: String,
+    /// 引用在正文中的字符偏移（最近一次定位结果）
+    pub char_offset: i64,
+    /// 引用前后的少量上下文，用于在多处相同原文中区分
+    pub context_before: String,
+    pub context_after: String,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -139,6 +280,8 @@ pub struct RagArc {
     pub end_chapter_id: String,
     /// 剧情概要（80-100字）
     pub summary: String,
+    /// 范围内章节有改动，概要待重新生成
+    pub stale: bool,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -149,7 +292,75 @@ pub struct Snapshot {
     pub id: String,
     pub chapter_id: String,
     pub snapshot_content: String,
+    /// user / ai（采纳 AI 续写、改写结果时创建）/ split / merge（拆分、合并前的原文）
+    pub source: String,
+    pub created_at: String,
+}
+
+/// 章节状态定义
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterStatusDef {
+    /// 内置状态为 draft / revising / complete / dirty / published，自定义状态为 custom:xxxxxxxx
+    pub key: String,
+    pub label: String,
+    /// 显示颜色（#RRGGBB）
+    pub color: String,
+    pub builtin: bool,
+    pub sort_order: i64,
+}
+
+/// 章节状态变更记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterStatusChange {
+    pub id: String,
+    pub chapter_id: String,
+    /// 章节已删除时为 None
+    pub chapter_name: Option<String>,
+    pub from_status: Option<String>,
+    pub to_status: String,
+    /// user / system（编辑、摘要更新等自动变更）
+    pub source: String,
+    /// 本地时间（RFC 3339）
+    pub created_at: String,
+}
+
+/// 连载发布计划（每本书一份）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishPlan {
+    /// 每天的发布时间点（HH:MM，升序），个数即每天更新章数
+    pub release_times: Vec<String>,
+    /// 存稿（已完成未发布）少于该章数时预警，0 为不预警
+    pub buffer_threshold: i64,
+    pub updated_at: Option<String>,
+}
+
+/// AI 提示词模板（每本书可单独修改，未修改时使用内置默认）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    /// continue / rewrite / polish
+    pub kind: String,
+    pub template: String,
+    /// 是否为内置默认模板
+    pub is_default: bool,
+    pub updated_at: Option<String>,
+}
+
+/// AI 抽取提出的待确认变更
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiProposal {
+    pub id: String,
+    /// 抽取来源章节
+    pub chapter_id: String,
+    /// attribute_update / timeline_event
+    pub kind: String,
+    pub entity_id: String,
+    /// attribute_update：{"attributes": {...}}，采纳后追加 "previous" 记录原值；
+    /// timeline_event：{"event": "...", "status_change": null}
+    pub payload_json: String,
+    /// pending / accepted / rejected
+    pub status: String,
     pub created_at: String,
+    pub resolved_at: Option<String>,
 }
 
 /// 回收站条目（软删除，30 天后清理）
@@ -1,9 +1,11 @@
 mod commands;
 mod db;
+mod llm;
+mod text;
 
 use commands::{
-    book, chapter, entity, foreshadow, io,
-    settings, snapshot, stats, volume, window,
+    ai, analytics, arc, assist, book, chapter, context, continuity, entity, extraction, foreshadow, io,
+    publish, restructure, session, settings, snapshot, stats, status, summary, usage, vector, volume, window,
 };
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -25,6 +27,7 @@ pub fn run() {
             // 书籍
             book::create_book,
             book::list_books,
+            book::list_books_with_stats,
             book::update_book,
             book::delete_book,
             book::list_deleted_books,
@@ -35,6 +38,7 @@ pub fn run() {
             volume::list_volumes,
             volume::rename_volume,
             volume::reorder_volumes,
+            volume::duplicate_volume,
             volume::delete_volume,
             // 章节
             chapter::create_chapter,
@@ -44,24 +48,77 @@ pub fn run() {
             chapter::rename_chapter,
             chapter::reorder_chapters,
             chapter::move_chapter,
+            chapter::duplicate_chapter,
             chapter::delete_chapter,
             chapter::set_chapter_status,
             chapter::search_chapters,
+            restructure::split_chapter,
+            restructure::merge_chapters,
+            // 章节状态
+            status::list_chapter_statuses,
+            status::create_chapter_status,
+            status::update_chapter_status_def,
+            status::delete_chapter_status,
+            status::get_status_transitions,
+            status::get_chapter_status_history,
+            status::list_status_changes,
+            // 连载发布
+            publish::get_publish_plan,
+            publish::set_publish_plan,
+            publish::schedule_chapter,
+            publish::auto_schedule_chapters,
+            publish::mark_chapter_published,
+            publish::get_publish_buffer,
+            publish::get_publish_calendar,
             // 设定集
             entity::create_entity,
             entity::list_entities,
+            entity::search_entities,
             entity::get_entity,
             entity::update_entity,
             entity::delete_entity,
+            entity::list_entity_aliases,
+            entity::add_entity_alias,
+            entity::remove_entity_alias,
+            entity::merge_entities,
+            // 连续性检查
+            continuity::check_continuity,
+            continuity::dismiss_continuity_finding,
+            continuity::undismiss_continuity_finding,
             // 伏笔
             foreshadow::create_foreshadow,
             foreshadow::list_foreshadows,
             foreshadow::resolve_foreshadow,
             foreshadow::delete_foreshadow,
+            foreshadow::update_foreshadow,
+            foreshadow::reopen_foreshadow,
+            foreshadow::list_foreshadow_history,
+            foreshadow::foreshadow_report,
+            foreshadow::set_foreshadow_anchor,
+            foreshadow::remove_foreshadow_anchor,
+            foreshadow::locate_foreshadow_anchors,
+            foreshadow::jump_to_foreshadow_anchor,
+            foreshadow::link_foreshadow_entity,
+            foreshadow::unlink_foreshadow_entity,
+            foreshadow::list_foreshadow_entities,
+            foreshadow::list_entity_foreshadows,
             // 统计
             stats::get_daily_stats,
+            stats::get_book_daily_stats,
             stats::update_daily_stats,
             stats::set_daily_goal,
+            stats::set_daily_goal_rule,
+            stats::list_daily_goal_rules,
+            stats::get_daily_goal,
+            stats::rebuild_daily_stats,
+            stats::set_word_count_mode,
+            stats::recompute_word_counts,
+            analytics::get_writing_analytics,
+            // 写作会话
+            session::start_writing_session,
+            session::stop_writing_session,
+            session::get_active_writing_session,
+            session::list_writing_sessions,
             // 快照 & 回收站
             snapshot::list_snapshots,
             snapshot::restore_snapshot,
@@ -75,9 +132,46 @@ pub fn run() {
             settings::update_setting,
             settings::get_data_dir,
             settings::set_data_dir,
+            // AI
+            ai::ai_chat,
+            ai::ai_chat_stream,
+            ai::cancel_ai_request,
+            ai::list_ai_models,
+            usage::list_ai_usage,
+            usage::get_ai_usage_summary,
+            usage::list_ai_budgets,
+            usage::set_ai_budget,
+            usage::delete_ai_budget,
+            summary::generate_chapter_summary,
+            summary::generate_chapter_title,
+            summary::regenerate_dirty_chapters,
+            context::build_ai_context,
+            assist::ai_continue_chapter,
+            assist::ai_rewrite_selection,
+            assist::apply_ai_output,
+            assist::list_prompt_templates,
+            assist::update_prompt_template,
+            assist::reset_prompt_template,
+            extraction::extract_entities_ai,
+            extraction::list_ai_proposals,
+            extraction::accept_ai_proposal,
+            extraction::reject_ai_proposal,
+            vector::semantic_search,
+            vector::update_vector_index,
+            vector::get_vector_index_status,
+            // 剧情弧
+            arc::list_arcs,
+            arc::create_arc,
+            arc::update_arc,
+            arc::delete_arc,
+            arc::segment_arcs,
+            arc::get_arc_for_chapter,
+            arc::generate_arc_summary,
+            arc::summarize_stale_arcs,
             // 导入导出
             io::export_txt,
             io::import_txt,
+            io::export_world_bible,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
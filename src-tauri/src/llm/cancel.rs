@@ -0,0 +1,131 @@
+//! 取消令牌
+//!
+//! 每个 AI 请求由前端生成一个 request_id，后端登记对应的 `CancelToken`；
+//! `cancel_ai_request` 按 ID 触发取消，请求结束时登记自动移除。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::Notify;
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// 可克隆的取消令牌
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<Inner>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// 等待直到被取消（用于 `tokio::select!`）
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.0.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+fn registry() -> &'static Mutex<HashMap<String, CancelToken>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, CancelToken>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 登记守卫：离开作用域时移除登记
+pub struct Registration {
+    request_id: String,
+    pub token: CancelToken,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Ok(mut map) = registry().lock() {
+            // 同一 ID 已被后来的请求重新登记时，不移除对方的令牌
+            if map.get(&self.request_id).is_some_and(|t| Arc::ptr_eq(&t.0, &self.token.0)) {
+                map.remove(&self.request_id);
+            }
+        }
+    }
+}
+
+/// 为请求登记取消令牌（同一 ID 已登记时替换为新令牌）
+pub fn register(request_id: &str) -> Registration {
+    let token = CancelToken::new();
+    if let Ok(mut map) = registry().lock() {
+        map.insert(request_id.to_string(), token.clone());
+    }
+    Registration {
+        request_id: request_id.to_string(),
+        token,
+    }
+}
+
+/// 仅在该 ID 未登记时登记（检查与登记在同一把锁内完成）；已登记时返回 None
+pub fn try_register(request_id: &str) -> Option<Registration> {
+    let token = CancelToken::new();
+    let mut map = registry().lock().ok()?;
+    if map.contains_key(request_id) {
+        return None;
+    }
+    map.insert(request_id.to_string(), token.clone());
+    Some(Registration {
+        request_id: request_id.to_string(),
+        token,
+    })
+}
+
+/// 取消请求；请求不存在（已结束）时返回 false
+pub fn cancel(request_id: &str) -> bool {
+    let token = registry().lock().ok().and_then(|map| map.get(request_id).cloned());
+    match token {
+        Some(t) => {
+            t.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropping_replaced_registration_keeps_newer_token() {
+        let first = register("cancel-test-duplicate");
+        let second = register("cancel-test-duplicate");
+        drop(first);
+        assert!(cancel("cancel-test-duplicate"));
+        assert!(second.token.is_cancelled());
+        drop(second);
+        assert!(!cancel("cancel-test-duplicate"));
+    }
+
+    #[test]
+    fn try_register_rejects_running_request() {
+        let first = try_register("cancel-test-exclusive").unwrap();
+        assert!(try_register("cancel-test-exclusive").is_none());
+        drop(first);
+        assert!(try_register("cancel-test-exclusive").is_some());
+    }
+}
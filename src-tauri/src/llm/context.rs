@@ -0,0 +1,476 @@
+//! 分层上下文组装（续写用）
+//!
+//! 送给模型的上下文由远到近分为五段：
+//! - 前情提要：当前剧情弧之前各剧情弧的 L4 概要
+//! - 本弧前文：当前剧情弧内、近章之前各章的 L2 摘要
+//! - 登场人物：近章正文中出现的人物卡片
+//! - 未回收伏笔
+//! - 近章原文：目标章节及其前 K-1 章的 L1 原文
+//!
+//! 超出 token 预算时按 `compression_mode` 逐步降级，离续写位置越远的信息越先被压缩或舍弃：
+//! - `auto`：L4 → 本弧 L2 降为 L3 标题再舍弃 → 伏笔 → 人物卡片降为一行 → 近章原文降为 L2 → …
+//! - `full`：近章只保留原文或整章舍弃，从不替换为摘要
+//! - `summary`：除目标章节外，近章一开始就使用 L2 摘要
+//!
+//! 全部降级后仍超出预算时，只保留目标章节原文的末尾部分。
+
+use super::tokens::TokenEstimator;
+use rusqlite::Connection;
+
+/// 上下文压缩策略（global.db 设置 `compression_mode`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionMode {
+    Auto,
+    Full,
+    Summary,
+}
+
+impl CompressionMode {
+    /// 未知取值按 auto 处理
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "full" => Self::Full,
+            "summary" => Self::Summary,
+            _ => Self::Auto,
+        }
+    }
+}
+
+/// 上下文组装设置（存储在 global.db 的 settings 表）
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ContextConfig {
+    /// token 预算
+    pub budget: usize,
+    /// 放入原文的近章数 K（含目标章节）
+    pub recent_chapters: usize,
+    pub mode: CompressionMode,
+    /// 估算器参数：每个 CJK 字符折合的 token 数
+    pub tokens_per_cjk: f64,
+}
+
+impl ContextConfig {
+    /// 从 global.db settings 读取
+    pub fn load(conn: &Connection) -> Result<Self, String> {
+        let get = |key: &str| -> Result<Option<String>, String> {
+            match conn.query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get(0)) {
+                Ok(v) => Ok(Some(v)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(format!("读取上下文设置失败: {}", e)),
+            }
+        };
+
+        Ok(Self {
+            budget: get("ai_context_budget")?.and_then(|v| v.parse().ok()).unwrap_or(8000),
+            recent_chapters: get("ai_context_recent_chapters")?.and_then(|v| v.parse().ok()).unwrap_or(3),
+            mode: CompressionMode::parse(&get("compression_mode")?.unwrap_or_default()),
+            tokens_per_cjk: get("ai_tokens_per_cjk")?.and_then(|v| v.parse().ok()).unwrap_or(1.0),
+        })
+    }
+}
+
+/// 章节素材
+#[derive(Debug, Clone)]
+pub struct ChapterText {
+    pub id: String,
+    pub name: String,
+    pub content: String,
+    pub l2_summary: Option<String>,
+    pub l3_title: Option<String>,
+}
+
+/// 剧情弧素材
+#[derive(Debug, Clone)]
+pub struct ArcText {
+    /// 范围说明，如"第一章 ~ 第十章"
+    pub label: String,
+    pub summary: String,
+}
+
+/// 人物卡片素材
+#[derive(Debug, Clone)]
+pub struct EntityCard {
+    pub name: String,
+    pub aliases: Vec<String>,
+    /// 已转为中文的状态
+    pub status: String,
+    /// (属性路径, 属性值)
+    pub attributes: Vec<(String, String)>,
+}
+
+/// 伏笔素材
+#[derive(Debug, Clone)]
+pub struct ForeshadowItem {
+    pub description: String,
+    /// 0 低 / 1 普通 / 2 高
+    pub priority: i64,
+    /// 埋设章节名
+    pub planted_in: Option<String>,
+}
+
+/// 组装素材，各列表均已按重要性/时间排好序
+#[derive(Debug, Clone, Default)]
+pub struct ContextSources {
+    /// 由远到近
+    pub history: Vec<ArcText>,
+    /// 由远到近
+    pub arc_chapters: Vec<ChapterText>,
+    /// 按出现次数降序
+    pub entities: Vec<EntityCard>,
+    /// 按优先级降序
+    pub foreshadows: Vec<ForeshadowItem>,
+    /// 由远到近，最后一章为目标章节
+    pub recent: Vec<ChapterText>,
+}
+
+/// 各段 token 用量
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SectionUsage {
+    /// history / arc / entities / foreshadows / recent
+    pub section: String,
+    pub tokens: usize,
+}
+
+/// 章节最终使用的层级
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ChapterLayer {
+    pub chapter_id: String,
+    /// L1 / L1_tail（截取末尾）/ L2 / L3 / omitted
+    pub layer: String,
+}
+
+/// 组装结果
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AssembledContext {
+    pub text: String,
+    pub total_tokens: usize,
+    pub budget: usize,
+    pub mode: CompressionMode,
+    pub sections: Vec<SectionUsage>,
+    pub chapters: Vec<ChapterLayer>,
+    /// 是否发生过降级
+    pub degraded: bool,
+    /// 目标章节原文是否被截断
+    pub truncated: bool,
+}
+
+// 层级等级：数值越大越精简
+const RANK_FULL: u8 = 0;
+const RANK_SUMMARY: u8 = 1;
+const RANK_TITLE: u8 = 2;
+const RANK_OMITTED: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    History,
+    Arc,
+    Entities,
+    Foreshadows,
+    Recent,
+}
+
+impl Section {
+    const ALL: [Section; 5] = [
+        Section::History,
+        Section::Arc,
+        Section::Entities,
+        Section::Foreshadows,
+        Section::Recent,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            Section::History => "history",
+            Section::Arc => "arc",
+            Section::Entities => "entities",
+            Section::Foreshadows => "foreshadows",
+            Section::Recent => "recent",
+        }
+    }
+
+    fn header(self) -> &'static str {
+        match self {
+            Section::History => "【前情提要】",
+            Section::Arc => "【本弧前文摘要】",
+            Section::Entities => "【登场人物】",
+            Section::Foreshadows => "【未回收伏笔】",
+            Section::Recent => "【近章原文】",
+        }
+    }
+}
+
+/// 一条上下文素材的各级形式（由详到简，最后一级总是舍弃）
+struct Item {
+    section: Section,
+    chapter_id: Option<String>,
+    /// (等级, 层级名, 文本)
+    variants: Vec<(u8, &'static str, String)>,
+    level: usize,
+}
+
+impl Item {
+    fn new(section: Section, chapter_id: Option<&str>, mut variants: Vec<(u8, &'static str, String)>) -> Self {
+        variants.retain(|v| !v.2.trim().is_empty());
+        variants.push((RANK_OMITTED, "omitted", String::new()));
+        Self {
+            section,
+            chapter_id: chapter_id.map(str::to_string),
+            variants,
+            level: 0,
+        }
+    }
+
+    fn text(&self) -> &str {
+        &self.variants[self.level].2
+    }
+
+    /// 降级到不低于 `rank` 的第一种形式；返回是否有变化
+    fn degrade_to(&mut self, rank: u8) -> bool {
+        let target = self.variants.iter().position(|v| v.0 >= rank).unwrap_or(self.variants.len() - 1);
+        if target > self.level {
+            self.level = target;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// 在预算内组装上下文
+pub fn assemble(
+    sources: &ContextSources,
+    budget: usize,
+    mode: CompressionMode,
+    estimator: &dyn TokenEstimator,
+) -> AssembledContext {
+    let mut items = build_items(sources);
+    let target = items.len().checked_sub(1).filter(|_| !sources.recent.is_empty());
+
+    if mode == CompressionMode::Summary {
+        for (i, item) in items.iter_mut().enumerate() {
+            if item.section == Section::Recent && Some(i) != target {
+                item.degrade_to(RANK_SUMMARY);
+            }
+        }
+    }
+
+    let mut degraded = false;
+    for (index, rank) in degrade_steps(&items, target, mode) {
+        if estimator.estimate(&render(&items, None)) <= budget {
+            break;
+        }
+        degraded |= items[index].degrade_to(rank);
+    }
+
+    // 仍超出预算：只保留目标章节原文末尾
+    let mut tail = None;
+    if let Some(t) = target {
+        if estimator.estimate(&render(&items, None)) > budget {
+            let content: Vec<char> = sources.recent[sources.recent.len() - 1].content.chars().collect();
+            let name = &sources.recent[sources.recent.len() - 1].name;
+            let fits = |keep: usize| {
+                let text = format!("《{}》\n……{}", name, content[content.len() - keep..].iter().collect::<String>());
+                estimator.estimate(&render(&items, Some((t, &text)))) <= budget
+            };
+            let (mut lo, mut hi) = (0, content.len());
+            while lo < hi {
+                let mid = (lo + hi).div_ceil(2);
+                if fits(mid) {
+                    lo = mid;
+                } else {
+                    hi = mid - 1;
+                }
+            }
+            let text = format!("《{}》\n……{}", name, content[content.len() - lo..].iter().collect::<String>());
+            tail = Some((t, text));
+            degraded = true;
+        }
+    }
+
+    let text = render(&items, tail.as_ref().map(|(t, s)| (*t, s.as_str())));
+    let sections = Section::ALL
+        .iter()
+        .map(|&section| {
+            let body: Vec<&str> = items
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| item.section == section)
+                .map(|(i, item)| match &tail {
+                    Some((t, s)) if *t == i => s.as_str(),
+                    _ => item.text(),
+                })
+                .filter(|s| !s.is_empty())
+                .collect();
+            SectionUsage {
+                section: section.key().to_string(),
+                tokens: if body.is_empty() { 0 } else { estimator.estimate(&body.join("\n")) },
+            }
+        })
+        .collect();
+    let chapters = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| {
+            let id = item.chapter_id.clone()?;
+            let layer = match &tail {
+                Some((t, _)) if *t == i => "L1_tail",
+                _ => item.variants[item.level].1,
+            };
+            Some(ChapterLayer {
+                chapter_id: id,
+                layer: layer.to_string(),
+            })
+        })
+        .collect();
+
+    AssembledContext {
+        total_tokens: estimator.estimate(&text),
+        text,
+        budget,
+        mode,
+        sections,
+        chapters,
+        degraded,
+        truncated: tail.is_some(),
+    }
+}
+
+fn build_items(sources: &ContextSources) -> Vec<Item> {
+    let mut items = Vec::new();
+
+    for arc in &sources.history {
+        items.push(Item::new(
+            Section::History,
+            None,
+            vec![(RANK_FULL, "L4", format!("{}：{}", arc.label, arc.summary))],
+        ));
+    }
+    for c in &sources.arc_chapters {
+        items.push(Item::new(
+            Section::Arc,
+            Some(&c.id),
+            vec![
+                (RANK_SUMMARY, "L2", opt_line(&c.name, &c.l2_summary)),
+                (RANK_TITLE, "L3", opt_line(&c.name, &c.l3_title)),
+            ],
+        ));
+    }
+    for e in &sources.entities {
+        let mut head = e.name.clone();
+        let mut notes = Vec::new();
+        if !e.aliases.is_empty() {
+            notes.push(format!("别名：{}", e.aliases.join("、")));
+        }
+        if !e.status.is_empty() {
+            notes.push(format!("状态：{}", e.status));
+        }
+        if !notes.is_empty() {
+            head = format!("{}（{}）", head, notes.join("；"));
+        }
+        let attrs = e
+            .attributes
+            .iter()
+            .map(|(k, v)| format!("{}：{}", k, v))
+            .collect::<Vec<_>>()
+            .join("；");
+        let card = if attrs.is_empty() { head.clone() } else { format!("{}\n  {}", head, attrs) };
+        items.push(Item::new(
+            Section::Entities,
+            None,
+            vec![(RANK_FULL, "card", card), (RANK_SUMMARY, "brief", head)],
+        ));
+    }
+    for f in &sources.foreshadows {
+        let priority = match f.priority {
+            p if p >= 2 => "[高] ",
+            0 => "[低] ",
+            _ => "",
+        };
+        let planted = f.planted_in.as_ref().map(|n| format!("（埋于《{}》）", n)).unwrap_or_default();
+        items.push(Item::new(
+            Section::Foreshadows,
+            None,
+            vec![(RANK_FULL, "line", format!("- {}{}{}", priority, f.description, planted))],
+        ));
+    }
+    for c in &sources.recent {
+        items.push(Item::new(
+            Section::Recent,
+            Some(&c.id),
+            vec![
+                (RANK_FULL, "L1", format!("《{}》\n{}", c.name, c.content)),
+                (RANK_SUMMARY, "L2", opt_line(&format!("{}（摘要）", c.name), &c.l2_summary)),
+                (RANK_TITLE, "L3", opt_line(&format!("{}（标题）", c.name), &c.l3_title)),
+            ],
+        ));
+    }
+    items
+}
+
+fn opt_line(name: &str, text: &Option<String>) -> String {
+    match text.as_deref().map(str::trim) {
+        Some(t) if !t.is_empty() => format!("《{}》{}", name, t),
+        _ => String::new(),
+    }
+}
+
+/// 按压缩策略生成降级步骤 (素材下标, 目标等级)
+fn degrade_steps(items: &[Item], target: Option<usize>, mode: CompressionMode) -> Vec<(usize, u8)> {
+    let of = |section: Section| -> Vec<usize> {
+        items
+            .iter()
+            .enumerate()
+            .filter(|(i, item)| item.section == section && Some(*i) != target)
+            .map(|(i, _)| i)
+            .collect()
+    };
+    let history = of(Section::History);
+    let arc = of(Section::Arc);
+    let recent = of(Section::Recent);
+    // 伏笔、人物按重要性降序排列，从末尾开始舍弃
+    let foreshadows: Vec<usize> = of(Section::Foreshadows).into_iter().rev().collect();
+    let entities: Vec<usize> = of(Section::Entities).into_iter().rev().collect();
+
+    let to = |indices: &[usize], rank: u8| indices.iter().map(|&i| (i, rank)).collect::<Vec<_>>();
+
+    let mut steps = Vec::new();
+    steps.extend(to(&history, RANK_OMITTED));
+    steps.extend(to(&arc, RANK_TITLE));
+    steps.extend(to(&arc, RANK_OMITTED));
+    steps.extend(to(&foreshadows, RANK_OMITTED));
+    steps.extend(to(&entities, RANK_SUMMARY));
+    match mode {
+        CompressionMode::Full => {
+            steps.extend(to(&entities, RANK_OMITTED));
+            steps.extend(to(&recent, RANK_OMITTED));
+        }
+        CompressionMode::Auto | CompressionMode::Summary => {
+            steps.extend(to(&recent, RANK_SUMMARY));
+            steps.extend(to(&entities, RANK_OMITTED));
+            steps.extend(to(&recent, RANK_TITLE));
+            steps.extend(to(&recent, RANK_OMITTED));
+        }
+    }
+    steps
+}
+
+/// 拼接全文；`replace` 用于替换某条素材的文本（目标章节截断时）
+fn render(items: &[Item], replace: Option<(usize, &str)>) -> String {
+    let mut parts = Vec::new();
+    for section in Section::ALL {
+        let body: Vec<&str> = items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.section == section)
+            .map(|(i, item)| match replace {
+                Some((r, text)) if r == i => text,
+                _ => item.text(),
+            })
+            .filter(|s| !s.is_empty())
+            .collect();
+        if !body.is_empty() {
+            parts.push(format!("{}\n{}", section.header(), body.join("\n")));
+        }
+    }
+    parts.join("\n\n")
+}
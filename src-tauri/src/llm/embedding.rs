@@ -0,0 +1,109 @@
+//! 文本向量（embedding）接口
+//!
+//! 远程实现走 OpenAI 兼容的 `/embeddings`（见 `openai` 模块）；未配置 embedding 模型时
+//! 使用本地的字符 n-gram 哈希向量，无需联网，对字面相近的段落有不错的召回。
+
+use super::cancel::CancelToken;
+use super::provider::ProviderFuture;
+
+/// 本地哈希向量的维度
+pub const HASHING_DIMS: usize = 512;
+
+/// 向量服务提供方
+pub trait EmbeddingProvider: Send + Sync {
+    /// 模型标识，写入索引以便切换模型后重新计算
+    fn model_id(&self) -> String;
+
+    /// 批量计算向量，返回顺序与输入一致
+    fn embed<'a>(&'a self, texts: &'a [String], cancel: &'a CancelToken) -> ProviderFuture<'a, Vec<Vec<f32>>>;
+}
+
+/// 本地字符 n-gram 哈希向量
+///
+/// 取单字与相邻两字（跳过空白和标点），按 FNV-1a 哈希到固定维度并做 L2 归一化。
+pub struct HashingEmbedder {
+    pub dims: usize,
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self { dims: HASHING_DIMS }
+    }
+}
+
+impl HashingEmbedder {
+    pub fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0f32; self.dims];
+        let chars: Vec<char> = text.chars().filter(|c| c.is_alphanumeric()).collect();
+        let mut add = |feature: &[char], weight: f32| {
+            let hash = fnv1a(feature);
+            let index = (hash % self.dims as u64) as usize;
+            // 用高位决定符号，减少哈希冲突带来的偏差
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[index] += sign * weight;
+        };
+        for unigram in chars.windows(1) {
+            add(unigram, 0.5);
+        }
+        for bigram in chars.windows(2) {
+            add(bigram, 1.0);
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+impl EmbeddingProvider for HashingEmbedder {
+    fn model_id(&self) -> String {
+        format!("local-ngram-{}", self.dims)
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [String], _cancel: &'a CancelToken) -> ProviderFuture<'a, Vec<Vec<f32>>> {
+        Box::pin(async move { Ok(texts.iter().map(|t| self.embed_one(t)).collect()) })
+    }
+}
+
+/// 余弦相似度（维度不一致时为 0）
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// 向量 → BLOB（f32 小端序列）
+pub fn to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+/// BLOB → 向量
+pub fn from_blob(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+fn fnv1a(chars: &[char]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for c in chars {
+        for b in (*c as u32).to_le_bytes() {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
@@ -0,0 +1,140 @@
+//! AI 实体抽取：读取章节正文，提出新实体、已有实体的属性更新、时间线事件
+
+use super::cancel::CancelToken;
+use super::provider::{ChatMessage, ChatProvider, ChatRequest};
+use serde::Deserialize;
+
+/// 送入模型的正文上限
+const MAX_INPUT_CHARS: usize = 12000;
+/// 时间线行为标签最大字数（与 `TimelineNode.event` 约定一致）
+pub const EVENT_MAX_CHARS: usize = 10;
+
+const ENTITY_TYPES: [&str; 4] = ["character", "item", "location", "faction"];
+
+/// 模型可提出的状态变更：角色死亡、道具被毁（其余取值一律丢弃）
+const STATUS_CHANGES: [&str; 2] = ["dead", "destroyed"];
+
+/// 提示中提供给模型的已有实体
+#[derive(Debug, Clone)]
+pub struct KnownEntity {
+    pub name: String,
+    pub entity_type: String,
+    pub attributes: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Extraction {
+    #[serde(default)]
+    pub new_entities: Vec<NewEntity>,
+    #[serde(default)]
+    pub attribute_updates: Vec<AttributeUpdate>,
+    #[serde(default)]
+    pub timeline_events: Vec<TimelineEvent>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewEntity {
+    pub name: String,
+    pub entity_type: String,
+    #[serde(default)]
+    pub attributes: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AttributeUpdate {
+    pub name: String,
+    #[serde(default)]
+    pub attributes: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimelineEvent {
+    pub name: String,
+    pub event: String,
+    #[serde(default)]
+    pub status_change: Option<String>,
+}
+
+const SYSTEM_PROMPT: &str = "\
+你是网络小说设定整理助手。阅读章节正文，整理其中的设定信息，只输出一个 JSON 对象，不加任何说明或代码块标记。格式：
+{
+  \"new_entities\": [{\"name\": \"名称\", \"entity_type\": \"character|item|location|faction\", \"attributes\": {\"属性名\": \"属性值\"}}],
+  \"attribute_updates\": [{\"name\": \"已有实体名称\", \"attributes\": {\"属性名\": \"新值\"}}],
+  \"timeline_events\": [{\"name\": \"实体名称\", \"event\": \"不超过10字的行为标签\", \"status_change\": null}]
+}
+规则：
+- new_entities 只收录本章首次出现、且不在已有实体列表中的人物、道具、地点、势力；
+- attribute_updates 只写本章中发生变化或新揭示的属性，没有变化不要输出；
+- timeline_events 记录实体在本章的关键行为，每个实体最多 2 条；角色死亡时 status_change 为 \"dead\"，道具被毁为 \"destroyed\"，否则为 null；
+- 不要编造正文中没有的信息。";
+
+/// 调用模型抽取；返回结果已做基本清洗（类型校验、标签截断、去除空项）
+pub async fn extract(
+    provider: &dyn ChatProvider,
+    chapter_name: &str,
+    content: &str,
+    known: &[KnownEntity],
+    cancel: &CancelToken,
+) -> Result<Extraction, String> {
+    let body: String = content.chars().take(MAX_INPUT_CHARS).collect();
+    let known_text = if known.is_empty() {
+        "（无）".to_string()
+    } else {
+        known
+            .iter()
+            .map(|k| format!("- {}（{}）：{}", k.name, k.entity_type, k.attributes))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let request = ChatRequest {
+        messages: vec![
+            ChatMessage::system(SYSTEM_PROMPT),
+            ChatMessage::user(format!(
+                "已有实体：\n{}\n\n章节：{}\n\n正文：\n{}",
+                known_text, chapter_name, body
+            )),
+        ],
+        temperature: Some(0.1),
+        max_tokens: Some(2000),
+    };
+
+    let response = provider.chat(&request, cancel).await?;
+    parse(&response.content)
+}
+
+/// 解析模型输出（容忍 <think> 段落、代码块包裹与前后多余文字）
+pub fn parse(text: &str) -> Result<Extraction, String> {
+    let text = match text.find("</think>") {
+        Some(pos) => &text[pos + "</think>".len()..],
+        None => text,
+    };
+    let (Some(start), Some(end)) = (text.find('{'), text.rfind('}')) else {
+        return Err("AI 返回内容不是 JSON".into());
+    };
+    if start > end {
+        return Err("AI 返回内容不是 JSON".into());
+    }
+    let mut extraction: Extraction =
+        serde_json::from_str(&text[start..=end]).map_err(|e| format!("解析 AI 抽取结果失败: {}", e))?;
+
+    extraction.new_entities.retain_mut(|e| {
+        e.name = e.name.trim().to_string();
+        e.entity_type = e.entity_type.trim().to_lowercase();
+        !e.name.is_empty() && ENTITY_TYPES.contains(&e.entity_type.as_str())
+    });
+    extraction.attribute_updates.retain_mut(|u| {
+        u.name = u.name.trim().to_string();
+        !u.name.is_empty() && !u.attributes.is_empty()
+    });
+    extraction.timeline_events.retain_mut(|t| {
+        t.name = t.name.trim().to_string();
+        t.event = t.event.trim().chars().take(EVENT_MAX_CHARS).collect();
+        t.status_change = t
+            .status_change
+            .take()
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| STATUS_CHANGES.contains(&s.as_str()));
+        !t.name.is_empty() && !t.event.is_empty()
+    });
+    Ok(extraction)
+}
@@ -0,0 +1,83 @@
+//! AI 服务层：OpenAI 兼容的对话接口（OpenAI、DeepSeek、llama.cpp、Ollama 等）
+//!
+//! - `provider`：`ChatProvider` trait，业务代码只依赖它，测试时可换成固定输出的假实现
+//! - `openai`：基于 reqwest 的 OpenAI 兼容实现（流式、超时、重试、取消）
+//! - `cancel`：取消令牌及按请求 ID 的全局登记表
+//! - `summary`：L2 章节摘要 / L3 章节标题 / L4 剧情弧概要生成
+//! - `context`：续写用的分层上下文组装（token 预算内）
+//! - `tokens`：token 数估算
+//! - `prompt`：续写、改写、润色的提示词模板
+//! - `extract`：从章节正文抽取新实体、属性变化与时间线事件
+//! - `embedding`：文本向量接口及本地 n-gram 哈希实现（语义检索用）
+//! - `usage`：调用记录与每月 token 预算
+
+pub mod cancel;
+pub mod context;
+pub mod embedding;
+pub mod extract;
+pub mod openai;
+pub mod prompt;
+pub mod provider;
+pub mod summary;
+pub mod tokens;
+pub mod usage;
+
+use rusqlite::Connection;
+
+/// AI 服务配置（存储在 global.db 的 settings 表，键名以 ai_ 开头）
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AiConfig {
+    /// 接口根地址，如 https://api.openai.com/v1、http://127.0.0.1:11434/v1
+    pub base_url: String,
+    pub model: String,
+    /// 语义检索用的 embedding 模型，留空使用本地哈希向量
+    pub embedding_model: String,
+    /// 本地服务通常不需要，留空则不发送 Authorization 头
+    pub api_key: String,
+    /// 非流式请求的总超时；流式请求中两次数据之间的最长等待
+    pub timeout_secs: u64,
+    /// 连接失败、超时、429、5xx 时的最大重试次数
+    pub max_retries: u32,
+}
+
+impl AiConfig {
+    /// 从 global.db settings 读取
+    pub fn load(conn: &Connection) -> Result<Self, String> {
+        let get = |key: &str| -> Result<Option<String>, String> {
+            match conn.query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get(0)) {
+                Ok(v) => Ok(Some(v)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(format!("读取 AI 设置失败: {}", e)),
+            }
+        };
+
+        Ok(Self {
+            base_url: get("ai_base_url")?.unwrap_or_else(|| "http://127.0.0.1:11434/v1".into()),
+            model: get("ai_model")?.unwrap_or_default(),
+            embedding_model: get("ai_embedding_model")?.unwrap_or_default(),
+            api_key: get("ai_api_key")?.unwrap_or_default(),
+            timeout_secs: get("ai_timeout_secs")?.and_then(|v| v.parse().ok()).unwrap_or(120),
+            max_retries: get("ai_max_retries")?.and_then(|v| v.parse().ok()).unwrap_or(2),
+        })
+    }
+}
+
+/// 按当前设置创建 OpenAI 兼容的服务实例
+pub fn configured_provider() -> Result<openai::OpenAiCompatible, String> {
+    let conn = crate::db::open_global_db()?;
+    let config = AiConfig::load(&conn)?;
+    if config.model.trim().is_empty() {
+        return Err("未配置 AI 模型".into());
+    }
+    openai::OpenAiCompatible::new(config)
+}
+
+/// 按当前设置创建向量服务：配置了 embedding 模型时走远程接口（记录用量），否则用本地哈希向量
+pub fn configured_embedder(context: usage::UsageContext) -> Result<Box<dyn embedding::EmbeddingProvider>, String> {
+    let conn = crate::db::open_global_db()?;
+    let config = AiConfig::load(&conn)?;
+    if config.embedding_model.trim().is_empty() {
+        return Ok(Box::new(embedding::HashingEmbedder::default()));
+    }
+    Ok(Box::new(usage::Metered::new(openai::OpenAiCompatible::new(config)?, context)))
+}
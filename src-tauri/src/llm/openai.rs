@@ -0,0 +1,475 @@
+//! OpenAI 兼容接口实现（POST {base_url}/chat/completions、{base_url}/embeddings）
+
+use super::cancel::CancelToken;
+use super::embedding::EmbeddingProvider;
+use super::provider::{ChatProvider, ChatRequest, ChatResponse, ProviderFuture, CANCELLED};
+use super::AiConfig;
+use std::time::Duration;
+
+/// 首次重试前的等待时间，之后每次翻倍
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+pub struct OpenAiCompatible {
+    config: AiConfig,
+    client: reqwest::Client,
+}
+
+impl OpenAiCompatible {
+    pub fn new(config: AiConfig) -> Result<Self, String> {
+        if config.base_url.trim().is_empty() {
+            return Err("未配置 AI 接口地址".into());
+        }
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
+        Ok(Self { config, client })
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.config.timeout_secs.max(1))
+    }
+
+    fn request_body(&self, request: &ChatRequest, stream: bool) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "model": self.config.model,
+            "messages": request.messages,
+            "stream": stream,
+        });
+        if let Some(t) = request.temperature {
+            body["temperature"] = t.into();
+        }
+        if let Some(m) = request.max_tokens {
+            body["max_tokens"] = m.into();
+        }
+        if stream {
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+        body
+    }
+
+    /// 列出服务端可用模型（GET {base_url}/models）
+    pub async fn list_models(&self) -> Result<Vec<String>, String> {
+        let mut req = self.client.get(self.endpoint("models")).timeout(self.timeout());
+        if !self.config.api_key.is_empty() {
+            req = req.bearer_auth(&self.config.api_key);
+        }
+        let resp = req.send().await.map_err(|e| format!("请求模型列表失败: {}", e))?;
+        let status = resp.status();
+        if !status.is_success() {
+            let text = resp.text().await.unwrap_or_default();
+            return Err(format!("请求模型列表失败 (HTTP {}): {}", status.as_u16(), text));
+        }
+        let json: serde_json::Value = resp.json().await.map_err(|e| format!("解析模型列表失败: {}", e))?;
+        Ok(json["data"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m["id"].as_str().map(String::from))
+            .collect())
+    }
+
+    /// 发送请求，连接失败、超时、429、5xx 时按指数退避重试
+    ///
+    /// 流式请求不设总超时（由逐块读取的空闲超时控制）。
+    async fn send(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+        stream: bool,
+        cancel: &CancelToken,
+    ) -> Result<reqwest::Response, String> {
+        let mut attempt = 0;
+        loop {
+            let mut req = self.client.post(self.endpoint(path)).json(body);
+            if !self.config.api_key.is_empty() {
+                req = req.bearer_auth(&self.config.api_key);
+            }
+            if !stream {
+                req = req.timeout(self.timeout());
+            }
+
+            let result = tokio::select! {
+                _ = cancel.cancelled() => return Err(CANCELLED.into()),
+                r = req.send() => r,
+            };
+
+            let retry_reason = match result {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp) => {
+                    let status = resp.status();
+                    let text = resp.text().await.unwrap_or_default();
+                    let message = format!("AI 接口返回错误 (HTTP {}): {}", status.as_u16(), text);
+                    if status.as_u16() != 429 && !status.is_server_error() {
+                        return Err(message);
+                    }
+                    message
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => format!("AI 接口请求失败: {}", e),
+                Err(e) => return Err(format!("AI 接口请求失败: {}", e)),
+            };
+
+            if attempt >= self.config.max_retries {
+                return Err(retry_reason);
+            }
+            let delay = Duration::from_millis(RETRY_BASE_DELAY_MS << attempt.min(6));
+            attempt += 1;
+            log::warn!("{}，{} ms 后第 {} 次重试", retry_reason, delay.as_millis(), attempt);
+            tokio::select! {
+                _ = cancel.cancelled() => return Err(CANCELLED.into()),
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+    }
+
+    async fn chat_impl(&self, request: &ChatRequest, cancel: &CancelToken) -> Result<ChatResponse, String> {
+        let body = self.request_body(request, false);
+        let resp = self.send("chat/completions", &body, false, cancel).await?;
+
+        let json: serde_json::Value = tokio::select! {
+            _ = cancel.cancelled() => return Err(CANCELLED.into()),
+            r = resp.json() => r.map_err(|e| format!("解析 AI 回复失败: {}", e))?,
+        };
+
+        Ok(ChatResponse {
+            content: json["choices"][0]["message"]["content"].as_str().unwrap_or_default().to_string(),
+            model: json["model"].as_str().unwrap_or(&self.config.model).to_string(),
+            prompt_tokens: json["usage"]["prompt_tokens"].as_i64(),
+            completion_tokens: json["usage"]["completion_tokens"].as_i64(),
+        })
+    }
+
+    async fn chat_stream_impl(
+        &self,
+        request: &ChatRequest,
+        cancel: &CancelToken,
+        on_delta: &mut (dyn FnMut(&str) + Send),
+    ) -> Result<ChatResponse, String> {
+        let body = self.request_body(request, true);
+        let mut resp = self.send("chat/completions", &body, true, cancel).await?;
+
+        let mut result = ChatResponse {
+            model: self.config.model.clone(),
+            ..Default::default()
+        };
+        // 按字节缓冲，只处理完整的行，避免多字节字符被分块截断
+        let mut buffer: Vec<u8> = Vec::new();
+
+        loop {
+            let chunk = tokio::select! {
+                _ = cancel.cancelled() => return Err(CANCELLED.into()),
+                r = tokio::time::timeout(self.timeout(), resp.chunk()) => match r {
+                    Err(_) => return Err("AI 接口响应超时".into()),
+                    Ok(r) => r.map_err(|e| format!("读取 AI 回复失败: {}", e))?,
+                },
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
+            buffer.extend_from_slice(&chunk);
+
+            while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line);
+                if parse_sse_line(line.trim(), &mut result, on_delta)? {
+                    return Ok(result);
+                }
+            }
+        }
+
+        // 服务端未以换行结尾时处理剩余数据
+        let rest = String::from_utf8_lossy(&buffer).to_string();
+        parse_sse_line(rest.trim(), &mut result, on_delta)?;
+        Ok(result)
+    }
+
+    async fn embed_impl(&self, texts: &[String], cancel: &CancelToken) -> Result<Vec<Vec<f32>>, String> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let body = serde_json::json!({
+            "model": self.config.embedding_model,
+            "input": texts,
+        });
+        let resp = self.send("embeddings", &body, false, cancel).await?;
+        let json: serde_json::Value = tokio::select! {
+            _ = cancel.cancelled() => return Err(CANCELLED.into()),
+            r = resp.json() => r.map_err(|e| format!("解析向量结果失败: {}", e))?,
+        };
+
+        // 按 index 放回输入顺序
+        let mut vectors = vec![Vec::new(); texts.len()];
+        for (i, item) in json["data"].as_array().into_iter().flatten().enumerate() {
+            let index = item["index"].as_u64().map(|v| v as usize).unwrap_or(i);
+            if let Some(slot) = vectors.get_mut(index) {
+                *slot = item["embedding"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|v| v.as_f64().map(|f| f as f32))
+                    .collect();
+            }
+        }
+        if vectors.iter().any(|v| v.is_empty()) {
+            return Err("向量接口返回的结果数量不完整".into());
+        }
+        Ok(vectors)
+    }
+}
+
+/// 解析一行 SSE 数据，返回 true 表示收到 [DONE]
+fn parse_sse_line(
+    line: &str,
+    result: &mut ChatResponse,
+    on_delta: &mut (dyn FnMut(&str) + Send),
+) -> Result<bool, String> {
+    let Some(data) = line.strip_prefix("data:") else {
+        return Ok(false);
+    };
+    let data = data.trim();
+    if data == "[DONE]" {
+        return Ok(true);
+    }
+
+    let json: serde_json::Value = serde_json::from_str(data).map_err(|e| format!("解析 AI 流式数据失败: {}", e))?;
+    if let Some(err) = json.get("error") {
+        return Err(format!("AI 接口返回错误: {}", err));
+    }
+    if let Some(model) = json["model"].as_str() {
+        result.model = model.to_string();
+    }
+    if let Some(delta) = json["choices"][0]["delta"]["content"].as_str() {
+        if !delta.is_empty() {
+            result.content.push_str(delta);
+            on_delta(delta);
+        }
+    }
+    if let Some(usage) = json.get("usage").filter(|u| u.is_object()) {
+        result.prompt_tokens = usage["prompt_tokens"].as_i64();
+        result.completion_tokens = usage["completion_tokens"].as_i64();
+    }
+    Ok(false)
+}
+
+impl ChatProvider for OpenAiCompatible {
+    fn chat<'a>(&'a self, request: &'a ChatRequest, cancel: &'a CancelToken) -> ProviderFuture<'a, ChatResponse> {
+        Box::pin(self.chat_impl(request, cancel))
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        request: &'a ChatRequest,
+        cancel: &'a CancelToken,
+        on_delta: &'a mut (dyn FnMut(&str) + Send),
+    ) -> ProviderFuture<'a, ChatResponse> {
+        Box::pin(self.chat_stream_impl(request, cancel, on_delta))
+    }
+
+    fn model_name(&self) -> String {
+        self.config.model.clone()
+    }
+}
+
+impl EmbeddingProvider for OpenAiCompatible {
+    fn model_id(&self) -> String {
+        self.config.embedding_model.clone()
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [String], cancel: &'a CancelToken) -> ProviderFuture<'a, Vec<Vec<f32>>> {
+        Box::pin(self.embed_impl(texts, cancel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::cancel;
+    use crate::llm::provider::ChatMessage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// 模拟服务端对一次连接的应答
+    enum Reply {
+        /// 状态码 + 完整正文
+        Status(u16, &'static str),
+        /// 200 的 SSE 流，按块分次写出
+        Chunks(Vec<Vec<u8>>),
+        /// 写出一块后不再响应
+        Hang(&'static str),
+    }
+
+    /// 启动本地模拟服务，依次用 `replies` 应答每个连接；返回接口地址与已处理的连接数
+    async fn serve(replies: Vec<Reply>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            for reply in replies {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                counter.fetch_add(1, Ordering::SeqCst);
+                read_request(&mut stream).await;
+                respond(stream, reply).await;
+            }
+        });
+        (format!("http://{}/v1", addr), hits)
+    }
+
+    async fn read_request(stream: &mut TcpStream) {
+        let mut buf = Vec::new();
+        let mut tmp = [0u8; 4096];
+        loop {
+            let n = stream.read(&mut tmp).await.unwrap_or(0);
+            if n == 0 {
+                return;
+            }
+            buf.extend_from_slice(&tmp[..n]);
+            if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                let head = String::from_utf8_lossy(&buf[..end]).to_lowercase();
+                let len = head
+                    .lines()
+                    .find_map(|l| l.strip_prefix("content-length:"))
+                    .and_then(|v| v.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                if buf.len() >= end + 4 + len {
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn respond(mut stream: TcpStream, reply: Reply) {
+        const SSE_HEAD: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n";
+        match reply {
+            Reply::Status(code, body) => {
+                let head = format!(
+                    "HTTP/1.1 {} X\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    code,
+                    body.len()
+                );
+                let _ = stream.write_all(head.as_bytes()).await;
+                let _ = stream.write_all(body.as_bytes()).await;
+            }
+            Reply::Chunks(chunks) => {
+                let _ = stream.write_all(SSE_HEAD.as_bytes()).await;
+                for chunk in chunks {
+                    let _ = stream.write_all(&chunk).await;
+                    let _ = stream.flush().await;
+                    tokio::time::sleep(Duration::from_millis(30)).await;
+                }
+            }
+            Reply::Hang(first) => {
+                let _ = stream.write_all(SSE_HEAD.as_bytes()).await;
+                let _ = stream.write_all(first.as_bytes()).await;
+                let _ = stream.flush().await;
+                std::future::pending::<()>().await;
+            }
+        }
+        let _ = stream.shutdown().await;
+    }
+
+    fn provider(base_url: String, timeout_secs: u64, max_retries: u32) -> OpenAiCompatible {
+        OpenAiCompatible::new(AiConfig {
+            base_url,
+            model: "mock".into(),
+            embedding_model: String::new(),
+            api_key: String::new(),
+            timeout_secs,
+            max_retries,
+        })
+        .unwrap()
+    }
+
+    fn request() -> ChatRequest {
+        ChatRequest {
+            messages: vec![ChatMessage::user("你好")],
+            ..Default::default()
+        }
+    }
+
+    const DELTA_ONE: &str = "data: {\"choices\":[{\"delta\":{\"content\":\"一\"}}]}\n\n";
+
+    #[tokio::test]
+    async fn stream_handles_split_chunks() {
+        // 第二个事件的 data 行跨两次读取，且切在多字节字符中间
+        let second = "data: {\"model\":\"m1\",\"choices\":[{\"delta\":{\"content\":\"二\"}}]}\n\n".as_bytes();
+        let cut = second.len() - 8;
+        let (url, _) = serve(vec![Reply::Chunks(vec![
+            DELTA_ONE.as_bytes().to_vec(),
+            second[..cut].to_vec(),
+            second[cut..].to_vec(),
+            b"data: {\"choices\":[],\"usage\":{\"prompt_tokens\":3,\"completion_tokens\":2}}\n\ndata: [DONE]\n\n".to_vec(),
+        ])])
+        .await;
+
+        let mut deltas = Vec::new();
+        let mut on_delta = |d: &str| deltas.push(d.to_string());
+        let response = provider(url, 5, 0)
+            .chat_stream(&request(), &CancelToken::new(), &mut on_delta)
+            .await
+            .unwrap();
+        assert_eq!(deltas, vec!["一", "二"]);
+        assert_eq!(response.content, "一二");
+        assert_eq!(response.model, "m1");
+        assert_eq!((response.prompt_tokens, response.completion_tokens), (Some(3), Some(2)));
+    }
+
+    #[tokio::test]
+    async fn retries_after_rate_limit() {
+        let (url, hits) = serve(vec![
+            Reply::Status(429, "{\"error\":\"slow down\"}"),
+            Reply::Status(200, "{\"model\":\"mock\",\"choices\":[{\"message\":{\"content\":\"好\"}}]}"),
+        ])
+        .await;
+        let response = provider(url, 5, 2).chat(&request(), &CancelToken::new()).await.unwrap();
+        assert_eq!(response.content, "好");
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let max_retries = 1;
+        let replies = (0..=max_retries).map(|_| Reply::Status(500, "boom")).collect();
+        let (url, hits) = serve(replies).await;
+        let err = provider(url, 5, max_retries).chat(&request(), &CancelToken::new()).await.unwrap_err();
+        assert!(err.contains("HTTP 500"), "{}", err);
+        assert_eq!(hits.load(Ordering::SeqCst), max_retries as usize + 1);
+    }
+
+    #[tokio::test]
+    async fn hanging_stream_hits_idle_timeout() {
+        let (url, _) = serve(vec![Reply::Hang(DELTA_ONE)]).await;
+        let mut deltas = Vec::new();
+        let mut on_delta = |d: &str| deltas.push(d.to_string());
+        let err = provider(url, 1, 0)
+            .chat_stream(&request(), &CancelToken::new(), &mut on_delta)
+            .await
+            .unwrap_err();
+        assert_eq!(err, "AI 接口响应超时");
+        assert_eq!(deltas, vec!["一"]);
+    }
+
+    #[tokio::test]
+    async fn hanging_stream_can_be_cancelled() {
+        let (url, _) = serve(vec![Reply::Hang(DELTA_ONE)]).await;
+        let registration = cancel::register("openai-test-cancel");
+        tokio::spawn(async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            assert!(cancel::cancel("openai-test-cancel"));
+        });
+        let mut on_delta = |_: &str| {};
+        let err = provider(url, 30, 0)
+            .chat_stream(&request(), &registration.token, &mut on_delta)
+            .await
+            .unwrap_err();
+        assert_eq!(err, CANCELLED);
+    }
+}
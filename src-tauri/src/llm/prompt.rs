@@ -0,0 +1,70 @@
+//! AI 写作提示词模板
+//!
+//! 模板中的 `{{变量}}` 在发送前替换，可用变量：
+//! - `{{chapter_name}}`：目标章节名
+//! - `{{context}}`：分层上下文（见 `context` 模块）
+//! - `{{length}}`：续写目标字数
+//! - `{{before}}` / `{{selection}}` / `{{after}}`：选区前文、选区原文、选区后文
+//! - `{{instruction}}`：用户本次附加的要求（未填写时为空）
+
+/// 模板种类
+pub const KINDS: [&str; 3] = ["continue", "rewrite", "polish"];
+
+/// 所有模板共用的系统提示
+pub const SYSTEM_PROMPT: &str = "你是一位经验丰富的网络小说写作助手，熟悉中文网文的叙事节奏与语言风格。";
+
+const CONTINUE_TEMPLATE: &str = "\
+以下是小说的背景资料与最近章节：
+
+{{context}}
+
+请紧接《{{chapter_name}}》正文的最后一句续写约 {{length}} 字。\
+保持人物性格、叙述视角和文风一致，不要重复已有内容，不要总结，直接输出正文，不加任何说明。
+{{instruction}}";
+
+const REWRITE_TEMPLATE: &str = "\
+下面是《{{chapter_name}}》中的一段文字及其前后文。
+
+【前文】
+{{before}}
+【待改写】
+{{selection}}
+【后文】
+{{after}}
+
+请改写【待改写】部分，使其表达更生动，并与前后文自然衔接，情节走向保持不变。
+{{instruction}}
+只输出改写后的文字，不加任何说明。";
+
+const POLISH_TEMPLATE: &str = "\
+下面是《{{chapter_name}}》中的一段文字及其前后文。
+
+【前文】
+{{before}}
+【待润色】
+{{selection}}
+【后文】
+{{after}}
+
+请润色【待润色】部分：修正错别字和病句，优化用词与节奏，不增删情节和信息。
+{{instruction}}
+只输出润色后的文字，不加任何说明。";
+
+/// 内置默认模板
+pub fn default_template(kind: &str) -> Option<&'static str> {
+    match kind {
+        "continue" => Some(CONTINUE_TEMPLATE),
+        "rewrite" => Some(REWRITE_TEMPLATE),
+        "polish" => Some(POLISH_TEMPLATE),
+        _ => None,
+    }
+}
+
+/// 替换模板变量；未知变量原样保留
+pub fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = template.to_string();
+    for (name, value) in vars {
+        out = out.replace(&format!("{{{{{}}}}}", name), value);
+    }
+    out.trim().to_string()
+}
@@ -0,0 +1,149 @@
+//! 对话接口抽象
+
+use super::cancel::CancelToken;
+use std::future::Future;
+use std::pin::Pin;
+
+/// 对话消息
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ChatMessage {
+    /// system / user / assistant
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: "system".into(),
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".into(),
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: "assistant".into(),
+            content: content.into(),
+        }
+    }
+}
+
+/// 对话请求（模型由配置决定）
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ChatRequest {
+    pub messages: Vec<ChatMessage>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+}
+
+/// 对话结果
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ChatResponse {
+    pub content: String,
+    pub model: String,
+    /// 服务端未返回用量时为 None
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
+}
+
+/// 取消时返回的错误信息
+pub const CANCELLED: &str = "请求已取消";
+
+pub type ProviderFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+/// 对话服务提供方
+pub trait ChatProvider: Send + Sync {
+    /// 一次性返回完整回复
+    fn chat<'a>(&'a self, request: &'a ChatRequest, cancel: &'a CancelToken) -> ProviderFuture<'a, ChatResponse>;
+
+    /// 流式回复：每收到一段增量文本调用一次 `on_delta`，结束后返回完整结果
+    ///
+    /// 默认实现退化为一次性请求，整段回复作为一个增量。
+    fn chat_stream<'a>(
+        &'a self,
+        request: &'a ChatRequest,
+        cancel: &'a CancelToken,
+        on_delta: &'a mut (dyn FnMut(&str) + Send),
+    ) -> ProviderFuture<'a, ChatResponse> {
+        Box::pin(async move {
+            let response = self.chat(request, cancel).await?;
+            on_delta(&response.content);
+            Ok(response)
+        })
+    }
+
+    /// 配置的模型名（用于调用记录；请求失败时没有回复可取）
+    fn model_name(&self) -> String {
+        String::new()
+    }
+}
+
+/// 测试用的假实现：按请求返回预设回复，流式时逐字输出
+#[cfg(test)]
+pub(crate) mod fake {
+    use super::*;
+
+    type Reply = dyn Fn(&ChatRequest) -> Result<String, String> + Send + Sync;
+
+    pub(crate) struct FakeProvider {
+        reply: Box<Reply>,
+    }
+
+    impl FakeProvider {
+        pub(crate) fn new(reply: impl Fn(&ChatRequest) -> Result<String, String> + Send + Sync + 'static) -> Self {
+            Self { reply: Box::new(reply) }
+        }
+
+        fn respond(&self, request: &ChatRequest, cancel: &CancelToken) -> Result<ChatResponse, String> {
+            if cancel.is_cancelled() {
+                return Err(CANCELLED.into());
+            }
+            let content = (self.reply)(request)?;
+            // 回复函数内可能触发取消，模拟请求进行中被取消
+            if cancel.is_cancelled() {
+                return Err(CANCELLED.into());
+            }
+            Ok(ChatResponse {
+                content,
+                model: "fake".into(),
+                prompt_tokens: Some(0),
+                completion_tokens: Some(0),
+            })
+        }
+    }
+
+    impl ChatProvider for FakeProvider {
+        fn chat<'a>(&'a self, request: &'a ChatRequest, cancel: &'a CancelToken) -> ProviderFuture<'a, ChatResponse> {
+            Box::pin(async move { self.respond(request, cancel) })
+        }
+
+        fn chat_stream<'a>(
+            &'a self,
+            request: &'a ChatRequest,
+            cancel: &'a CancelToken,
+            on_delta: &'a mut (dyn FnMut(&str) + Send),
+        ) -> ProviderFuture<'a, ChatResponse> {
+            Box::pin(async move {
+                let response = self.respond(request, cancel)?;
+                for c in response.content.chars() {
+                    if cancel.is_cancelled() {
+                        return Err(CANCELLED.into());
+                    }
+                    on_delta(&c.to_string());
+                }
+                Ok(response)
+            })
+        }
+
+        fn model_name(&self) -> String {
+            "fake".into()
+        }
+    }
+}
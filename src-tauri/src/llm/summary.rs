@@ -0,0 +1,220 @@
+//! L2 章节摘要（200-300 字）与 L3 章节标题（≤20 字）生成
+
+use super::cancel::CancelToken;
+use super::provider::{ChatMessage, ChatProvider, ChatRequest};
+
+/// L2 摘要最大字数（模型超出时截断到句末）
+pub const L2_MAX_CHARS: usize = 300;
+/// L2 摘要最少字数（正文本身不足 L2_MAX_CHARS 的短章不要求）
+pub const L2_MIN_CHARS: usize = 200;
+/// L2 摘要过短时要求模型重写的次数
+const L2_LENGTH_RETRIES: usize = 1;
+/// L3 标题最大字数
+pub const L3_MAX_CHARS: usize = 20;
+/// L4 剧情弧概要最大字数
+pub const ARC_MAX_CHARS: usize = 100;
+/// 送入模型的正文上限，超长章节只取开头部分
+const MAX_INPUT_CHARS: usize = 12000;
+
+/// 生成 L2 章节摘要
+///
+/// 超过 L2_MAX_CHARS 时截断到最后一个完整句子；不足 L2_MIN_CHARS 时要求模型重写，
+/// 重写后仍过短则返回错误。
+pub async fn generate_summary(
+    provider: &dyn ChatProvider,
+    chapter_name: &str,
+    content: &str,
+    cancel: &CancelToken,
+) -> Result<String, String> {
+    let body: String = content.chars().take(MAX_INPUT_CHARS).collect();
+    let short_chapter = content.chars().count() < L2_MAX_CHARS;
+    let mut request = ChatRequest {
+        messages: vec![
+            ChatMessage::system(
+                "你是网络小说编辑，负责为章节撰写剧情摘要。摘要用第三人称、过去时，\
+                 交代本章主要人物、关键事件与结尾悬念，200-300 字，不加标题、不分点、不评价。",
+            ),
+            ChatMessage::user(format!("章节：{}\n\n正文：\n{}", chapter_name, body)),
+        ],
+        temperature: Some(0.3),
+        max_tokens: Some(600),
+    };
+
+    let mut attempt = 0;
+    loop {
+        let response = provider.chat(&request, cancel).await?;
+        let summary = clean_output(&response.content);
+        if summary.is_empty() {
+            return Err("AI 返回的摘要为空".into());
+        }
+        let summary = truncate_sentence(&summary, L2_MAX_CHARS);
+        let len = summary.chars().count();
+        if len >= L2_MIN_CHARS || short_chapter {
+            return Ok(summary);
+        }
+        if attempt == L2_LENGTH_RETRIES {
+            return Err(format!("AI 返回的摘要过短（{} 字，应为 {}-{} 字）", len, L2_MIN_CHARS, L2_MAX_CHARS));
+        }
+        attempt += 1;
+        request.messages.push(ChatMessage::assistant(response.content));
+        request.messages.push(ChatMessage::user(format!(
+            "摘要只有 {} 字，请补充关键情节，重写为 {}-{} 字，只输出摘要本身。",
+            len, L2_MIN_CHARS, L2_MAX_CHARS
+        )));
+    }
+}
+
+/// 生成 L3 章节标题（根据摘要，没有摘要时根据正文开头）
+pub async fn generate_title(
+    provider: &dyn ChatProvider,
+    chapter_name: &str,
+    source: &str,
+    cancel: &CancelToken,
+) -> Result<String, String> {
+    let body: String = source.chars().take(MAX_INPUT_CHARS).collect();
+    let request = ChatRequest {
+        messages: vec![
+            ChatMessage::system(
+                "你是网络小说编辑，负责为章节起标题。标题概括本章核心事件，不超过 20 字，\
+                 不带\"第X章\"、书名号或引号，只输出标题本身。",
+            ),
+            ChatMessage::user(format!("章节：{}\n\n内容：\n{}", chapter_name, body)),
+        ],
+        temperature: Some(0.5),
+        max_tokens: Some(60),
+    };
+
+    let response = provider.chat(&request, cancel).await?;
+    let first_line = clean_output(&response.content).lines().next().unwrap_or_default().to_string();
+    let title = first_line
+        .trim_start_matches("标题：")
+        .trim_start_matches("标题:")
+        .trim_matches(|c: char| c.is_whitespace() || "《》“”\"'「」".contains(c))
+        .to_string();
+    if title.is_empty() {
+        return Err("AI 返回的标题为空".into());
+    }
+    Ok(truncate_chars(&title, L3_MAX_CHARS))
+}
+
+/// 根据弧内各章 L2 摘要生成 L4 剧情弧概要
+///
+/// `chapters` 为按阅读顺序排列的 (章节名, L2 摘要)。
+pub async fn generate_arc_summary(
+    provider: &dyn ChatProvider,
+    chapters: &[(String, String)],
+    cancel: &CancelToken,
+) -> Result<String, String> {
+    let body = chapters
+        .iter()
+        .map(|(name, l2)| format!("【{}】{}", name, l2))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let request = ChatRequest {
+        messages: vec![
+            ChatMessage::system(
+                "你是网络小说编辑，负责把连续若干章的摘要浓缩为一段剧情弧概要。\
+                 概要交代这一段的主线冲突、转折与结果，80-100 字，不分点、不评价。",
+            ),
+            ChatMessage::user(format!("各章摘要（按顺序）：\n{}", body)),
+        ],
+        temperature: Some(0.3),
+        max_tokens: Some(300),
+    };
+
+    let response = provider.chat(&request, cancel).await?;
+    let summary = clean_output(&response.content);
+    if summary.is_empty() {
+        return Err("AI 返回的概要为空".into());
+    }
+    Ok(truncate_chars(&summary, ARC_MAX_CHARS))
+}
+
+/// 去掉推理模型的 <think> 段落与首尾空白
+fn clean_output(text: &str) -> String {
+    let text = match text.find("</think>") {
+        Some(pos) => &text[pos + "</think>".len()..],
+        None => text,
+    };
+    text.trim().to_string()
+}
+
+fn truncate_chars(s: &str, max: usize) -> String {
+    s.chars().take(max).collect()
+}
+
+/// 截断到 `max` 字以内的最后一个句末（含紧随其后的引号）；找不到句末时按字数截断
+fn truncate_sentence(s: &str, max: usize) -> String {
+    const ENDS: &str = "。！？!?…";
+    const QUOTES: &str = "”」』\"";
+    let chars: Vec<char> = s.chars().take(max + 1).collect();
+    if chars.len() <= max {
+        return s.to_string();
+    }
+    let chars = &chars[..max];
+    let end = (0..chars.len())
+        .rev()
+        .find(|&i| {
+            ENDS.contains(chars[i]) || (i > 0 && QUOTES.contains(chars[i]) && ENDS.contains(chars[i - 1]))
+        })
+        .map(|i| i + 1)
+        .unwrap_or(max);
+    chars[..end].iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::provider::fake::FakeProvider;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const LONG_CHAPTER: &str = "正文。";
+
+    fn long_content() -> String {
+        LONG_CHAPTER.repeat(200)
+    }
+
+    #[test]
+    fn truncates_at_sentence_end() {
+        let text = format!("{}{}", "甲".repeat(250), "。乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙乙");
+        let cut = truncate_sentence(&text, L2_MAX_CHARS);
+        assert_eq!(cut.chars().count(), 251);
+        assert!(cut.ends_with('。'));
+
+        let quoted = format!("{}。”{}", "甲".repeat(260), "乙".repeat(60));
+        assert!(truncate_sentence(&quoted, L2_MAX_CHARS).ends_with("。”"));
+
+        let no_end = "甲".repeat(400);
+        assert_eq!(truncate_sentence(&no_end, L2_MAX_CHARS).chars().count(), L2_MAX_CHARS);
+    }
+
+    #[tokio::test]
+    async fn retries_short_summary_then_accepts() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let provider = FakeProvider::new(move |request| {
+            Ok(match counter.fetch_add(1, Ordering::SeqCst) {
+                0 => "太短了。".to_string(),
+                _ => {
+                    assert_eq!(request.messages.len(), 4);
+                    "足".repeat(219) + "。"
+                }
+            })
+        });
+        let summary = generate_summary(&provider, "第一章", &long_content(), &CancelToken::new()).await.unwrap();
+        assert_eq!(summary.chars().count(), 220);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn reports_summary_still_too_short() {
+        let provider = FakeProvider::new(|_| Ok("太短了。".to_string()));
+        let err = generate_summary(&provider, "第一章", &long_content(), &CancelToken::new()).await.unwrap_err();
+        assert!(err.contains("摘要过短"), "{}", err);
+
+        // 正文本身很短时不要求最少字数
+        let summary = generate_summary(&provider, "第一章", LONG_CHAPTER, &CancelToken::new()).await.unwrap();
+        assert_eq!(summary, "太短了。");
+    }
+}
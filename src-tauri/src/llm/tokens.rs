@@ -0,0 +1,55 @@
+//! Token 数估算
+//!
+//! 不同模型的分词器差异很大，这里只做预算用的近似估算。
+//! 业务代码依赖 `TokenEstimator` trait，需要更准的估算时可换成具体分词器的实现。
+
+/// Token 数估算器
+pub trait TokenEstimator: Send + Sync {
+    fn estimate(&self, text: &str) -> usize;
+}
+
+/// 中日韩文本估算：CJK 字符按每字若干 token 计，其余字符按每若干字符 1 token 计
+#[derive(Debug, Clone, Copy)]
+pub struct CjkEstimator {
+    /// 每个 CJK 字符（含全角标点）折合的 token 数（GPT-4 系约 1.0，Qwen/DeepSeek 约 0.6-0.7）
+    pub tokens_per_cjk: f64,
+    /// 多少个其他字符（英文、数字、半角标点、空白）折合 1 token
+    pub chars_per_token: f64,
+}
+
+impl Default for CjkEstimator {
+    fn default() -> Self {
+        Self {
+            tokens_per_cjk: 1.0,
+            chars_per_token: 4.0,
+        }
+    }
+}
+
+impl TokenEstimator for CjkEstimator {
+    fn estimate(&self, text: &str) -> usize {
+        let (cjk, other) = text.chars().fold((0usize, 0usize), |(cjk, other), c| {
+            if is_cjk(c) {
+                (cjk + 1, other)
+            } else {
+                (cjk, other + 1)
+            }
+        });
+        let tokens = cjk as f64 * self.tokens_per_cjk + other as f64 / self.chars_per_token.max(1.0);
+        tokens.ceil() as usize
+    }
+}
+
+/// CJK 统一表意文字、假名、谚文及全角标点
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3000..=0x303F     // CJK 标点
+        | 0x3040..=0x30FF   // 平假名、片假名
+        | 0x3400..=0x4DBF   // 扩展 A
+        | 0x4E00..=0x9FFF   // 基本区
+        | 0xAC00..=0xD7AF   // 谚文
+        | 0xF900..=0xFAFF   // 兼容表意文字
+        | 0xFF00..=0xFFEF   // 全角字符
+        | 0x20000..=0x2FA1F // 扩展 B 及以后
+    )
+}
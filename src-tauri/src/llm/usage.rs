@@ -0,0 +1,241 @@
+//! AI 调用记录与每月 token 预算
+//!
+//! `Metered` 包装任意 `ChatProvider` / `EmbeddingProvider`：调用前检查预算（超出且设为
+//! block 时直接拒绝），调用后把书籍、功能、模型、token、耗时、结果写入 global.db 的
+//! `ai_usage` 表。服务端没有返回用量时按 `tokens` 模块估算并标记 estimated。
+
+use super::cancel::CancelToken;
+use super::embedding::EmbeddingProvider;
+use super::provider::{ChatProvider, ChatRequest, ChatResponse, ProviderFuture, CANCELLED};
+use super::tokens::{CjkEstimator, TokenEstimator};
+use crate::db::models::AiBudget;
+use rusqlite::{params, Connection};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// 全局预算的 scope
+pub const GLOBAL_SCOPE: &str = "global";
+
+/// 预算提醒回调
+pub type BudgetWarning = Arc<dyn Fn(&AiBudget) + Send + Sync>;
+
+/// 一次调用的归属
+#[derive(Clone, Default)]
+pub struct UsageContext {
+    pub book_id: Option<String>,
+    pub feature: String,
+    /// 预算已超出但设为 warn 时回调（每次调用前检查）
+    pub on_warning: Option<BudgetWarning>,
+}
+
+impl UsageContext {
+    pub fn new(book_id: Option<String>, feature: &str) -> Self {
+        Self {
+            book_id,
+            feature: feature.to_string(),
+            on_warning: None,
+        }
+    }
+}
+
+/// 带用量记录与预算检查的服务包装
+pub struct Metered<P> {
+    inner: P,
+    context: UsageContext,
+}
+
+impl<P> Metered<P> {
+    pub fn new(inner: P, context: UsageContext) -> Self {
+        Self { inner, context }
+    }
+
+    /// 检查适用于本次调用的预算：block 类超出时记录并拒绝，warn 类超出时回调提醒
+    fn check_budget(&self, model: &str) -> Result<(), String> {
+        let conn = crate::db::open_global_db()?;
+        for budget in applicable_budgets(&conn, self.context.book_id.as_deref())? {
+            if !budget.exceeded {
+                continue;
+            }
+            if budget.action == "block" {
+                let message = format!(
+                    "本月 AI token 预算已用完（{} / {}）",
+                    budget.used_tokens, budget.monthly_tokens
+                );
+                self.record(&conn, model, (0, 0, false), 0, "blocked", Some(&message));
+                return Err(message);
+            }
+            if let Some(on_warning) = &self.context.on_warning {
+                on_warning(&budget);
+            }
+        }
+        Ok(())
+    }
+
+    /// 写入一条调用记录；记录失败不影响调用结果
+    fn record(
+        &self,
+        conn: &Connection,
+        model: &str,
+        (prompt_tokens, completion_tokens, estimated): (i64, i64, bool),
+        latency_ms: i64,
+        status: &str,
+        error: Option<&str>,
+    ) {
+        let now = chrono::Local::now();
+        let result = conn.execute(
+            "INSERT INTO ai_usage (id, date, book_id, feature, model, prompt_tokens, completion_tokens,
+             estimated, latency_ms, status, error, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                uuid::Uuid::new_v4().to_string(),
+                now.format("%Y-%m-%d").to_string(),
+                self.context.book_id,
+                self.context.feature,
+                model,
+                prompt_tokens,
+                completion_tokens,
+                estimated,
+                latency_ms,
+                status,
+                error,
+                now.to_rfc3339(),
+            ],
+        );
+        if let Err(e) = result {
+            log::warn!("记录 AI 调用失败: {}", e);
+        }
+    }
+
+    fn finish(&self, model: &str, tokens: (i64, i64, bool), started: Instant, error: Option<&String>) {
+        let status = match error {
+            None => "ok",
+            Some(e) if e == CANCELLED => "cancelled",
+            Some(_) => "error",
+        };
+        match crate::db::open_global_db() {
+            Ok(conn) => self.record(
+                &conn,
+                model,
+                tokens,
+                started.elapsed().as_millis() as i64,
+                status,
+                error.map(String::as_str),
+            ),
+            Err(e) => log::warn!("记录 AI 调用失败: {}", e),
+        }
+    }
+}
+
+impl<P: ChatProvider> Metered<P> {
+    fn finish_chat(&self, request: &ChatRequest, result: &Result<ChatResponse, String>, started: Instant) {
+        let estimator = CjkEstimator::default();
+        let estimate_prompt = || {
+            request
+                .messages
+                .iter()
+                .map(|m| estimator.estimate(&m.content))
+                .sum::<usize>() as i64
+        };
+        match result {
+            Ok(resp) => {
+                let model = if resp.model.is_empty() { self.inner.model_name() } else { resp.model.clone() };
+                let tokens = match (resp.prompt_tokens, resp.completion_tokens) {
+                    (Some(p), Some(c)) => (p, c, false),
+                    _ => (estimate_prompt(), estimator.estimate(&resp.content) as i64, true),
+                };
+                self.finish(&model, tokens, started, None);
+            }
+            // 失败的调用仍可能已计费，按提示词估算
+            Err(e) => self.finish(&self.inner.model_name(), (estimate_prompt(), 0, true), started, Some(e)),
+        }
+    }
+}
+
+impl<P: ChatProvider> ChatProvider for Metered<P> {
+    fn chat<'a>(&'a self, request: &'a ChatRequest, cancel: &'a CancelToken) -> ProviderFuture<'a, ChatResponse> {
+        Box::pin(async move {
+            self.check_budget(&self.inner.model_name())?;
+            let started = Instant::now();
+            let result = self.inner.chat(request, cancel).await;
+            self.finish_chat(request, &result, started);
+            result
+        })
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        request: &'a ChatRequest,
+        cancel: &'a CancelToken,
+        on_delta: &'a mut (dyn FnMut(&str) + Send),
+    ) -> ProviderFuture<'a, ChatResponse> {
+        Box::pin(async move {
+            self.check_budget(&self.inner.model_name())?;
+            let started = Instant::now();
+            let result = self.inner.chat_stream(request, cancel, on_delta).await;
+            self.finish_chat(request, &result, started);
+            result
+        })
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+}
+
+impl<P: EmbeddingProvider> EmbeddingProvider for Metered<P> {
+    fn model_id(&self) -> String {
+        self.inner.model_id()
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [String], cancel: &'a CancelToken) -> ProviderFuture<'a, Vec<Vec<f32>>> {
+        Box::pin(async move {
+            let model = self.inner.model_id();
+            self.check_budget(&model)?;
+            let started = Instant::now();
+            let result = self.inner.embed(texts, cancel).await;
+            let estimator = CjkEstimator::default();
+            let tokens = texts.iter().map(|t| estimator.estimate(t)).sum::<usize>() as i64;
+            self.finish(&model, (tokens, 0, true), started, result.as_ref().err());
+            result
+        })
+    }
+}
+
+/// 适用于某本书的全部预算（全局预算 + 该书预算）及本月用量
+pub fn applicable_budgets(conn: &Connection, book_id: Option<&str>) -> Result<Vec<AiBudget>, String> {
+    Ok(list_budgets(conn)?
+        .into_iter()
+        .filter(|b| b.scope == GLOBAL_SCOPE || Some(b.scope.as_str()) == book_id)
+        .collect())
+}
+
+/// 全部预算及本月用量（全局在前）
+pub fn list_budgets(conn: &Connection) -> Result<Vec<AiBudget>, String> {
+    let month = chrono::Local::now().format("%Y-%m").to_string();
+    let mut stmt = conn
+        .prepare(
+            "SELECT b.scope, b.monthly_tokens, b.action,
+                    COALESCE((SELECT SUM(u.prompt_tokens + u.completion_tokens) FROM ai_usage u
+                              WHERE substr(u.date, 1, 7) = ?1
+                                AND (b.scope = ?2 OR u.book_id = b.scope)), 0)
+             FROM ai_budgets b
+             ORDER BY b.scope != ?2, b.scope",
+        )
+        .map_err(|e| format!("查询 AI 预算失败: {}", e))?;
+    let budgets = stmt
+        .query_map(params![month, GLOBAL_SCOPE], |row| {
+            let monthly_tokens: i64 = row.get(1)?;
+            let used_tokens: i64 = row.get(3)?;
+            Ok(AiBudget {
+                scope: row.get(0)?,
+                monthly_tokens,
+                action: row.get(2)?,
+                used_tokens,
+                exceeded: monthly_tokens > 0 && used_tokens >= monthly_tokens,
+            })
+        })
+        .map_err(|e| format!("读取 AI 预算失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析 AI 预算失败: {}", e))?;
+    Ok(budgets)
+}
@@ -0,0 +1,137 @@
+//! 文本锚点定位
+//!
+//! 锚点 = 引用原文 + 字符偏移 + 前后少量上下文。章节被编辑后偏移会失效，
+//! 定位时先找原文的精确出现位置（优先上下文吻合、离原偏移最近的一处），
+//! 找不到再做近似子串匹配（编辑距离），容忍引用原文本身被小幅修改。
+//!
+//! 所有偏移与长度均以字符（char）计。
+
+/// 锚点在正文中的定位结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnchorMatch {
+    pub offset: usize,
+    pub length: usize,
+    /// 与引用原文的编辑距离，精确匹配为 0
+    pub distance: usize,
+}
+
+/// 近似匹配允许的最大编辑距离：引用长度的 30%，至少 1
+fn max_distance(quote_len: usize) -> usize {
+    (quote_len * 3 / 10).max(1)
+}
+
+/// 在正文中定位锚点
+pub fn locate(content: &str, quote: &str, hint: usize, before: &str, after: &str) -> Option<AnchorMatch> {
+    let text: Vec<char> = content.chars().collect();
+    let pattern: Vec<char> = quote.chars().collect();
+    if pattern.is_empty() || text.is_empty() {
+        return None;
+    }
+
+    locate_exact(&text, &pattern, hint, before, after).or_else(|| locate_fuzzy(&text, &pattern, hint))
+}
+
+/// 截取锚点前后的上下文（各 `n` 个字符）
+pub fn context(content: &str, offset: usize, length: usize, n: usize) -> (String, String) {
+    let before: String = content.chars().skip(offset.saturating_sub(n)).take(offset.min(n)).collect();
+    let after: String = content.chars().skip(offset + length).take(n).collect();
+    (before, after)
+}
+
+fn locate_exact(text: &[char], pattern: &[char], hint: usize, before: &str, after: &str) -> Option<AnchorMatch> {
+    let before: Vec<char> = before.chars().collect();
+    let after: Vec<char> = after.chars().collect();
+    let m = pattern.len();
+
+    (0..=text.len().saturating_sub(m))
+        .filter(|&i| text[i..].starts_with(pattern))
+        .min_by_key(|&i| {
+            let ctx_ok = text[..i].ends_with(&before) && text[i + m..].starts_with(&after);
+            (!ctx_ok, i.abs_diff(hint))
+        })
+        .map(|offset| AnchorMatch {
+            offset,
+            length: m,
+            distance: 0,
+        })
+}
+
+/// 近似子串匹配（Sellers 算法）：正文任意位置开始/结束，求与引用编辑距离最小的片段
+fn locate_fuzzy(text: &[char], pattern: &[char], hint: usize) -> Option<AnchorMatch> {
+    let m = pattern.len();
+    let limit = max_distance(m);
+
+    // 每一列保存 (编辑距离, 片段起点)
+    let mut prev: Vec<(usize, usize)> = (0..=m).map(|i| (i, 0)).collect();
+    let mut best: Option<AnchorMatch> = None;
+
+    for (j, &tc) in text.iter().enumerate() {
+        let mut cur = vec![(0, j + 1); m + 1];
+        for i in 1..=m {
+            let sub = (prev[i - 1].0 + usize::from(pattern[i - 1] != tc), prev[i - 1].1);
+            let del = (cur[i - 1].0 + 1, cur[i - 1].1);
+            let ins = (prev[i].0 + 1, prev[i].1);
+            cur[i] = [sub, del, ins].into_iter().min_by_key(|c| c.0).unwrap_or(sub);
+        }
+
+        let (distance, start) = cur[m];
+        if distance <= limit {
+            let candidate = AnchorMatch {
+                offset: start,
+                length: j + 1 - start,
+                distance,
+            };
+            let better = match &best {
+                None => true,
+                Some(b) => (distance, start.abs_diff(hint)) < (b.distance, b.offset.abs_diff(hint)),
+            };
+            if better {
+                best = Some(candidate);
+            }
+        }
+        prev = cur;
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUOTE: &str = "一块温润的玉佩";
+
+    #[test]
+    fn exact_match_follows_moved_text() {
+        let content = "新加的开头。他从怀里掏出一块温润的玉佩，递给了她。";
+        let m = locate(content, QUOTE, 6, "", "").unwrap();
+        assert_eq!((m.offset, m.length, m.distance), (12, 7, 0));
+    }
+
+    #[test]
+    fn exact_match_prefers_context_then_nearest() {
+        let content = "玉佩。玉佩！";
+        assert_eq!(locate(content, "玉佩", 0, "。", "！").unwrap().offset, 3);
+        assert_eq!(locate(content, "玉佩", 4, "", "").unwrap().offset, 3);
+        assert_eq!(locate(content, "玉佩", 1, "", "").unwrap().offset, 0);
+        // 上下文都不吻合时按离原偏移的距离
+        assert_eq!(locate(content, "玉佩", 5, "？", "？").unwrap().offset, 3);
+    }
+
+    #[test]
+    fn fuzzy_match_tolerates_edits_within_limit() {
+        let m = locate("他从怀里掏出一块温热的玉佩，递给了她。", QUOTE, 0, "", "").unwrap();
+        assert_eq!((m.offset, m.length, m.distance), (6, 7, 1));
+        let m = locate("他从怀里掏出一块冰冷的玉佩。", QUOTE, 0, "", "").unwrap();
+        assert_eq!((m.offset, m.length, m.distance), (6, 7, 2));
+    }
+
+    #[test]
+    fn no_match_beyond_max_distance() {
+        assert_eq!(max_distance(7), 2);
+        assert_eq!(max_distance(2), 1);
+        assert!(locate("他从怀里掏出一块冰凉的石头。", QUOTE, 0, "", "").is_none());
+        assert!(locate("完全无关的内容", QUOTE, 0, "", "").is_none());
+        assert!(locate("", QUOTE, 0, "", "").is_none());
+    }
+}
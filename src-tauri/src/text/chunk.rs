@@ -0,0 +1,89 @@
+//! 正文分块（用于向量索引）
+//!
+//! 以段落为单位累积到目标长度；单个段落过长时在句末标点处切开，仍过长则按长度硬切。
+//! 段落边界稳定，因此修改一段文字通常只会影响它所在的分块。
+//!
+//! 所有偏移均以字符（char）计。
+
+/// 句末标点
+const SENTENCE_ENDS: [char; 6] = ['。', '！', '？', '!', '?', '…'];
+
+/// 一个分块
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// 把文本切成约 `target` 字的分块（空白段落不单独成块，结果不含首尾空白）
+pub fn split(text: &str, target: usize) -> Vec<Chunk> {
+    let target = target.max(1);
+    let chars: Vec<char> = text.chars().collect();
+
+    // 先切成不超过 2 × target 的片段
+    let mut pieces: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '\n' {
+            split_long(&chars, start, i + 1, target * 2, &mut pieces);
+            start = i + 1;
+        }
+    }
+    split_long(&chars, start, chars.len(), target * 2, &mut pieces);
+
+    // 再把相邻片段合并到目标长度
+    let mut chunks = Vec::new();
+    let mut current: Option<(usize, usize)> = None;
+    for (s, e) in pieces {
+        current = match current {
+            Some((cs, ce)) if ce - cs < target && e - cs <= target * 2 => Some((cs, e)),
+            Some(done) => {
+                push_chunk(&chars, done, &mut chunks);
+                Some((s, e))
+            }
+            None => Some((s, e)),
+        };
+    }
+    if let Some(done) = current {
+        push_chunk(&chars, done, &mut chunks);
+    }
+    chunks
+}
+
+fn split_long(chars: &[char], start: usize, end: usize, max: usize, out: &mut Vec<(usize, usize)>) {
+    let mut s = start;
+    while end - s > max {
+        let window = &chars[s..s + max];
+        let cut = window
+            .iter()
+            .rposition(|c| SENTENCE_ENDS.contains(c))
+            .filter(|&p| p >= max / 2)
+            .map(|p| s + p + 1)
+            .unwrap_or(s + max);
+        out.push((s, cut));
+        s = cut;
+    }
+    if s < end {
+        out.push((s, end));
+    }
+}
+
+/// 去掉首尾空白后加入结果；全是空白则丢弃
+fn push_chunk(chars: &[char], (start, end): (usize, usize), out: &mut Vec<Chunk>) {
+    let mut s = start;
+    let mut e = end;
+    while s < e && chars[s].is_whitespace() {
+        s += 1;
+    }
+    while e > s && chars[e - 1].is_whitespace() {
+        e -= 1;
+    }
+    if s < e {
+        out.push(Chunk {
+            start: s,
+            end: e,
+            text: chars[s..e].iter().collect(),
+        });
+    }
+}
@@ -0,0 +1,128 @@
+//! 字数统计
+//!
+//! 各平台的计数口径不同，提供以下模式（global.db settings 的 `word_count_mode`）：
+//! - `all`：全部字符，含空白与换行（旧版行为）
+//! - `non_whitespace`：去掉空白后的字符数
+//! - `cjk_words`：汉字（含日文假名、韩文）按字计，连续的字母数字按一个词计，不计标点
+//! - `qidian`：起点口径，汉字与中文标点按字计，英文单词与数字串按一个词计
+//! - `fanqie`：番茄口径，去掉空白后的全部字符（含标点、字母、数字逐个计）
+//!
+//! 除 `all` 外均先去掉 HTML 标签，平台口径为近似值。
+
+use std::sync::OnceLock;
+
+/// 计数模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CountMode {
+    #[default]
+    All,
+    NonWhitespace,
+    CjkWords,
+    Qidian,
+    Fanqie,
+}
+
+impl CountMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "all" => Some(Self::All),
+            "non_whitespace" => Some(Self::NonWhitespace),
+            "cjk_words" => Some(Self::CjkWords),
+            "qidian" => Some(Self::Qidian),
+            "fanqie" => Some(Self::Fanqie),
+            _ => None,
+        }
+    }
+}
+
+/// 按模式统计字数
+pub fn count(text: &str, mode: CountMode) -> i64 {
+    let n = match mode {
+        CountMode::All => text.chars().count(),
+        CountMode::NonWhitespace | CountMode::Fanqie => {
+            strip_markup(text).chars().filter(|c| !c.is_whitespace()).count()
+        }
+        CountMode::CjkWords => count_cjk_and_words(&strip_markup(text), false),
+        CountMode::Qidian => count_cjk_and_words(&strip_markup(text), true),
+    };
+    n as i64
+}
+
+/// 汉字逐字计、字母数字串按词计；`with_punctuation` 时中文标点也逐个计
+fn count_cjk_and_words(text: &str, with_punctuation: bool) -> usize {
+    let mut n = 0;
+    let mut in_word = false;
+    for c in text.chars() {
+        if is_cjk(c) {
+            n += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() {
+            if !in_word {
+                n += 1;
+                in_word = true;
+            }
+        } else {
+            in_word = in_word && (c == '\'' || c == '-');
+            if with_punctuation && is_cjk_punctuation(c) {
+                n += 1;
+            }
+        }
+    }
+    n
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{4E00}'..='\u{9FFF}'     // CJK 统一表意文字
+        | '\u{3400}'..='\u{4DBF}'   // 扩展 A
+        | '\u{20000}'..='\u{2FA1F}' // 扩展 B 及之后、兼容补充
+        | '\u{F900}'..='\u{FAFF}'   // 兼容表意文字
+        | '\u{3040}'..='\u{30FF}'   // 平假名、片假名
+        | '\u{AC00}'..='\u{D7AF}'   // 韩文音节
+    )
+}
+
+fn is_cjk_punctuation(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{303F}'     // CJK 符号和标点
+        | '\u{FF00}'..='\u{FFEF}'   // 全角符号
+        | '\u{2014}' | '\u{2026}'   // 破折号、省略号
+        | '\u{2018}'..='\u{201D}'   // 中文引号
+        | '\u{00B7}'                // 间隔号
+    )
+}
+
+fn strip_markup(text: &str) -> std::borrow::Cow<'_, str> {
+    static TAG: OnceLock<regex::Regex> = OnceLock::new();
+    let re = TAG.get_or_init(|| regex::Regex::new(r"</?[A-Za-z][^<>]*>").expect("标签正则有效"));
+    re.replace_all(text, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_per_mode() {
+        use CountMode::*;
+        // (文本, [all, non_whitespace, cjk_words, qidian, fanqie])
+        let cases: [(&str, [i64; 5]); 5] = [
+            ("你好，世界！", [6, 6, 4, 6, 6]),
+            ("Hello world 2024", [16, 14, 3, 3, 14]),
+            ("<p>他说：“don't go”</p>\n", [21, 12, 4, 7, 12]),
+            ("state-of-the-art 三个字", [20, 19, 4, 4, 19]),
+            ("", [0, 0, 0, 0, 0]),
+        ];
+        for (text, expected) in cases {
+            let actual = [All, NonWhitespace, CjkWords, Qidian, Fanqie].map(|mode| count(text, mode));
+            assert_eq!(actual, expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn parses_mode_names() {
+        assert_eq!(CountMode::parse(" qidian "), Some(CountMode::Qidian));
+        assert_eq!(CountMode::parse("non_whitespace"), Some(CountMode::NonWhitespace));
+        assert_eq!(CountMode::parse("words"), None);
+    }
+}
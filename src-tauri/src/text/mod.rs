@@ -0,0 +1,4 @@
+pub mod anchor;
+pub mod chunk;
+pub mod count;
+pub mod pinyin;
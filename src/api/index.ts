@@ -1,5 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { Book, Volume, Chapter, Entity, Foreshadow, DailyStat, Snapshot, TrashItem, Setting } from "@/types";
+import type { Book, Volume, Chapter, RestructureResult, ChapterStatusDef, ChapterStatusChange, PublishPlan, ScheduledChapter, BufferReport, Entity, Foreshadow, DailyStat, DailyGoalRule, WritingAnalytics, WritingSession, Snapshot, TrashItem, Setting } from "@/types";
 
 // ============================================================================
 // 书籍管理
@@ -53,6 +53,9 @@ export const renameVolume = (storagePath: string, id: string, name: string) =>
 export const reorderVolumes = (storagePath: string, ids: string[]) =>
   invoke<void>("reorder_volumes", { storagePath, ids });
 
+export const duplicateVolume = (storagePath: string, id: string, name?: string) =>
+  invoke<Volume>("duplicate_volume", { storagePath, id, name });
+
 export const deleteVolume = (storagePath: string, id: string) =>
   invoke<void>("delete_volume", { storagePath, id });
 
@@ -69,8 +72,9 @@ export const listChapters = (storagePath: string, volumeId: string) =>
 export const getChapter = (storagePath: string, id: string) =>
   invoke<Chapter>("get_chapter", { storagePath, id });
 
-export const updateChapter = (storagePath: string, id: string, content: string) =>
-  invoke<void>("update_chapter", { storagePath, id, content });
+// source：快照与写作日志的来源，默认 user；采纳 AI 输出传 "ai"，批量替换传 "replace"
+export const updateChapter = (storagePath: string, id: string, content: string, source?: string) =>
+  invoke<void>("update_chapter", { storagePath, id, content, source });
 
 export const renameChapter = (storagePath: string, id: string, name: string) =>
   invoke<void>("rename_chapter", { storagePath, id, name });
@@ -81,12 +85,72 @@ export const reorderChapters = (storagePath: string, ids: string[]) =>
 export const moveChapter = (storagePath: string, id: string, targetVolumeId: string) =>
   invoke<void>("move_chapter", { storagePath, id, targetVolumeId });
 
+export const duplicateChapter = (storagePath: string, id: string, opts: { targetVolumeId?: string; name?: string } = {}) =>
+  invoke<Chapter>("duplicate_chapter", { storagePath, id, ...opts });
+
 export const deleteChapter = (storagePath: string, id: string) =>
   invoke<void>("delete_chapter", { storagePath, id });
 
 export const setChapterStatus = (storagePath: string, id: string, status: string) =>
   invoke<void>("set_chapter_status", { storagePath, id, status });
 
+export const splitChapter = (storagePath: string, id: string, offset: number, name?: string) =>
+  invoke<RestructureResult>("split_chapter", { storagePath, id, offset, name });
+
+export const mergeChapters = (storagePath: string, ids: string[]) =>
+  invoke<RestructureResult>("merge_chapters", { storagePath, ids });
+
+// ============================================================================
+// 章节状态
+// ============================================================================
+
+export const listChapterStatuses = (storagePath: string) =>
+  invoke<ChapterStatusDef[]>("list_chapter_statuses", { storagePath });
+
+export const createChapterStatus = (storagePath: string, label: string, color: string) =>
+  invoke<ChapterStatusDef>("create_chapter_status", { storagePath, label, color });
+
+export const updateChapterStatusDef = (storagePath: string, key: string, opts: { label?: string; color?: string }) =>
+  invoke<void>("update_chapter_status_def", { storagePath, key, ...opts });
+
+export const deleteChapterStatus = (storagePath: string, key: string) =>
+  invoke<number>("delete_chapter_status", { storagePath, key });
+
+export const getStatusTransitions = (storagePath: string, chapterId: string) =>
+  invoke<string[]>("get_status_transitions", { storagePath, chapterId });
+
+export const getChapterStatusHistory = (storagePath: string, chapterId: string) =>
+  invoke<ChapterStatusChange[]>("get_chapter_status_history", { storagePath, chapterId });
+
+export const listStatusChanges = (storagePath: string, startDate: string, endDate: string, toStatus?: string) =>
+  invoke<ChapterStatusChange[]>("list_status_changes", { storagePath, toStatus, startDate, endDate });
+
+// ============================================================================
+// 连载发布
+// ============================================================================
+
+export const getPublishPlan = (storagePath: string) =>
+  invoke<PublishPlan>("get_publish_plan", { storagePath });
+
+export const setPublishPlan = (storagePath: string, releaseTimes: string[], bufferThreshold: number) =>
+  invoke<PublishPlan>("set_publish_plan", { storagePath, releaseTimes, bufferThreshold });
+
+// scheduledAt 为本地时间 YYYY-MM-DDTHH:MM，传 null 清除
+export const scheduleChapter = (storagePath: string, id: string, scheduledAt: string | null) =>
+  invoke<void>("schedule_chapter", { storagePath, id, scheduledAt });
+
+export const autoScheduleChapters = (storagePath: string, startDate: string) =>
+  invoke<ScheduledChapter[]>("auto_schedule_chapters", { storagePath, startDate });
+
+export const markChapterPublished = (storagePath: string, id: string) =>
+  invoke<BufferReport>("mark_chapter_published", { storagePath, id });
+
+export const getPublishBuffer = (storagePath: string) =>
+  invoke<BufferReport>("get_publish_buffer", { storagePath });
+
+export const getPublishCalendar = (storagePath: string, startDate: string, endDate: string) =>
+  invoke<ScheduledChapter[]>("get_publish_calendar", { storagePath, startDate, endDate });
+
 export interface SearchHit {
   chapter_id: string;
   chapter_name: string;
@@ -139,12 +203,43 @@ export const deleteForeshadow = (storagePath: string, id: string) =>
 export const getDailyStats = (startDate: string, endDate: string) =>
   invoke<DailyStat[]>("get_daily_stats", { startDate, endDate });
 
-export const updateDailyStats = (date: string, wordCountDelta: number, durationDelta: number) =>
-  invoke<void>("update_daily_stats", { date, wordCountDelta, durationDelta });
+// 字数由后端在保存章节时记录，这里只上报写作时长
+export const updateDailyStats = (date: string, durationDelta: number, storagePath?: string) =>
+  invoke<void>("update_daily_stats", { date, durationDelta, storagePath });
+
+export const rebuildDailyStats = () => invoke<number>("rebuild_daily_stats");
 
 export const setDailyGoal = (goal: number) =>
   invoke<void>("set_daily_goal", { goal });
 
+// weekday：1-7 为周一至周日，省略表示每天；goal 为 0 时删除该规则
+export const setDailyGoalRule = (goal: number, opts: { bookId?: string; weekday?: number } = {}) =>
+  invoke<void>("set_daily_goal_rule", { goal, ...opts });
+
+export const listDailyGoalRules = () => invoke<DailyGoalRule[]>("list_daily_goal_rules");
+
+export const getDailyGoal = (bookId?: string, date?: string) =>
+  invoke<number>("get_daily_goal", { bookId, date });
+
+export const getWritingAnalytics = (startDate: string, endDate: string) =>
+  invoke<WritingAnalytics>("get_writing_analytics", { startDate, endDate });
+
+// ============================================================================
+// 写作会话
+// ============================================================================
+
+export const startWritingSession = (
+  storagePath: string,
+  opts: { chapterId?: string; pomodoroWorkMinutes?: number; pomodoroBreakMinutes?: number } = {},
+) => invoke<WritingSession>("start_writing_session", { storagePath, ...opts });
+
+export const stopWritingSession = () => invoke<WritingSession | null>("stop_writing_session");
+
+export const getActiveWritingSession = () => invoke<WritingSession | null>("get_active_writing_session");
+
+export const listWritingSessions = (startDate: string, endDate: string, bookId?: string) =>
+  invoke<WritingSession[]>("list_writing_sessions", { startDate, endDate, bookId });
+
 // ============================================================================
 // 快照 & 回收站
 // ============================================================================
@@ -36,7 +36,7 @@ interface EditorState {
   // 章节
   fetchChapters: (volumeId: string) => Promise<void>;
   openChapter: (id: string) => Promise<void>;
-  saveChapter: (content: string) => Promise<void>;
+  saveChapter: (content: string, source?: string) => Promise<void>;
   addChapter: (volumeId: string, name: string) => Promise<Chapter>;
   renameChapter: (id: string, name: string) => Promise<void>;
   setChapterStatus: (id: string, status: string) => Promise<void>;
@@ -154,11 +154,11 @@ export const useEditorStore = create<EditorState>((set, get) => ({
     });
   },
 
-  saveChapter: async (content) => {
+  saveChapter: async (content, source) => {
     const { storagePath, currentChapterId } = get();
     if (!storagePath || !currentChapterId) return;
     const wordCount = content.length;
-    await api.updateChapter(storagePath, currentChapterId, content);
+    await api.updateChapter(storagePath, currentChapterId, content, source);
     set((s) => ({
       dirty: false,
       tabs: s.tabs.map((t) =>
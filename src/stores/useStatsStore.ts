@@ -36,7 +36,10 @@ export const useStatsStore = create<StatsState>((set, get) => ({
   },
 
   recordWords: async (date, wordCountDelta, durationDelta) => {
-    await api.updateDailyStats(date, wordCountDelta, durationDelta);
+    // 字数已由保存章节时的写作日志计入，这里只同步界面显示
+    if (durationDelta !== 0) {
+      await api.updateDailyStats(date, durationDelta);
+    }
     const today = new Date().toISOString().slice(0, 10);
     if (date === today) {
       set((s) => ({
@@ -40,6 +40,7 @@ export interface Volume {
   id: string;
   name: string;
   sort_order: number;
+  copied_from: string | null;
   created_at: string;
 }
 
@@ -50,13 +51,69 @@ export interface Chapter {
   content: string;
   l2_summary: string | null;
   l3_title: string | null;
-  status: 'draft' | 'complete' | 'dirty';
+  /** 内置状态或自定义状态 key（custom:xxxxxxxx） */
+  status: 'draft' | 'revising' | 'complete' | 'dirty' | 'published' | (string & {});
   word_count: number;
   sort_order: number;
+  scheduled_at: string | null;
+  published_at: string | null;
+  copied_from: string | null;
   created_at: string;
   updated_at: string;
 }
 
+export interface RestructureResult {
+  /** 拆分出的新章节，或合并后的章节 */
+  chapter: Chapter;
+  /** 回收站中的还原记录，恢复即撤销 */
+  trash_id: string;
+}
+
+export interface ChapterStatusDef {
+  key: string;
+  label: string;
+  color: string;
+  builtin: boolean;
+  sort_order: number;
+}
+
+export interface ChapterStatusChange {
+  id: string;
+  chapter_id: string;
+  chapter_name: string | null;
+  from_status: string | null;
+  to_status: string;
+  source: 'user' | 'system';
+  created_at: string;
+}
+
+export interface PublishPlan {
+  release_times: string[];
+  buffer_threshold: number;
+  updated_at: string | null;
+}
+
+export interface ScheduledChapter {
+  id: string;
+  volume_id: string;
+  name: string;
+  status: Chapter["status"];
+  word_count: number;
+  scheduled_at: string | null;
+  published_at: string | null;
+}
+
+export interface BufferReport {
+  buffer_chapters: number;
+  buffer_words: number;
+  chapters_per_day: number;
+  buffer_days: number | null;
+  threshold: number;
+  low: boolean;
+  overdue: ScheduledChapter[];
+  next: ScheduledChapter | null;
+}
+
 export interface Entity {
   id: string;
   name: string;
@@ -88,6 +145,41 @@ export interface DailyStat {
   daily_goal: number;
 }
 
+export interface DailyGoalRule {
+  scope: string;
+  weekday: number;
+  goal: number;
+  updated_at: string;
+}
+
+export interface WritingSession {
+  id: string;
+  book_id: string | null;
+  chapter_id: string | null;
+  started_at: string;
+  ended_at: string | null;
+  last_activity_at: string;
+  duration_seconds: number;
+  words_added: number;
+  words_deleted: number;
+  end_reason: "manual" | "idle" | null;
+  pomodoro_work_minutes: number;
+  pomodoro_break_minutes: number;
+}
+
+export interface WritingAnalytics {
+  current_streak: number;
+  longest_streak: number;
+  total_words: number;
+  total_duration_seconds: number;
+  words_per_hour: number | null;
+  avg_7d: number;
+  avg_30d: number;
+  heatmap: { date: string; word_count: number; goal: number; goal_met: boolean; level: number }[];
+  rolling: { date: string; avg_7d: number; avg_30d: number }[];
+  by_hour: { hour: number; word_count: number; duration_seconds: number }[];
+}
+
 export interface Snapshot {
   id: string;
   chapter_id: string;